@@ -1,4 +1,6 @@
 use std::env;
+use std::fs;
+use std::path::Path;
 
 const WINDOWS_TABLES: &'static [&'static str] = &[
     "etc_hosts",
@@ -49,6 +51,7 @@ const LINUX_TABLES: &'static [&'static str] = &[
     "interface_details",
     "system_info",
     "os_version",
+    "logical_drives",
     "uptime",
     "processes",
     "process_open_sockets",
@@ -58,7 +61,8 @@ const LINUX_TABLES: &'static [&'static str] = &[
     "groups",
     "users",
     "logged_in_users",
-    "proxies"
+    "proxies",
+    "file_events"
 ];
 
 const MACOS_TABLES: &'static [&'static str] = &[
@@ -85,6 +89,41 @@ fn emit_features(features: &'static [&'static str]) {
     }
 }
 
+/// Writes `TABLE_PLATFORMS`, the platforms implementing each table, for `siquery_tables`.
+fn write_table_platforms() {
+    let mut tables: Vec<&str> = WINDOWS_TABLES.iter().chain(LINUX_TABLES.iter()).chain(MACOS_TABLES.iter())
+        .cloned()
+        .collect();
+    tables.sort();
+    tables.dedup();
+    let mut code = String::from("pub const TABLE_PLATFORMS: &[(&str, &str)] = &[\n");
+    for table in tables {
+        let mut platforms = Vec::new();
+        if LINUX_TABLES.contains(&table) {
+            platforms.push("linux");
+        }
+        if MACOS_TABLES.contains(&table) {
+            platforms.push("macos");
+        }
+        if WINDOWS_TABLES.contains(&table) {
+            platforms.push("windows");
+        }
+        code.push_str(&format!("    (\"{}\", \"{}\"),\n", table, platforms.join(",")));
+    }
+    code.push_str("];\n");
+    let out_dir = env::var("OUT_DIR").unwrap();
+    fs::write(Path::new(&out_dir).join("table_platforms.rs"), code).unwrap();
+}
+
+/// Passes the VERSION and REVISION files of the repository to the crate.
+fn emit_build_info() {
+    for (file, var) in [("../VERSION", "SIQUERY_VERSION"), ("../REVISION", "SIQUERY_REVISION")].iter() {
+        let value = fs::read_to_string(file).map(|value| value.trim().to_string()).unwrap_or_default();
+        println!("cargo:rustc-env={}={}", var, value);
+        println!("cargo:rerun-if-changed={}", file);
+    }
+}
+
 fn main() {
     let target = env::var("TARGET").unwrap();
     let target: Vec<_> = target.split('-').collect();
@@ -103,5 +142,7 @@ fn main() {
             _ => {}
         }
     }
+    write_table_platforms();
+    emit_build_info();
     println!("cargo:rerun-if-changed=build.rs");
 }
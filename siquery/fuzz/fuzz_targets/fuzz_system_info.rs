@@ -30,6 +30,9 @@ cfg_if! {
             fn cpu_count(&self) -> u32 {
                 self.u
             }
+            fn dmi_attribute(&self, _name: &str) -> Option<String> {
+                Some(self.s.to_string())
+            }
         }
     }
 }
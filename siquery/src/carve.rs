@@ -0,0 +1,598 @@
+//! File carving: collects files into a zstd compressed tar archive with a manifest.
+//!
+//! Files are stored under `files/` with their absolute path, and `manifest.json`, the last
+//! entry, records the original path, size, modification time and SHA-256 of every file, along
+//! with the files left out and why. The `carves` table lists the manifests of the archives
+//! found in the carve directory, and carves the files its `path` constraints match when the
+//! query has `carve = 1`, as osquery does.
+
+use rusqlite::Connection;
+use rusqlite::types::Value;
+use serde_json::Value as serdValue;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Component, Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::events::unix_time;
+use crate::options::get_options;
+use crate::profile::record_warning;
+use crate::query::{execute_query_json, Constraint, ConstraintOp};
+use crate::tables::CarvesRow;
+
+pub const MANIFEST_NAME: &str = "manifest.json";
+const ARCHIVE_EXTENSION: &str = ".tar.zst";
+
+/// Bounds of a carve. Files past either limit are skipped and listed in the manifest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CarveLimits {
+    pub max_files: usize,
+    /// Sum of the sizes of the carved files, before compression.
+    pub max_bytes: u64,
+}
+
+impl Default for CarveLimits {
+    fn default() -> CarveLimits {
+        CarveLimits {
+            max_files: 1000,
+            max_bytes: 100 * 1024 * 1024,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CarvedFile {
+    pub path: String,
+    /// Name of the file in the archive.
+    pub archive_path: String,
+    pub size: u64,
+    /// Modification time in seconds since the epoch.
+    pub mtime: i64,
+    pub sha256: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct SkippedFile {
+    pub path: String,
+    pub reason: String,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CarveManifest {
+    /// Creation time in seconds since the epoch.
+    pub time: i64,
+    /// Query the paths came from, if any.
+    pub query: Option<String>,
+    pub max_files: usize,
+    pub max_bytes: u64,
+    pub files: Vec<CarvedFile>,
+    pub skipped: Vec<SkippedFile>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Query(rusqlite::Error),
+    Manifest(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Query(e) => write!(f, "{}", e),
+            Error::Manifest(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Error {
+        Error::Query(e)
+    }
+}
+
+/// Limits of carves started from the `carves` table.
+pub fn carve_limits() -> CarveLimits {
+    let options = get_options();
+    let defaults = CarveLimits::default();
+    CarveLimits {
+        max_files: options.carve_max_files.unwrap_or(defaults.max_files),
+        max_bytes: options.carve_max_bytes.unwrap_or(defaults.max_bytes),
+    }
+}
+
+/// Directory of the `carves` table and of archives written without an explicit path.
+pub fn carve_dir() -> PathBuf {
+    get_options().carve_dir
+        .unwrap_or_else(|| crate::history::default_history_path().with_file_name("carves"))
+}
+
+/// New archive path in the carve directory, named after the current time.
+pub fn default_archive_path() -> PathBuf {
+    let dir = carve_dir();
+    let time = unix_time();
+    let mut path = dir.join(format!("carve_{}{}", time, ARCHIVE_EXTENSION));
+    let mut index = 1;
+    while path.exists() {
+        path = dir.join(format!("carve_{}_{}{}", time, index, ARCHIVE_EXTENSION));
+        index += 1;
+    }
+    path
+}
+
+/// Name of a file in the archive: its absolute path under `files/`, without root or drive prefix.
+///
+/// `..` components are resolved lexically, as tar refuses them, and never climb above `files/`.
+fn archive_path(path: &Path) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    for component in path.components() {
+        match component {
+            Component::Prefix(prefix) => {
+                let prefix = prefix.as_os_str().to_string_lossy();
+                parts.push(prefix.trim_end_matches(':').replace(['\\', '?'], ""));
+            },
+            Component::RootDir | Component::CurDir => {},
+            Component::ParentDir => {
+                parts.pop();
+            },
+            Component::Normal(part) => parts.push(part.to_string_lossy().to_string()),
+        }
+    }
+    parts.insert(0, String::from("files"));
+    parts.join("/")
+}
+
+fn mtime(metadata: &fs::Metadata) -> i64 {
+    metadata.modified().ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+type ArchiveBuilder = tar::Builder<zstd::Encoder<'static, File>>;
+
+fn append<R: Read>(builder: &mut ArchiveBuilder, name: &str, size: u64, data: R, mtime: i64) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(size);
+    header.set_mode(0o644);
+    header.set_mtime(mtime.max(0) as u64);
+    header.set_entry_type(tar::EntryType::Regular);
+    builder.append_data(&mut header, name, data)
+}
+
+/// Reads exactly `remaining` bytes of a file into the archive and hashes them on the way.
+///
+/// A file that grew since its size was taken is cut at that size, and one that shrank is padded
+/// with zeros, so the size in the tar header and the hash match what is stored.
+struct HashingReader {
+    file: File,
+    remaining: u64,
+    hasher: Sha256,
+}
+
+impl Read for HashingReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.remaining as usize);
+        if len == 0 {
+            return Ok(0);
+        }
+        let read = match self.file.read(&mut buf[..len])? {
+            0 => {
+                buf[..len].iter_mut().for_each(|byte| *byte = 0);
+                len
+            },
+            read => read,
+        };
+        self.hasher.update(&buf[..read]);
+        self.remaining -= read as u64;
+        Ok(read)
+    }
+}
+
+/// Opens a file to carve, or returns why it is skipped given the `count` files and `total`
+/// bytes carved so far.
+fn open_file(path: &str, limits: CarveLimits, count: usize, total: u64) -> Result<(File, fs::Metadata), String> {
+    let file = File::open(path).map_err(|e| e.to_string())?;
+    let metadata = file.metadata().map_err(|e| e.to_string())?;
+    if !metadata.is_file() {
+        return Err("not a regular file".to_string());
+    }
+    if count >= limits.max_files {
+        return Err(format!("file count limit of {} reached", limits.max_files));
+    }
+    if total + metadata.len() > limits.max_bytes {
+        return Err(format!("size limit of {} bytes reached", limits.max_bytes));
+    }
+    Ok((file, metadata))
+}
+
+/// Streams a file into the archive, hashing it at the same time.
+fn append_file(builder: &mut ArchiveBuilder, path: &str, file: File, metadata: &fs::Metadata)
+               -> io::Result<CarvedFile> {
+    let archive_path = archive_path(Path::new(path));
+    let mut reader = HashingReader { file, remaining: metadata.len(), hasher: Sha256::new() };
+    append(builder, &archive_path, metadata.len(), &mut reader, mtime(metadata))?;
+    Ok(CarvedFile {
+        path: path.to_string(),
+        archive_path,
+        size: metadata.len(),
+        mtime: mtime(metadata),
+        sha256: format!("{:x}", reader.hasher.finalize()),
+    })
+}
+
+/// Writes the files of `paths` to a new tar.zst archive at `archive`.
+///
+/// Files that cannot be opened, are not regular files or exceed the limits are skipped rather
+/// than failing the carve. Files are streamed with the size they had when opened, see
+/// `HashingReader`. A carve that fails removes its partial archive.
+pub fn carve(paths: &[String], archive: &Path, limits: CarveLimits, query: Option<&str>)
+             -> Result<CarveManifest, Error> {
+    if let Some(parent) = archive.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let result = write_archive(paths, File::create(archive)?, limits, query);
+    if result.is_err() {
+        let _ = fs::remove_file(archive);
+    }
+    result
+}
+
+fn write_archive(paths: &[String], archive: File, limits: CarveLimits, query: Option<&str>)
+                 -> Result<CarveManifest, Error> {
+    let encoder = zstd::Encoder::new(archive, 0)?;
+    let mut builder = tar::Builder::new(encoder);
+    let mut manifest = CarveManifest {
+        time: unix_time(),
+        query: query.map(|query| query.to_string()),
+        max_files: limits.max_files,
+        max_bytes: limits.max_bytes,
+        ..CarveManifest::default()
+    };
+    let mut total: u64 = 0;
+    let mut seen = HashSet::new();
+    for path in paths.iter().filter(|path| !path.is_empty()) {
+        if !seen.insert(path.as_str()) {
+            continue;
+        }
+        match open_file(path, limits, manifest.files.len(), total) {
+            Ok((file, metadata)) => {
+                let file = append_file(&mut builder, path, file, &metadata)?;
+                total += file.size;
+                manifest.files.push(file);
+            },
+            Err(reason) => manifest.skipped.push(SkippedFile { path: path.clone(), reason }),
+        }
+    }
+    let json = serde_json::to_vec_pretty(&manifest).map_err(|e| Error::Manifest(e.to_string()))?;
+    append(&mut builder, MANIFEST_NAME, json.len() as u64, json.as_slice(), manifest.time)?;
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+/// Paths from the rows of `query`: its `path` column, or its first column.
+pub fn query_paths(db: &Connection, query: &str) -> Result<Vec<String>, Error> {
+    let statement = db.prepare(query)?;
+    let names: Vec<String> = statement.column_names().iter().map(|name| name.to_string()).collect();
+    if names.is_empty() {
+        return Ok(Vec::new());
+    }
+    let column = names.iter().position(|name| name == "path").unwrap_or(0);
+    let rows = execute_query_json(db, query, &[])?;
+    Ok(rows.iter()
+        .filter_map(|row| match row.get(&names[column]) {
+            Some(serdValue::String(path)) => Some(path.clone()),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Carves the files whose paths `query` returns.
+pub fn carve_query(db: &Connection, query: &str, archive: &Path, limits: CarveLimits) -> Result<CarveManifest, Error> {
+    let paths = query_paths(db, query)?;
+    carve(&paths, archive, limits, Some(query))
+}
+
+/// Reads the manifest of an archive written by `carve`.
+pub fn read_manifest(archive: &Path) -> Result<CarveManifest, Error> {
+    let mut tar = tar::Archive::new(zstd::Decoder::new(File::open(archive)?)?);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.as_os_str() == MANIFEST_NAME {
+            let mut json = Vec::new();
+            entry.read_to_end(&mut json)?;
+            return serde_json::from_slice(&json).map_err(|e| Error::Manifest(format!("{}: {}", archive.display(), e)));
+        }
+    }
+    Err(Error::Manifest(format!("{}: no {}", archive.display(), MANIFEST_NAME)))
+}
+
+/// Matches `text` against a pattern where `*` stands for any characters and `?` for one.
+fn wildcard_match(pattern: &[char], text: &[char], ignore_case: bool) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| wildcard_match(rest, &text[skip..], ignore_case)),
+        Some((c, rest)) => match text.split_first() {
+            Some((t, text)) if *c == '?' || c == t || (ignore_case && c.eq_ignore_ascii_case(t)) =>
+                wildcard_match(rest, text, ignore_case),
+            _ => false,
+        },
+    }
+}
+
+/// Pattern of a `path LIKE` or `path GLOB` constraint in `*` and `?` wildcards, and whether it ignores ASCII case.
+fn path_pattern(constraint: &Constraint) -> Option<(Vec<char>, bool)> {
+    let value = match constraint.value {
+        Value::Text(ref value) => value,
+        _ => return None,
+    };
+    match constraint.op {
+        ConstraintOp::Glob => Some((value.chars().collect(), false)),
+        ConstraintOp::Like => Some((value.chars().map(|c| match c {
+            '%' => '*',
+            '_' => '?',
+            c => c,
+        }).collect(), true)),
+        _ => None,
+    }
+}
+
+/// Files matching a path pattern. Wildcards match within one path component, as in a shell.
+fn expand_pattern(pattern: &[char], ignore_case: bool) -> Vec<PathBuf> {
+    let pattern: String = pattern.iter().collect();
+    let mut paths = vec![PathBuf::new()];
+    for component in Path::new(&pattern).components() {
+        let part = component.as_os_str().to_string_lossy();
+        if !part.contains(&['*', '?'][..]) {
+            paths.iter_mut().for_each(|path| path.push(component.as_os_str()));
+            continue;
+        }
+        let part: Vec<char> = part.chars().collect();
+        let mut matched = Vec::new();
+        for dir in paths.iter() {
+            let entries = match fs::read_dir(if dir.as_os_str().is_empty() { Path::new(".") } else { dir }) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            let mut names: Vec<String> = entries.filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().into_owned())
+                .filter(|name| wildcard_match(&part, &name.chars().collect::<Vec<char>>(), ignore_case))
+                .collect();
+            names.sort();
+            matched.extend(names.into_iter().map(|name| dir.join(name)));
+        }
+        paths = matched;
+    }
+    paths.into_iter().filter(|path| path.is_file()).collect()
+}
+
+/// Paths matching every `path` constraint: the `path =` one, or else the files of the first
+/// pattern that does not start with a wildcard, kept when the other patterns match them too.
+fn constrained_paths(constraints: &[Constraint]) -> Vec<String> {
+    let path_constraints = || constraints.iter().filter(|constraint| constraint.column == "path");
+    let patterns: Vec<(Vec<char>, bool)> = path_constraints().filter_map(path_pattern).collect();
+    let equal = path_constraints().find(|constraint| constraint.op == ConstraintOp::Eq);
+    let candidates = match equal.map(|constraint| &constraint.value) {
+        Some(Value::Text(path)) => vec![PathBuf::from(path)],
+        Some(_) => return Vec::new(),
+        None => match patterns.iter().find(|(pattern, _)| !pattern.is_empty() && pattern[0] != '*' && pattern[0] != '?') {
+            Some((pattern, ignore_case)) => expand_pattern(pattern, *ignore_case),
+            None => return Vec::new(),
+        },
+    };
+    candidates.into_iter()
+        .map(|path| path.display().to_string())
+        .filter(|path| {
+            let path: Vec<char> = path.chars().collect();
+            patterns.iter().all(|(pattern, ignore_case)| wildcard_match(pattern, &path, *ignore_case))
+        })
+        .collect()
+}
+
+fn constraint_sql(constraint: &Constraint) -> String {
+    let value = match constraint.value {
+        Value::Text(ref text) => format!("'{}'", text.replace('\'', "''")),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        _ => "NULL".to_string(),
+    };
+    format!("{} {} {}", constraint.column, constraint.op.sql(), value)
+}
+
+fn manifest_rows(archive: &Path, manifest: CarveManifest) -> Vec<CarvesRow> {
+    let carve = archive.display().to_string();
+    let time = manifest.time;
+    manifest.files.into_iter().map(|file| CarvesRow {
+        time,
+        carve: carve.clone(),
+        path: file.path,
+        size: file.size as i64,
+        mtime: file.mtime,
+        sha256: file.sha256,
+    }).collect()
+}
+
+impl CarvesRow {
+    /// Carves the files matching the `path` constraints into a new archive of the carve directory
+    /// when the query has `carve = 1`, and lists that archive. Lists every archive otherwise.
+    ///
+    /// `LIKE` and `GLOB` patterns are expanded on the file system, their wildcards matching
+    /// within one path component.
+    pub(crate) fn get_constrained(constraints: &[Constraint]) -> Vec<CarvesRow> {
+        let start = constraints.iter().any(|constraint| constraint.column == "carve" && match constraint.value {
+            Value::Integer(1) => true,
+            Value::Text(ref text) => text == "1",
+            _ => false,
+        });
+        if !start {
+            return CarvesRow::get_specific();
+        }
+        let paths = constrained_paths(constraints);
+        if paths.is_empty() {
+            record_warning("carves", "carve = 1 matched no file, give existing paths with path =, LIKE or GLOB");
+            return Vec::new();
+        }
+        let query: Vec<String> = constraints.iter().map(constraint_sql).collect();
+        let query = format!("SELECT * FROM carves WHERE {}", query.join(" AND "));
+        let archive = default_archive_path();
+        match carve(&paths, &archive, carve_limits(), Some(&query)) {
+            Ok(manifest) => {
+                for file in manifest.skipped.iter() {
+                    record_warning("carves", &format!("skipped {}: {}", file.path, file.reason));
+                }
+                manifest_rows(&archive, manifest)
+            },
+            Err(e) => {
+                record_warning("carves", &format!("{}: {}", archive.display(), e));
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn get_specific() -> Vec<CarvesRow> {
+        let mut archives: Vec<PathBuf> = match fs::read_dir(carve_dir()) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.to_string_lossy().ends_with(ARCHIVE_EXTENSION))
+                .collect(),
+            Err(_) => return Vec::new(),
+        };
+        archives.sort();
+        let mut rows = Vec::new();
+        for archive in archives.iter() {
+            if let Ok(manifest) = read_manifest(archive) {
+                rows.extend(manifest_rows(archive, manifest));
+            }
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::init_db;
+
+    #[test]
+    fn test_archive_path() {
+        assert_eq!(archive_path(Path::new("/etc/hosts")), "files/etc/hosts");
+        assert_eq!(archive_path(Path::new("relative/file")), "files/relative/file");
+        assert_eq!(archive_path(Path::new("/etc/ssh/../hosts")), "files/etc/hosts");
+        assert_eq!(archive_path(Path::new("../../etc/./hosts")), "files/etc/hosts");
+    }
+
+    #[test]
+    fn test_hashing_reader() {
+        let path = std::env::temp_dir().join(format!("siquery_hashing_{}", std::process::id()));
+        fs::write(&path, "0123456789").unwrap();
+        let read = |size: u64| {
+            let mut reader = HashingReader { file: File::open(&path).unwrap(), remaining: size, hasher: Sha256::new() };
+            let mut data = Vec::new();
+            reader.read_to_end(&mut data).unwrap();
+            assert_eq!(format!("{:x}", reader.hasher.finalize()), format!("{:x}", Sha256::digest(&data)));
+            data
+        };
+        assert_eq!(read(4), b"0123");
+        assert_eq!(read(12), b"0123456789\0\0");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_carve() {
+        let dir = std::env::temp_dir().join(format!("siquery_carve_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let file = |name: &str, content: &str| {
+            let path = dir.join(name);
+            fs::write(&path, content).unwrap();
+            path.display().to_string()
+        };
+        let (a, b, c) = (file("a.txt", "hello"), file("b.txt", "0123456789"), file("c.txt", "!"));
+        let missing = dir.join("missing").display().to_string();
+        let archive = dir.join("out").join("test.tar.zst");
+        let c_parent = dir.join("out").join("..").join("c.txt").display().to_string();
+        let paths = vec![a.clone(), a.clone(), missing.clone(), dir.display().to_string(), b.clone(), c_parent.clone()];
+
+        let limits = CarveLimits { max_files: 10, max_bytes: 8 };
+        let manifest = carve(&paths, &archive, limits, None).unwrap();
+        let carved: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(carved, vec![a.as_str(), c_parent.as_str()]);
+        assert_eq!(manifest.files[1].archive_path, archive_path(Path::new(&c)));
+        assert_eq!(manifest.files[0].size, 5);
+        assert_eq!(manifest.files[0].sha256, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert!(manifest.files[0].mtime > 0);
+        let skipped: Vec<&str> = manifest.skipped.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(skipped, vec![missing.as_str(), &dir.display().to_string(), b.as_str()]);
+        assert!(manifest.skipped[2].reason.contains("size limit"));
+        assert_eq!(read_manifest(&archive).unwrap(), manifest);
+
+        let mut tar = tar::Archive::new(zstd::Decoder::new(File::open(&archive).unwrap()).unwrap());
+        let mut entries = tar.entries().unwrap();
+        let mut first = entries.next().unwrap().unwrap();
+        assert_eq!(first.path().unwrap().display().to_string(), manifest.files[0].archive_path);
+        let mut content = String::new();
+        first.read_to_string(&mut content).unwrap();
+        assert_eq!(content, "hello");
+
+        let limits = CarveLimits { max_files: 1, max_bytes: 1000 };
+        let db = init_db();
+        let query = format!("select '{}' as path union all select '{}'", b, c);
+        let manifest = carve_query(&db, &query, &dir.join("query.tar.zst"), limits).unwrap();
+        assert_eq!(manifest.files.len(), 1);
+        assert_eq!(manifest.query.as_ref(), Some(&query));
+        assert!(manifest.skipped[0].reason.contains("file count limit"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wildcard_match() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        assert!(wildcard_match(&chars("/tmp/*.txt"), &chars("/tmp/a.txt"), false));
+        assert!(wildcard_match(&chars("/tmp/?.TXT"), &chars("/tmp/a.txt"), true));
+        assert!(!wildcard_match(&chars("/tmp/?.TXT"), &chars("/tmp/a.txt"), false));
+        assert!(!wildcard_match(&chars("/tmp/?.txt"), &chars("/tmp/ab.txt"), false));
+    }
+
+    #[test]
+    fn test_carves_table() {
+        let dir = std::env::temp_dir().join(format!("siquery_carves_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("logs")).unwrap();
+        fs::write(dir.join("a.txt"), "hello").unwrap();
+        fs::write(dir.join("b.log"), "world").unwrap();
+        fs::write(dir.join("logs").join("c.txt"), "!").unwrap();
+        fs::write(dir.join("logs").join("d.txt"), "?").unwrap();
+        crate::options::set_carve_dir(Some(dir.join("out")));
+
+        let db = init_db();
+        let query = format!("select path from carves where path like '{}' and carve = 1", dir.join("%.txt").display());
+        let rows = execute_query_json(&db, &query, &[]).unwrap();
+        let paths: Vec<&str> = rows.iter().map(|row| row["path"].as_str().unwrap()).collect();
+        assert_eq!(paths, vec![dir.join("a.txt").display().to_string()]);
+
+        let query = format!("select path from carves where path glob '{}' and path like '%/c.txt' and carve = 1", dir.join("*").join("*.txt").display());
+        let rows = execute_query_json(&db, &query, &[]).unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["path"], dir.join("logs").join("c.txt").display().to_string());
+
+        let query = format!("select path from carves where path = '{}' and carve = 1", dir.join("b.log").display());
+        assert_eq!(execute_query_json(&db, &query, &[]).unwrap().len(), 1);
+
+        let rows = execute_query_json(&db, "select carve from carves", &[]).unwrap();
+        assert_eq!(rows.len(), 3);
+        crate::options::set_carve_dir(None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
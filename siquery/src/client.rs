@@ -0,0 +1,255 @@
+use rusqlite::{Connection, NO_PARAMS};
+use rusqlite::types::{FromSql, Value, ValueRef};
+use serde::de::{self, DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use std::fmt;
+use std::rc::Rc;
+use std::vec::IntoIter;
+
+use crate::query::init_db;
+
+#[derive(Debug)]
+pub enum Error {
+    Sqlite(rusqlite::Error),
+    Deserialize(String),
+    NoSuchColumn(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Sqlite(e) => write!(f, "{}", e),
+            Error::Deserialize(msg) => write!(f, "{}", msg),
+            Error::NoSuchColumn(name) => write!(f, "no such column: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl de::Error for Error {
+    fn custom<T: fmt::Display>(msg: T) -> Error {
+        Error::Deserialize(msg.to_string())
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Error {
+        Error::Sqlite(e)
+    }
+}
+
+/// Library entry point that returns query results as typed values instead of printing them.
+///
+/// ```no_run
+/// use siquery::Client;
+/// use siquery::tables::ProcessesRow;
+///
+/// let client = Client::new();
+/// let processes: Vec<ProcessesRow> = client.query("select * from processes").unwrap();
+/// ```
+pub struct Client {
+    db: Connection,
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client { db: init_db() }
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.db
+    }
+
+    /// Runs `sql` and deserializes every row into `T`, matching columns to fields by name.
+    pub fn query<T: DeserializeOwned>(&self, sql: &str) -> Result<Vec<T>> {
+        self.query_rows(sql)?.map(|row| row.deserialize()).collect()
+    }
+
+    /// Runs `sql` and returns its rows with their column names.
+    pub fn query_rows(&self, sql: &str) -> Result<RowIter> {
+        let mut statement = self.db.prepare(sql)?;
+        let columns: Rc<Vec<String>> = Rc::new(
+            statement.column_names().iter().map(|name| name.to_string()).collect());
+        let mut response = statement.query(NO_PARAMS)?;
+        let mut rows = Vec::new();
+        while let Some(res) = response.next()? {
+            let mut values = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                values.push(res.get::<usize, Value>(i)?);
+            }
+            rows.push(Row { columns: columns.clone(), values });
+        }
+        Ok(RowIter { rows: rows.into_iter() })
+    }
+}
+
+pub struct RowIter {
+    rows: IntoIter<Row>,
+}
+
+impl Iterator for RowIter {
+    type Item = Row;
+
+    fn next(&mut self) -> Option<Row> {
+        self.rows.next()
+    }
+}
+
+/// One result row, owning its values.
+#[derive(Clone, Debug)]
+pub struct Row {
+    columns: Rc<Vec<String>>,
+    values: Vec<Value>,
+}
+
+impl Row {
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn value(&self, column: &str) -> Option<&Value> {
+        self.columns.iter().position(|c| c == column).map(|i| &self.values[i])
+    }
+
+    /// Converts a column using rusqlite's `FromSql` rules.
+    pub fn get<T: FromSql>(&self, column: &str) -> Result<T> {
+        let value = self.value(column).ok_or_else(|| Error::NoSuchColumn(column.to_string()))?;
+        T::column_result(ValueRef::from(value)).map_err(|e| Error::Deserialize(format!("{}: {}", column, e)))
+    }
+
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<T> {
+        T::deserialize(RowDeserializer { row: self })
+    }
+}
+
+struct RowDeserializer<'a> {
+    row: &'a Row,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for RowDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_map(RowMapAccess { row: self.row, index: 0 })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+struct RowMapAccess<'a> {
+    row: &'a Row,
+    index: usize,
+}
+
+impl<'de, 'a> MapAccess<'de> for RowMapAccess<'a> {
+    type Error = Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(&mut self, seed: K) -> Result<Option<K::Value>> {
+        match self.row.columns.get(self.index) {
+            Some(name) => seed.deserialize(name.as_str().into_deserializer()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value> {
+        let value = &self.row.values[self.index];
+        self.index += 1;
+        seed.deserialize(ValueDeserializer { value })
+    }
+}
+
+struct ValueDeserializer<'a> {
+    value: &'a Value,
+}
+
+impl<'de, 'a> de::Deserializer<'de> for ValueDeserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Real(f) => visitor.visit_f64(*f),
+            Value::Text(s) => visitor.visit_str(s),
+            Value::Blob(b) => visitor.visit_bytes(b),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Integer(i) => visitor.visit_bool(*i != 0),
+            Value::Text(s) if s == "true" || s == "false" => visitor.visit_bool(s == "true"),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(self, _name: &'static str, visitor: V) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    serde::forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple
+        tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::Dummy;
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct Named {
+        name: String,
+        size: Option<u32>,
+        enabled: bool,
+        ratio: f64,
+    }
+
+    #[test]
+    fn test_query_table_struct() {
+        let client = Client::new();
+        let rows: Vec<Dummy> = client.query("select * from Dummy").unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].a, 25);
+        assert_eq!(rows[1].b, 25);
+    }
+
+    #[test]
+    fn test_query_typed_values() {
+        let client = Client::new();
+        let rows: Vec<Named> = client.query(
+            "select 'eth0' as name, null as size, 1 as enabled, 0.5 as ratio").unwrap();
+        assert_eq!(rows[0], Named { name: "eth0".to_string(), size: None, enabled: true, ratio: 0.5 });
+        assert!(client.query::<Dummy>("select 'x' as a, 1 as b").is_err());
+    }
+
+    #[test]
+    fn test_query_rows() {
+        let client = Client::new();
+        let mut rows = client.query_rows("select a, b from Dummy limit 1").unwrap();
+        let row = rows.next().unwrap();
+        assert_eq!(row.columns(), &["a".to_string(), "b".to_string()]);
+        assert_eq!(row.get::<i64>("a").unwrap(), 25);
+        assert!(row.get::<i64>("c").is_err());
+        assert!(rows.next().is_none());
+    }
+}
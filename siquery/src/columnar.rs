@@ -0,0 +1,296 @@
+use arrow::array::{ArrayRef, BinaryBuilder, Float64Builder, Int64Builder, StringBuilder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::errors::ParquetError;
+use parquet::file::properties::WriterProperties;
+use rusqlite::{Connection, Rows};
+use rusqlite::types::Value;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::query::query_with_params;
+
+/// Rows converted to one record batch at a time.
+const BATCH_SIZE: usize = 65_536;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ColumnarFormat {
+    Parquet,
+    /// Arrow IPC file format.
+    Arrow,
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Sqlite(rusqlite::Error),
+    Arrow(ArrowError),
+    Parquet(ParquetError),
+    Io(io::Error),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Sqlite(e) => write!(f, "{}", e),
+            Error::Arrow(e) => write!(f, "{}", e),
+            Error::Parquet(e) => write!(f, "{}", e),
+            Error::Io(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Error {
+        Error::Sqlite(e)
+    }
+}
+
+impl From<ArrowError> for Error {
+    fn from(e: ArrowError) -> Error {
+        Error::Arrow(e)
+    }
+}
+
+impl From<ParquetError> for Error {
+    fn from(e: ParquetError) -> Error {
+        Error::Parquet(e)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+/// Maps a column type declared by a table schema (see `get_columns_type`) to an Arrow type.
+fn declared_type(decl_type: &str) -> Option<DataType> {
+    match decl_type.to_uppercase().as_str() {
+        "INTEGER" => Some(DataType::Int64),
+        "REAL" => Some(DataType::Float64),
+        "TEXT" => Some(DataType::Utf8),
+        "BLOB" => Some(DataType::Binary),
+        _ => None,
+    }
+}
+
+/// Picks a type for an expression column from the first non-null value.
+fn inferred_type(rows: &[Vec<Value>], index: usize) -> DataType {
+    let value = rows.iter().map(|row| &row[index]).find(|value| **value != Value::Null);
+    match value {
+        Some(Value::Integer(_)) => DataType::Int64,
+        Some(Value::Real(_)) => DataType::Float64,
+        Some(Value::Blob(_)) => DataType::Binary,
+        _ => DataType::Utf8,
+    }
+}
+
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Integer(i) => Some(i.to_string()),
+        Value::Real(f) => Some(f.to_string()),
+        Value::Text(s) => Some(s.clone()),
+        Value::Blob(b) => Some(String::from_utf8_lossy(b).into_owned()),
+    }
+}
+
+/// Builds one Arrow array, converting values SQLite stored with another type when possible.
+fn build_array(data_type: &DataType, rows: &[Vec<Value>], index: usize) -> ArrayRef {
+    match data_type {
+        DataType::Int64 => {
+            let mut builder = Int64Builder::with_capacity(rows.len());
+            for row in rows {
+                builder.append_option(match &row[index] {
+                    Value::Integer(i) => Some(*i),
+                    Value::Real(f) => Some(*f as i64),
+                    Value::Text(s) => s.trim().parse().ok(),
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        },
+        DataType::Float64 => {
+            let mut builder = Float64Builder::with_capacity(rows.len());
+            for row in rows {
+                builder.append_option(match &row[index] {
+                    Value::Integer(i) => Some(*i as f64),
+                    Value::Real(f) => Some(*f),
+                    Value::Text(s) => s.trim().parse().ok(),
+                    _ => None,
+                });
+            }
+            Arc::new(builder.finish())
+        },
+        DataType::Binary => {
+            let mut builder = BinaryBuilder::new();
+            for row in rows {
+                match &row[index] {
+                    Value::Blob(b) => builder.append_value(b),
+                    Value::Null => builder.append_null(),
+                    value => builder.append_option(text(value)),
+                }
+            }
+            Arc::new(builder.finish())
+        },
+        _ => {
+            let mut builder = StringBuilder::new();
+            for row in rows {
+                builder.append_option(text(&row[index]));
+            }
+            Arc::new(builder.finish())
+        },
+    }
+}
+
+fn record_batch(schema: &SchemaRef, rows: &[Vec<Value>]) -> Result<RecordBatch, Error> {
+    let columns: Vec<ArrayRef> = schema.fields().iter().enumerate()
+        .map(|(i, field)| build_array(field.data_type(), rows, i))
+        .collect();
+    Ok(RecordBatch::try_new(schema.clone(), columns)?)
+}
+
+enum Sink {
+    Parquet(ArrowWriter<File>),
+    Arrow(FileWriter<File>),
+}
+
+impl Sink {
+    fn create(format: ColumnarFormat, path: &Path, schema: &SchemaRef) -> Result<Sink, Error> {
+        let file = File::create(path)?;
+        Ok(match format {
+            ColumnarFormat::Parquet => {
+                let props = WriterProperties::builder()
+                    .set_compression(Compression::SNAPPY)
+                    .build();
+                Sink::Parquet(ArrowWriter::try_new(file, schema.clone(), Some(props))?)
+            },
+            ColumnarFormat::Arrow => Sink::Arrow(FileWriter::try_new(file, schema)?),
+        })
+    }
+
+    fn write(&mut self, batch: &RecordBatch) -> Result<(), Error> {
+        match self {
+            Sink::Parquet(writer) => writer.write(batch)?,
+            Sink::Arrow(writer) => writer.write(batch)?,
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<(), Error> {
+        match self {
+            Sink::Parquet(writer) => {
+                writer.close()?;
+            },
+            Sink::Arrow(mut writer) => writer.finish()?,
+        }
+        Ok(())
+    }
+}
+
+fn next_rows(response: &mut Rows, column_count: usize) -> Result<Vec<Vec<Value>>, Error> {
+    let mut rows = Vec::new();
+    while rows.len() < BATCH_SIZE {
+        match response.next()? {
+            Some(row) => {
+                let mut values = Vec::with_capacity(column_count);
+                for i in 0..column_count {
+                    values.push(row.get::<usize, Value>(i)?);
+                }
+                rows.push(values);
+            },
+            None => break,
+        }
+    }
+    Ok(rows)
+}
+
+/// Runs `query` and writes its result to `path` as a typed Parquet or Arrow file.
+///
+/// Columns read from a table keep the type of its schema; other expressions take the type of
+/// their first non-null value. Returns the number of rows written.
+pub fn export_query(db: &Connection, query: &str, params: &[(String, String)],
+                    format: ColumnarFormat, path: &Path) -> Result<usize, Error> {
+    let mut statement = db.prepare(query)?;
+    let columns: Vec<(String, Option<DataType>)> = statement.columns().iter()
+        .map(|column| (column.name().to_string(), column.decl_type().and_then(declared_type)))
+        .collect();
+    let mut response = query_with_params(&mut statement, params)?;
+
+    let mut rows = next_rows(&mut response, columns.len())?;
+    let fields: Vec<Field> = columns.iter().enumerate()
+        .map(|(i, (name, data_type))| {
+            let data_type = data_type.clone().unwrap_or_else(|| inferred_type(&rows, i));
+            Field::new(name.as_str(), data_type, true)
+        })
+        .collect();
+    let schema: SchemaRef = Arc::new(Schema::new(fields));
+
+    let mut sink = Sink::create(format, path, &schema)?;
+    let mut count = 0;
+    while !rows.is_empty() {
+        count += rows.len();
+        sink.write(&record_batch(&schema, &rows)?)?;
+        rows = next_rows(&mut response, columns.len())?;
+    }
+    sink.finish()?;
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::init_db;
+    use arrow::array::{Array, Float64Array, Int64Array, StringArray};
+    use arrow::ipc::reader::FileReader;
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    const QUERY: &str = "select a, b, 'x' || a as label, null as empty, 0.5 as ratio from Dummy";
+
+    fn check_batch(batch: &RecordBatch) {
+        let schema = batch.schema();
+        assert_eq!(schema.field(0).data_type(), &DataType::Int64);
+        assert_eq!(schema.field(2).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(3).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(4).data_type(), &DataType::Float64);
+        let a = batch.column(0).as_any().downcast_ref::<Int64Array>().unwrap();
+        assert_eq!(a.value(0), 25);
+        let label = batch.column(2).as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(label.value(0), "x25");
+        assert!(batch.column(3).is_null(0));
+        let ratio = batch.column(4).as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(ratio.value(1), 0.5);
+    }
+
+    #[test]
+    fn test_export_arrow() {
+        let db = init_db();
+        let path = std::env::temp_dir().join(format!("siquery_test_{}.arrow", std::process::id()));
+        assert_eq!(export_query(&db, QUERY, &[], ColumnarFormat::Arrow, &path).unwrap(), 2);
+        let mut reader = FileReader::try_new(File::open(&path).unwrap(), None).unwrap();
+        check_batch(&reader.next().unwrap().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_export_parquet() {
+        let db = init_db();
+        let path = std::env::temp_dir().join(format!("siquery_test_{}.parquet", std::process::id()));
+        assert_eq!(export_query(&db, QUERY, &[], ColumnarFormat::Parquet, &path).unwrap(), 2);
+        let mut reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        check_batch(&reader.next().unwrap().unwrap());
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,458 @@
+//! osquery compatible views over siquery tables.
+//!
+//! siquery names some columns differently from osquery (`type_` instead of `type`, `name`
+//! instead of `device_id`, ...) and lacks others. `create_compat_views` adds TEMP views named
+//! and shaped like the osquery tables, so osquery queries run unchanged. Views shadow the siquery
+//! tables of the same name, which stay reachable as `main.<table>`. Columns siquery does not
+//! collect read as NULL.
+
+use rusqlite::{Connection, NO_PARAMS};
+
+use crate::utils::quote_identifier as quote;
+
+pub struct CompatColumn {
+    pub name: &'static str,
+    /// osquery type: TEXT, INTEGER, BIGINT, UNSIGNED_BIGINT or DOUBLE.
+    pub column_type: &'static str,
+    /// siquery column holding the value, `None` when siquery does not collect it.
+    pub source: Option<&'static str>,
+}
+
+pub struct CompatTable {
+    /// osquery table name.
+    pub name: &'static str,
+    /// siquery table the view reads from.
+    pub source: &'static str,
+    pub columns: &'static [CompatColumn],
+}
+
+const fn column(name: &'static str, column_type: &'static str, source: Option<&'static str>) -> CompatColumn {
+    CompatColumn { name, column_type, source }
+}
+
+pub const COMPAT_TABLES: &[CompatTable] = &[
+    CompatTable { name: "etc_hosts", source: "etc_hosts", columns: &[
+        column("address", "TEXT", Some("address")),
+        column("hostnames", "TEXT", Some("hostnames")),
+    ]},
+    CompatTable { name: "etc_protocols", source: "etc_protocols", columns: &[
+        column("name", "TEXT", Some("name")),
+        column("number", "INTEGER", Some("number")),
+        column("alias", "TEXT", Some("alias")),
+        column("comment", "TEXT", Some("comment")),
+    ]},
+    CompatTable { name: "etc_services", source: "etc_services", columns: &[
+        column("name", "TEXT", Some("name")),
+        column("port", "INTEGER", Some("port")),
+        column("protocol", "TEXT", Some("protocol")),
+        column("aliases", "TEXT", Some("aliases")),
+        column("comment", "TEXT", Some("comment")),
+    ]},
+    CompatTable { name: "system_info", source: "system_info", columns: &[
+        column("hostname", "TEXT", Some("computer_name")),
+        column("uuid", "TEXT", Some("uuid")),
+        column("cpu_type", "TEXT", None),
+        column("cpu_subtype", "TEXT", None),
+        column("cpu_brand", "TEXT", Some("cpu_brand")),
+        column("cpu_physical_cores", "INTEGER", Some("cpu_physical_cores")),
+        column("cpu_logical_cores", "INTEGER", Some("cpu_logical_cores")),
+        column("cpu_sockets", "INTEGER", Some("cpu_sockets")),
+        column("cpu_microcode", "TEXT", Some("cpu_microcode")),
+        column("physical_memory", "BIGINT", Some("physical_memory")),
+        column("hardware_vendor", "TEXT", Some("hardware_vendor")),
+        column("hardware_model", "TEXT", Some("hardware_model")),
+        column("hardware_version", "TEXT", Some("hardware_version")),
+        column("hardware_serial", "TEXT", Some("hardware_serial")),
+        column("board_vendor", "TEXT", Some("board_vendor")),
+        column("board_model", "TEXT", Some("board_model")),
+        column("board_version", "TEXT", Some("board_version")),
+        column("board_serial", "TEXT", Some("board_serial")),
+        column("computer_name", "TEXT", Some("computer_name")),
+        column("local_hostname", "TEXT", None),
+    ]},
+    CompatTable { name: "os_version", source: "os_version", columns: &[
+        column("name", "TEXT", Some("name")),
+        column("version", "TEXT", Some("version")),
+        column("major", "INTEGER", Some("major")),
+        column("minor", "INTEGER", Some("minor")),
+        column("patch", "INTEGER", Some("patch")),
+        column("build", "TEXT", Some("build")),
+        column("platform", "TEXT", Some("id")),
+        column("platform_like", "TEXT", Some("id_like")),
+        column("codename", "TEXT", Some("codename")),
+        column("arch", "TEXT", None),
+        column("install_date", "BIGINT", None),
+        column("revision", "INTEGER", None),
+    ]},
+    CompatTable { name: "logical_drives", source: "logical_drives", columns: &[
+        column("device_id", "TEXT", Some("name")),
+        column("type", "TEXT", Some("drive_type")),
+        column("description", "TEXT", Some("description")),
+        column("free_space", "BIGINT", Some("free_space")),
+        column("size", "BIGINT", Some("size")),
+        column("file_system", "TEXT", Some("file_system")),
+        column("boot_partition", "INTEGER", None),
+    ]},
+    CompatTable { name: "uptime", source: "uptime", columns: &[
+        column("days", "INTEGER", Some("days")),
+        column("hours", "INTEGER", Some("hours")),
+        column("minutes", "INTEGER", Some("minutes")),
+        column("seconds", "INTEGER", Some("seconds")),
+        column("total_seconds", "BIGINT", Some("total_seconds")),
+    ]},
+    CompatTable { name: "processes", source: "processes", columns: &[
+        column("pid", "BIGINT", Some("pid")),
+        column("name", "TEXT", Some("name")),
+        column("path", "TEXT", Some("path")),
+        column("cmdline", "TEXT", Some("cmdline")),
+        column("state", "TEXT", Some("state")),
+        column("cwd", "TEXT", Some("cwd")),
+        column("root", "TEXT", Some("root")),
+        column("uid", "BIGINT", Some("uid")),
+        column("gid", "BIGINT", Some("gid")),
+        column("euid", "BIGINT", Some("euid")),
+        column("egid", "BIGINT", Some("egid")),
+        column("suid", "BIGINT", Some("suid")),
+        column("sgid", "BIGINT", Some("sgid")),
+        column("on_disk", "INTEGER", Some("on_disk")),
+        column("wired_size", "BIGINT", Some("wired_size")),
+        column("resident_size", "BIGINT", Some("resident_size")),
+        column("total_size", "BIGINT", Some("total_size")),
+        column("user_time", "BIGINT", Some("user_time")),
+        column("system_time", "BIGINT", Some("system_time")),
+        column("disk_bytes_read", "BIGINT", Some("disk_bytes_read")),
+        column("disk_bytes_written", "BIGINT", Some("disk_bytes_written")),
+        column("start_time", "BIGINT", Some("start_time")),
+        column("parent", "BIGINT", Some("parent")),
+        column("pgroup", "BIGINT", Some("pgroup")),
+        column("threads", "INTEGER", Some("threads")),
+        column("nice", "INTEGER", Some("nice")),
+        column("elevated_token", "INTEGER", Some("is_elevated_token")),
+        column("elapsed_time", "BIGINT", None),
+        column("handle_count", "BIGINT", None),
+        column("percent_processor_time", "BIGINT", None),
+        column("upid", "BIGINT", None),
+        column("uppid", "BIGINT", None),
+        column("cpu_type", "INTEGER", None),
+        column("cpu_subtype", "INTEGER", None),
+        column("cgroup_path", "TEXT", None),
+    ]},
+    CompatTable { name: "interface_addresses", source: "interface_address", columns: &[
+        column("interface", "TEXT", Some("interface")),
+        column("address", "TEXT", Some("address")),
+        column("mask", "TEXT", Some("mask")),
+        column("broadcast", "TEXT", Some("broadcast")),
+        column("point_to_point", "TEXT", Some("point_to_point")),
+        column("type", "TEXT", Some("interface_type")),
+        column("friendly_name", "TEXT", Some("friendly_name")),
+    ]},
+    CompatTable { name: "interface_details", source: "interface_details", columns: &[
+        column("interface", "TEXT", Some("interface")),
+        column("mac", "TEXT", Some("mac")),
+        column("type", "INTEGER", Some("type_")),
+        column("mtu", "INTEGER", Some("mtu")),
+        column("metric", "INTEGER", Some("metric")),
+        column("flags", "INTEGER", Some("flags")),
+        column("ipackets", "BIGINT", Some("ipackets")),
+        column("opackets", "BIGINT", Some("opackets")),
+        column("ibytes", "BIGINT", Some("ibytes")),
+        column("obytes", "BIGINT", Some("obytes")),
+        column("ierrors", "BIGINT", Some("ierrors")),
+        column("oerrors", "BIGINT", Some("oerrors")),
+        column("idrops", "BIGINT", Some("idrops")),
+        column("odrops", "BIGINT", Some("odrops")),
+        column("collisions", "BIGINT", Some("collisions")),
+        column("last_change", "BIGINT", Some("last_change")),
+        column("link_speed", "BIGINT", Some("link_speed")),
+        column("pci_slot", "TEXT", Some("pci_slot")),
+        column("friendly_name", "TEXT", None),
+        column("description", "TEXT", None),
+        column("manufacturer", "TEXT", None),
+        column("connection_id", "TEXT", None),
+        column("connection_status", "TEXT", None),
+        column("enabled", "INTEGER", Some("enabled")),
+        column("physical_adapter", "INTEGER", None),
+        column("speed", "INTEGER", None),
+        column("service", "TEXT", None),
+        column("dhcp_enabled", "INTEGER", None),
+        column("dhcp_lease_expires", "TEXT", None),
+        column("dhcp_lease_obtained", "TEXT", None),
+        column("dhcp_server", "TEXT", None),
+        column("dns_domain", "TEXT", None),
+        column("dns_domain_suffix_search_order", "TEXT", None),
+        column("dns_host_name", "TEXT", None),
+        column("dns_server_search_order", "TEXT", None),
+    ]},
+    CompatTable { name: "process_open_sockets", source: "process_open_sockets", columns: &[
+        column("pid", "INTEGER", Some("pid")),
+        column("fd", "BIGINT", Some("fd")),
+        column("socket", "BIGINT", Some("socket")),
+        column("family", "INTEGER", Some("family")),
+        column("protocol", "INTEGER", Some("protocol")),
+        column("local_address", "TEXT", Some("local_address")),
+        column("remote_address", "TEXT", Some("remote_address")),
+        column("local_port", "INTEGER", Some("local_port")),
+        column("remote_port", "INTEGER", Some("remote_port")),
+        column("path", "TEXT", Some("path")),
+        column("state", "TEXT", Some("state")),
+        column("net_namespace", "TEXT", Some("net_namespace")),
+    ]},
+    CompatTable { name: "process_memory_map", source: "process_memory_map", columns: &[
+        column("pid", "INTEGER", Some("pid")),
+        column("start", "TEXT", Some("start")),
+        column("end", "TEXT", Some("end")),
+        column("permissions", "TEXT", Some("permissions")),
+        column("offset", "BIGINT", Some("offset")),
+        column("device", "TEXT", Some("device")),
+        column("inode", "INTEGER", Some("inode")),
+        column("path", "TEXT", Some("path")),
+        column("pseudo", "INTEGER", Some("pseudo")),
+    ]},
+    CompatTable { name: "users", source: "users", columns: &[
+        column("uid", "BIGINT", Some("uid")),
+        column("gid", "BIGINT", Some("gid")),
+        column("uid_signed", "BIGINT", Some("uid_signed")),
+        column("gid_signed", "BIGINT", Some("gid_signed")),
+        column("username", "TEXT", Some("username")),
+        column("description", "TEXT", Some("description")),
+        column("directory", "TEXT", Some("directory")),
+        column("shell", "TEXT", Some("shell")),
+        column("uuid", "TEXT", Some("uuid")),
+        column("type", "TEXT", Some("type_")),
+        column("is_hidden", "INTEGER", None),
+    ]},
+    CompatTable { name: "logged_in_users", source: "logged_in_users", columns: &[
+        column("type", "TEXT", Some("type_")),
+        column("user", "TEXT", Some("user")),
+        column("tty", "TEXT", Some("tty")),
+        column("host", "TEXT", Some("host")),
+        column("time", "BIGINT", Some("time")),
+        column("pid", "INTEGER", Some("pid")),
+        column("sid", "TEXT", None),
+        column("registry_hive", "TEXT", None),
+    ]},
+    CompatTable { name: "logon_sessions", source: "logon_sessions", columns: &[
+        column("logon_id", "INTEGER", Some("logon_id")),
+        column("user", "TEXT", Some("user")),
+        column("logon_domain", "TEXT", Some("logon_domain")),
+        column("authentication_package", "TEXT", Some("authentication_package")),
+        column("logon_type", "TEXT", Some("logon_type")),
+        column("session_id", "INTEGER", Some("session_id")),
+        column("logon_sid", "TEXT", Some("logon_sid")),
+        column("logon_time", "BIGINT", Some("logon_time")),
+        column("logon_server", "TEXT", Some("logon_server")),
+        column("dns_domain_name", "TEXT", Some("dns_domain_name")),
+        column("upn", "TEXT", Some("upn")),
+        column("logon_script", "TEXT", Some("logon_script")),
+        column("profile_path", "TEXT", Some("profile_path")),
+        column("home_directory", "TEXT", Some("home_directory")),
+        column("home_directory_drive", "TEXT", Some("home_directory_drive")),
+    ]},
+    CompatTable { name: "groups", source: "groups", columns: &[
+        column("gid", "BIGINT", Some("gid")),
+        column("gid_signed", "BIGINT", Some("gid_signed")),
+        column("groupname", "TEXT", Some("groupname")),
+        column("group_sid", "TEXT", Some("group_sid")),
+        column("comment", "TEXT", Some("comment")),
+        column("is_hidden", "INTEGER", None),
+    ]},
+    // No osquery counterpart, only the escaped column name is fixed.
+    CompatTable { name: "wmi_local_accounts", source: "wmi_local_accounts", columns: &[
+        column("account_type", "TEXT", Some("account_type")),
+        column("caption", "TEXT", Some("caption")),
+        column("description", "TEXT", Some("description")),
+        column("domain", "TEXT", Some("_domain")),
+        column("local_account", "TEXT", Some("local_account")),
+        column("name", "TEXT", Some("name")),
+        column("sid", "TEXT", Some("sid")),
+        column("sid_type", "INTEGER", Some("sid_type")),
+        column("status", "TEXT", Some("status")),
+    ]},
+];
+
+pub fn compat_table(name: &str) -> Option<&'static CompatTable> {
+    COMPAT_TABLES.iter().find(|table| table.name == name)
+}
+
+/// SQLite type affinity storing an osquery type.
+pub fn affinity(column_type: &str) -> &'static str {
+    match column_type {
+        "INTEGER" | "BIGINT" | "UNSIGNED_BIGINT" => "INTEGER",
+        "DOUBLE" => "REAL",
+        "BLOB" => "BLOB",
+        _ => "TEXT",
+    }
+}
+
+/// osquery schema of a compatibility view, as printed by `--schema`.
+pub fn compat_schema(name: &str) -> Option<String> {
+    compat_table(name).map(|table| {
+        let columns: Vec<String> = table.columns.iter()
+            .map(|column| format!("{} {}", quote(column.name), column.column_type))
+            .collect();
+        format!("CREATE TABLE {}({});", table.name, columns.join(", "))
+    })
+}
+
+/// Columns of `main.<table>` with their declared type, empty when the table is not registered.
+fn source_columns(db: &Connection, table: &str) -> Result<Vec<(String, String)>, rusqlite::Error> {
+    let mut statement = db.prepare(&format!("PRAGMA main.table_info({})", quote(table)))?;
+    let columns = statement.query_map(NO_PARAMS, |row| Ok((row.get(1)?, row.get(2)?)))?;
+    columns.collect()
+}
+
+/// `SELECT` statement of the view, casting values whose siquery type differs from osquery's.
+pub fn view_sql(table: &CompatTable, source_columns: &[(String, String)]) -> String {
+    let columns: Vec<String> = table.columns.iter()
+        .map(|column| {
+            let affinity = affinity(column.column_type);
+            let source = column.source
+                .and_then(|source| source_columns.iter().find(|(name, _)| name == source));
+            match source {
+                Some((name, declared)) if declared.eq_ignore_ascii_case(affinity) =>
+                    format!("{} AS {}", quote(name), quote(column.name)),
+                Some((name, _)) => format!("CAST({} AS {}) AS {}", quote(name), affinity, quote(column.name)),
+                None => format!("CAST(NULL AS {}) AS {}", affinity, quote(column.name)),
+            }
+        })
+        .collect();
+    format!("SELECT {} FROM main.{}", columns.join(", "), quote(table.source))
+}
+
+/// Creates a TEMP view for every compatibility table whose siquery table is registered in `db`.
+///
+/// Returns the names of the views created.
+pub fn create_compat_views(db: &Connection) -> Result<Vec<&'static str>, rusqlite::Error> {
+    let mut created = Vec::new();
+    for table in COMPAT_TABLES.iter() {
+        let columns = source_columns(db, table.source)?;
+        if columns.is_empty() {
+            continue;
+        }
+        db.execute_batch(&format!("DROP VIEW IF EXISTS temp.{0}; CREATE TEMP VIEW {0} AS {1}",
+                                  quote(table.name), view_sql(table, &columns)))?;
+        created.push(table.name);
+    }
+    Ok(created)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::get_schema;
+    use crate::tables::get_table_list;
+    use std::collections::BTreeMap;
+    use std::fs;
+    use std::path::Path;
+
+    /// Column of a vendored osquery `.table` spec.
+    #[derive(Debug)]
+    struct SpecColumn {
+        name: String,
+        column_type: String,
+        hidden: bool,
+    }
+
+    /// Reads `table_name(...)` and every `Column(...)` of a spec, from `schema` and all
+    /// `extended_schema` blocks.
+    fn parse_spec(content: &str) -> (String, Vec<SpecColumn>) {
+        let mut name = String::new();
+        let mut columns = Vec::new();
+        for line in content.lines().map(|line| line.trim()) {
+            if line.starts_with("table_name(\"") {
+                name = line["table_name(\"".len()..].split('"').next().unwrap_or("").to_string();
+            } else if line.starts_with("Column(\"") {
+                let mut parts = line["Column(\"".len()..].splitn(2, '"');
+                let column = parts.next().unwrap_or("").to_string();
+                let column_type = parts.next().unwrap_or("")
+                    .trim_start_matches(',').trim()
+                    .split(|c: char| c == ',' || c == ')').next().unwrap_or("").trim().to_string();
+                columns.push(SpecColumn { name: column, column_type, hidden: line.contains("hidden=True") });
+            }
+        }
+        (name, columns)
+    }
+
+    fn specs() -> BTreeMap<String, Vec<SpecColumn>> {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("test_data").join("osquery_specs");
+        fs::read_dir(&dir).unwrap()
+            .map(|entry| entry.unwrap().path())
+            .filter(|path| path.extension().map(|ext| ext == "table").unwrap_or(false))
+            .map(|path| parse_spec(&fs::read_to_string(&path).unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn test_parse_spec() {
+        let (name, columns) = parse_spec(r#"
+table_name("users")
+schema([
+    Column("uid", BIGINT, "User ID", index=True),
+    Column("username", TEXT, "Username", additional=True),
+])
+extended_schema(LINUX, [
+    Column("pid_with_namespace", INTEGER, "Pids that contain a namespace", additional=True, hidden=True),
+])
+"#);
+        assert_eq!(name, "users");
+        assert_eq!(columns.len(), 3);
+        assert_eq!((columns[0].name.as_str(), columns[0].column_type.as_str()), ("uid", "BIGINT"));
+        assert!(!columns[1].hidden);
+        assert!(columns[2].hidden);
+    }
+
+    /// Every vendored osquery spec has a view with the same visible columns, in order, and types.
+    #[test]
+    fn test_conformance_with_osquery_specs() {
+        let specs = specs();
+        assert!(!specs.is_empty());
+        for (name, spec_columns) in specs.iter() {
+            let table = compat_table(name).unwrap_or_else(|| panic!("no compat view for osquery table {}", name));
+            let expected: Vec<(&str, &str)> = spec_columns.iter()
+                .filter(|column| !column.hidden)
+                .map(|column| (column.name.as_str(), column.column_type.as_str()))
+                .collect();
+            let actual: Vec<(&str, &str)> = table.columns.iter()
+                .map(|column| (column.name, column.column_type))
+                .collect();
+            assert_eq!(actual, expected, "columns of {}", name);
+        }
+        for table in COMPAT_TABLES.iter().filter(|table| table.name != "wmi_local_accounts") {
+            assert!(specs.contains_key(table.name), "no vendored spec for {}", table.name);
+        }
+    }
+
+    /// Mapped columns exist in the siquery tables of this platform.
+    #[test]
+    fn test_sources_exist() {
+        let tables = get_table_list();
+        for table in COMPAT_TABLES.iter().filter(|table| tables.iter().any(|t| t == table.source)) {
+            let schema = get_schema(table.source).unwrap();
+            for source in table.columns.iter().filter_map(|column| column.source) {
+                assert!(schema.contains(&format!("\"{}\"", source)), "{}.{} is not a siquery column", table.source, source);
+            }
+        }
+    }
+
+    #[test]
+    fn test_create_compat_views() {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE users(uid INTEGER, gid INTEGER, uid_signed INTEGER, gid_signed INTEGER,
+                              username TEXT, description TEXT, directory TEXT, shell TEXT, uuid TEXT, type_ TEXT);
+                          INSERT INTO users VALUES (0, 0, 0, 0, 'root', '', '/root', '/bin/sh', '', 'local');
+                          CREATE TABLE interface_address(interface TEXT, address TEXT, mask TEXT, interface_type TEXT,
+                              friendly_name TEXT, broadcast TEXT, point_to_point TEXT);").unwrap();
+        assert_eq!(create_compat_views(&db).unwrap(), vec!["interface_addresses", "users"]);
+        let row: (String, String, Option<i64>) = db.query_row(
+            "SELECT username, type, is_hidden FROM users", NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?))).unwrap();
+        assert_eq!(row, ("root".to_string(), "local".to_string(), None));
+        // The siquery table is still reachable.
+        let type_: String = db.query_row("SELECT type_ FROM main.users", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(type_, "local");
+        // Creating the views twice replaces them.
+        assert_eq!(create_compat_views(&db).unwrap().len(), 2);
+        assert_eq!(compat_schema("logical_drives").unwrap(),
+                   "CREATE TABLE logical_drives(\"device_id\" TEXT, \"type\" TEXT, \"description\" TEXT, \
+                    \"free_space\" BIGINT, \"size\" BIGINT, \"file_system\" TEXT, \"boot_partition\" INTEGER);");
+    }
+}
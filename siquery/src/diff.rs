@@ -0,0 +1,218 @@
+use serde_json::{Value as serdValue, Map};
+use std::collections::{BTreeMap, HashMap};
+
+/// Column identifying a row of `table` across snapshots, when the table has an obvious one.
+pub fn natural_key(table: &str) -> Option<&'static str> {
+    match table {
+        "processes" => Some("pid"),
+        "users" => Some("username"),
+        "groups" => Some("groupname"),
+        "mounts" => Some("path"),
+        "wmi_services" => Some("name"),
+        "wmi_hotfixes" => Some("hotfix_id"),
+        _ => None,
+    }
+}
+
+/// A row whose key is in both snapshots with different values.
+#[derive(Debug, PartialEq)]
+pub struct RowChange {
+    pub key: serdValue,
+    pub before: Map<String, serdValue>,
+    pub after: Map<String, serdValue>,
+}
+
+impl RowChange {
+    /// Columns whose value differs, with the value before and after.
+    pub fn columns(&self) -> Vec<(String, serdValue, serdValue)> {
+        let mut names: Vec<&String> = self.before.keys().chain(self.after.keys()).collect();
+        names.sort();
+        names.dedup();
+        names.into_iter()
+            .filter_map(|name| {
+                let before = self.before.get(name).cloned().unwrap_or(serdValue::Null);
+                let after = self.after.get(name).cloned().unwrap_or(serdValue::Null);
+                if before != after { Some((name.clone(), before, after)) } else { None }
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct TableDiff {
+    pub table: String,
+    /// Column rows were matched on, or `None` when whole rows were compared.
+    pub key: Option<String>,
+    pub added: Vec<Map<String, serdValue>>,
+    pub removed: Vec<Map<String, serdValue>>,
+    pub changed: Vec<RowChange>,
+}
+
+impl TableDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+fn row_string(row: &Map<String, serdValue>) -> String {
+    serdValue::Object(row.clone()).to_string()
+}
+
+/// Rows only found after, and rows only found before, comparing whole rows and counting duplicates.
+pub fn row_changes(before: Vec<Map<String, serdValue>>, after: Vec<Map<String, serdValue>>)
+                   -> (Vec<Map<String, serdValue>>, Vec<Map<String, serdValue>>) {
+    let mut counts: BTreeMap<String, i64> = BTreeMap::new();
+    for row in before.iter() {
+        *counts.entry(row_string(row)).or_insert(0) += 1;
+    }
+    let mut added = Vec::new();
+    for row in after {
+        match counts.get_mut(&row_string(&row)) {
+            Some(count) if *count > 0 => *count -= 1,
+            _ => added.push(row),
+        }
+    }
+    let mut removed = Vec::new();
+    for row in before {
+        if let Some(count) = counts.get_mut(&row_string(&row)) {
+            if *count > 0 {
+                *count -= 1;
+                removed.push(row);
+            }
+        }
+    }
+    (added, removed)
+}
+
+/// Indexes rows by `key`, or returns `None` when a row lacks it or two rows share it.
+fn index_rows(rows: &[Map<String, serdValue>], key: &str) -> Option<BTreeMap<String, usize>> {
+    let mut index = BTreeMap::new();
+    for (i, row) in rows.iter().enumerate() {
+        let value = row.get(key)?.to_string();
+        if index.insert(value, i).is_some() {
+            return None;
+        }
+    }
+    Some(index)
+}
+
+/// Compares two result sets of one table, matching rows on `key` when it identifies them.
+pub fn diff_rows(table: &str, key: Option<&str>, before: Vec<Map<String, serdValue>>,
+                 after: Vec<Map<String, serdValue>>) -> TableDiff {
+    let indexes = key.and_then(|key| Some((key, index_rows(&before, key)?, index_rows(&after, key)?)));
+    let (key, before_index, after_index) = match indexes {
+        Some(indexes) => indexes,
+        None => {
+            let (added, removed) = row_changes(before, after);
+            return TableDiff { table: table.to_string(), key: None, added, removed, changed: Vec::new() };
+        }
+    };
+
+    let mut diff = TableDiff { table: table.to_string(), key: Some(key.to_string()), ..TableDiff::default() };
+    for (value, i) in before_index.iter() {
+        match after_index.get(value) {
+            None => diff.removed.push(before[*i].clone()),
+            Some(j) if before[*i] != after[*j] => diff.changed.push(RowChange {
+                key: before[*i][key].clone(),
+                before: before[*i].clone(),
+                after: after[*j].clone(),
+            }),
+            Some(_) => {},
+        }
+    }
+    for (value, j) in after_index.iter() {
+        if !before_index.contains_key(value) {
+            diff.added.push(after[*j].clone());
+        }
+    }
+    diff
+}
+
+fn table_rows(tables: &Map<String, serdValue>, table: &str) -> Vec<Map<String, serdValue>> {
+    tables.get(table)
+        .and_then(|rows| rows.as_array())
+        .map(|rows| rows.iter().filter_map(|row| row.as_object().cloned()).collect())
+        .unwrap_or_default()
+}
+
+/// Compares two `{"table": [rows]}` documents as printed by `siquery --json -a <tables>`, table by table.
+///
+/// `keys` overrides the natural key of a table.
+pub fn diff_results(before: &Map<String, serdValue>, after: &Map<String, serdValue>,
+                    keys: &HashMap<String, String>) -> Vec<TableDiff> {
+    let mut tables: Vec<&String> = before.keys().chain(after.keys()).collect();
+    tables.sort();
+    tables.dedup();
+    tables.into_iter()
+        .map(|table| {
+            let key = keys.get(table).map(|key| key.as_str()).or_else(|| natural_key(table));
+            diff_rows(table, key, table_rows(before, table), table_rows(after, table))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tables(value: serdValue) -> Map<String, serdValue> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_diff_results() {
+        let before = tables(json!({
+            "processes": [
+                {"pid": 1, "name": "init", "resident_size": 10},
+                {"pid": 2, "name": "sshd", "resident_size": 20}
+            ],
+            "etc_hosts": [{"address": "127.0.0.1", "hostnames": "localhost"}],
+            "mounts": [{"path": "/", "flags": "rw"}]
+        }));
+        let after = tables(json!({
+            "processes": [
+                {"pid": 1, "name": "init", "resident_size": 12},
+                {"pid": 3, "name": "nginx", "resident_size": 30}
+            ],
+            "etc_hosts": [
+                {"address": "127.0.0.1", "hostnames": "localhost"},
+                {"address": "10.0.0.1", "hostnames": "db"}
+            ],
+            "mounts": [{"path": "/", "flags": "rw"}]
+        }));
+        let diffs = diff_results(&before, &after, &HashMap::new());
+        assert_eq!(diffs.iter().map(|d| d.table.as_str()).collect::<Vec<_>>(), vec!["etc_hosts", "mounts", "processes"]);
+
+        let hosts = &diffs[0];
+        assert_eq!(hosts.key, None);
+        assert_eq!(hosts.added.len(), 1);
+        assert!(hosts.removed.is_empty());
+        assert!(diffs[1].is_empty());
+
+        let processes = &diffs[2];
+        assert_eq!(processes.key, Some("pid".to_string()));
+        assert_eq!(processes.added[0]["name"], json!("nginx"));
+        assert_eq!(processes.removed[0]["name"], json!("sshd"));
+        assert_eq!(processes.changed.len(), 1);
+        assert_eq!(processes.changed[0].key, json!(1));
+        assert_eq!(processes.changed[0].columns(), vec![("resident_size".to_string(), json!(10), json!(12))]);
+
+        let mut keys = HashMap::new();
+        keys.insert("etc_hosts".to_string(), "address".to_string());
+        let diffs = diff_results(&before, &after, &keys);
+        assert_eq!(diffs[0].key, Some("address".to_string()));
+        assert_eq!(diffs[0].added[0]["hostnames"], json!("db"));
+    }
+
+    #[test]
+    fn test_duplicate_keys_compare_rows() {
+        let before = vec![json!({"pid": 1, "name": "a"}).as_object().unwrap().clone()];
+        let after = vec![
+            json!({"pid": 1, "name": "a"}).as_object().unwrap().clone(),
+            json!({"pid": 1, "name": "b"}).as_object().unwrap().clone(),
+        ];
+        let diff = diff_rows("processes", Some("pid"), before, after);
+        assert_eq!(diff.key, None);
+        assert_eq!(diff.added.len(), 1);
+    }
+}
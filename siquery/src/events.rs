@@ -0,0 +1,137 @@
+use std::collections::VecDeque;
+
+/// Default number of events kept by an event table before the oldest ones are dropped.
+pub const DEFAULT_EVENTS_CAPACITY: usize = 50_000;
+
+#[derive(Clone, Debug)]
+pub struct Event<T> {
+    /// Monotonic event id, unique within a buffer.
+    pub eid: u64,
+    /// Unix time at which the event was recorded.
+    pub time: i64,
+    pub data: T,
+}
+
+/// Bounded ring buffer backing an event table.
+///
+/// Publishers push events as they are observed; once `capacity` is reached the oldest
+/// events are overwritten. Queries either drain the buffer or page through it by time.
+pub struct EventBuffer<T> {
+    capacity: usize,
+    next_eid: u64,
+    dropped: u64,
+    events: VecDeque<Event<T>>,
+}
+
+impl<T: Clone> EventBuffer<T> {
+    pub fn new(capacity: usize) -> EventBuffer<T> {
+        EventBuffer {
+            capacity,
+            next_eid: 1,
+            dropped: 0,
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.evict();
+    }
+
+    pub fn len(&self) -> usize {
+        self.events.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty()
+    }
+
+    /// Number of events overwritten because the buffer was full.
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    pub fn push(&mut self, time: i64, data: T) -> u64 {
+        let eid = self.next_eid;
+        self.next_eid += 1;
+        self.events.push_back(Event { eid, time, data });
+        self.evict();
+        eid
+    }
+
+    /// Removes and returns every buffered event.
+    pub fn drain(&mut self) -> Vec<Event<T>> {
+        self.events.drain(..).collect()
+    }
+
+    /// Returns the events recorded at or after `time`, without consuming them.
+    pub fn since(&self, time: i64) -> Vec<Event<T>> {
+        self.events.iter().filter(|e| e.time >= time).cloned().collect()
+    }
+
+    /// Removes and returns the events recorded at or after `time`, keeping the older ones.
+    pub fn drain_since(&mut self, time: i64) -> Vec<Event<T>> {
+        let (kept, drained): (VecDeque<Event<T>>, VecDeque<Event<T>>) =
+            self.events.drain(..).partition(|e| e.time < time);
+        self.events = kept;
+        drained.into_iter().collect()
+    }
+
+    /// Returns every buffered event, without consuming them.
+    pub fn all(&self) -> Vec<Event<T>> {
+        self.events.iter().cloned().collect()
+    }
+
+    fn evict(&mut self) {
+        while self.events.len() > self.capacity {
+            self.events.pop_front();
+            self.dropped += 1;
+        }
+    }
+}
+
+/// Current unix time, used to timestamp events.
+pub fn unix_time() -> i64 {
+    time::get_time().sec
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_buffer_bounded() {
+        let mut buffer: EventBuffer<u32> = EventBuffer::new(2);
+        buffer.push(10, 1);
+        buffer.push(11, 2);
+        buffer.push(12, 3);
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.dropped(), 1);
+        let events = buffer.all();
+        assert_eq!(events[0].eid, 2);
+        assert_eq!(events[1].data, 3);
+    }
+
+    #[test]
+    fn test_event_buffer_since_and_drain() {
+        let mut buffer: EventBuffer<&str> = EventBuffer::new(10);
+        buffer.push(100, "a");
+        buffer.push(200, "b");
+        buffer.push(300, "c");
+        let recent = buffer.since(200);
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].data, "b");
+        assert_eq!(buffer.len(), 3);
+        let drained = buffer.drain_since(300);
+        assert_eq!(drained.len(), 1);
+        assert_eq!(drained[0].data, "c");
+        assert_eq!(buffer.len(), 2);
+        assert_eq!(buffer.drain().len(), 2);
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.push(400, "d"), 4);
+    }
+}
@@ -0,0 +1,338 @@
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeZone};
+use rusqlite::{Connection, Error, NO_PARAMS};
+use rusqlite::types::Value;
+use serde_json::{Value as serdValue, Map};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::diff::{diff_rows, natural_key, TableDiff};
+use crate::events::unix_time;
+use crate::utils::quote_identifier as quote;
+
+/// Schema name the store is attached under in a query connection.
+const SCHEMA: &str = "history";
+
+/// Limits applied to every table of the store after each snapshot.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Retention {
+    pub max_age: Option<Duration>,
+    /// Number of snapshots kept per table.
+    pub max_snapshots: Option<usize>,
+}
+
+/// Two snapshots of a table and the rows added, removed and changed between them.
+#[derive(Debug, Default)]
+pub struct HistoryDiff {
+    pub from: i64,
+    pub to: i64,
+    pub changes: TableDiff,
+}
+
+/// On-disk SQLite database holding periodic snapshots of tables.
+///
+/// Each table is stored under its own name with a leading `snapshot_time` column, and
+/// `snapshots` lists every snapshot taken.
+pub struct HistoryStore {
+    store: Connection,
+    path: PathBuf,
+    retention: Retention,
+}
+
+/// Default store location, next to the user's other application data.
+pub fn default_history_path() -> PathBuf {
+    let dir = if cfg!(windows) {
+        std::env::var("PROGRAMDATA").map(PathBuf::from).unwrap_or_default()
+    } else if let Ok(dir) = std::env::var("XDG_DATA_HOME") {
+        PathBuf::from(dir)
+    } else {
+        std::env::var("HOME").map(|home| Path::new(&home).join(".local").join("share")).unwrap_or_default()
+    };
+    dir.join("siquery").join("history.db")
+}
+
+impl HistoryStore {
+    pub fn open(path: &Path) -> Result<HistoryStore, Error> {
+        if let Some(parent) = path.parent() {
+            let _ = fs::create_dir_all(parent);
+        }
+        let store = Connection::open(path)?;
+        store.execute_batch(
+            "CREATE TABLE IF NOT EXISTS snapshots(table_name TEXT NOT NULL, snapshot_time INTEGER NOT NULL, \
+             rows INTEGER NOT NULL, PRIMARY KEY (table_name, snapshot_time));")?;
+        Ok(HistoryStore {
+            store,
+            path: path.to_path_buf(),
+            retention: Retention::default(),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn set_retention(&mut self, retention: Retention) {
+        self.retention = retention;
+    }
+
+    /// Creates the table of `table` in the store, adding columns a newer schema introduced.
+    fn prepare_table(&self, table: &str, columns: &[(String, Option<String>)]) -> Result<(), Error> {
+        let mut sql = format!("CREATE TABLE IF NOT EXISTS {}(snapshot_time INTEGER NOT NULL", quote(table));
+        for (name, decl_type) in columns {
+            sql.push_str(&format!(", {} {}", quote(name), decl_type.as_ref().map(|t| t.as_str()).unwrap_or("")));
+        }
+        sql.push_str(&format!(");\nCREATE INDEX IF NOT EXISTS {} ON {}(snapshot_time);",
+                              quote(&format!("{}_snapshot_time", table)), quote(table)));
+        self.store.execute_batch(&sql)?;
+
+        let existing: Vec<String> = {
+            let mut statement = self.store.prepare(&format!("PRAGMA table_info({})", quote(table)))?;
+            let names = statement.query_map(NO_PARAMS, |row| row.get::<usize, String>(1))?;
+            names.collect::<Result<Vec<String>, Error>>()?
+        };
+        for (name, decl_type) in columns.iter().filter(|(name, _)| !existing.contains(name)) {
+            self.store.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", quote(table), quote(name),
+                                              decl_type.as_ref().map(|t| t.as_str()).unwrap_or("")))?;
+        }
+        Ok(())
+    }
+
+    /// Stores the current rows of `table`, read through `live`, as the snapshot taken at `time`.
+    pub fn record(&self, live: &Connection, table: &str, time: i64) -> Result<usize, Error> {
+        let mut statement = live.prepare(&format!("SELECT * FROM {}", quote(table)))?;
+        let columns: Vec<(String, Option<String>)> = statement.columns().iter()
+            .map(|column| (column.name().to_string(), column.decl_type().map(|t| t.to_string())))
+            .collect();
+        self.prepare_table(table, &columns)?;
+
+        let names: Vec<String> = columns.iter().map(|(name, _)| quote(name)).collect();
+        let placeholders: Vec<String> = (0..=columns.len()).map(|i| format!("?{}", i + 1)).collect();
+        let sql = format!("INSERT INTO {}(snapshot_time, {}) VALUES ({})",
+                          quote(table), names.join(", "), placeholders.join(", "));
+        let mut rows = statement.query(NO_PARAMS)?;
+
+        self.store.execute_batch("BEGIN")?;
+        let result = (|| {
+            let mut insert = self.store.prepare(&sql)?;
+            let mut count = 0;
+            while let Some(row) = rows.next()? {
+                let mut values = vec![Value::Integer(time)];
+                for i in 0..columns.len() {
+                    values.push(row.get::<usize, Value>(i)?);
+                }
+                insert.execute(&values)?;
+                count += 1;
+            }
+            self.store.execute("INSERT OR REPLACE INTO snapshots(table_name, snapshot_time, rows) VALUES (?1, ?2, ?3)",
+                               &[&table as &dyn rusqlite::ToSql, &time, &(count as i64)])?;
+            Ok(count)
+        })();
+        match result {
+            Ok(count) => {
+                self.store.execute_batch("COMMIT")?;
+                self.prune(table, time)?;
+                Ok(count)
+            },
+            Err(e) => {
+                let _ = self.store.execute_batch("ROLLBACK");
+                Err(e)
+            }
+        }
+    }
+
+    /// Drops snapshots of `table` that fall outside the retention limits.
+    pub fn prune(&self, table: &str, now: i64) -> Result<usize, Error> {
+        let mut expired: Vec<i64> = Vec::new();
+        let snapshots = self.snapshots(table)?;
+        if let Some(max_age) = self.retention.max_age {
+            let oldest = now - max_age.as_secs() as i64;
+            expired.extend(snapshots.iter().filter(|time| **time < oldest));
+        }
+        if let Some(max_snapshots) = self.retention.max_snapshots {
+            if snapshots.len() > max_snapshots {
+                expired.extend(&snapshots[..snapshots.len() - max_snapshots]);
+            }
+        }
+        expired.sort();
+        expired.dedup();
+        for time in expired.iter() {
+            self.store.execute(&format!("DELETE FROM {} WHERE snapshot_time = ?1", quote(table)), &[time])?;
+            self.store.execute("DELETE FROM snapshots WHERE table_name = ?1 AND snapshot_time = ?2",
+                               &[&table as &dyn rusqlite::ToSql, time])?;
+        }
+        Ok(expired.len())
+    }
+
+    /// Tables with at least one snapshot.
+    pub fn tables(&self) -> Result<Vec<String>, Error> {
+        let mut statement = self.store.prepare("SELECT DISTINCT table_name FROM snapshots ORDER BY table_name")?;
+        let tables = statement.query_map(NO_PARAMS, |row| row.get::<usize, String>(0))?;
+        tables.collect()
+    }
+
+    /// Snapshot times of `table`, oldest first.
+    pub fn snapshots(&self, table: &str) -> Result<Vec<i64>, Error> {
+        let mut statement = self.store.prepare(
+            "SELECT snapshot_time FROM snapshots WHERE table_name = ?1 ORDER BY snapshot_time")?;
+        let times = statement.query_map(&[table], |row| row.get::<usize, i64>(0))?;
+        times.collect()
+    }
+
+    /// Latest snapshot of `table` taken at or before `time`.
+    pub fn snapshot_at(&self, table: &str, time: i64) -> Result<Option<i64>, Error> {
+        Ok(self.snapshots(table)?.into_iter().filter(|t| *t <= time).last())
+    }
+
+    /// Rows of one snapshot, without the `snapshot_time` column.
+    pub fn rows(&self, table: &str, snapshot_time: i64) -> Result<Vec<Map<String, serdValue>>, Error> {
+        let mut statement = self.store.prepare(
+            &format!("SELECT * FROM {} WHERE snapshot_time = ?1", quote(table)))?;
+        let columns: Vec<String> = statement.column_names().iter().map(|c| c.to_string()).collect();
+        let mut response = statement.query(&[snapshot_time])?;
+        let mut rows = Vec::new();
+        while let Some(row) = response.next()? {
+            let mut values = Map::new();
+            for (i, column) in columns.iter().enumerate().filter(|(_, column)| *column != "snapshot_time") {
+                let value = match row.get::<usize, Value>(i)? {
+                    Value::Null => serdValue::Null,
+                    Value::Integer(i) => json!(i),
+                    Value::Real(f) => json!(f),
+                    Value::Text(s) => json!(s),
+                    Value::Blob(b) => json!(String::from_utf8_lossy(&b)),
+                };
+                values.insert(column.clone(), value);
+            }
+            rows.push(values);
+        }
+        Ok(rows)
+    }
+
+    /// Compares the snapshots of `table` in effect at `from` and at `to`, matching rows on the
+    /// natural key of the table as `diff_results` does.
+    pub fn diff(&self, table: &str, from: i64, to: i64) -> Result<HistoryDiff, String> {
+        let snapshot = |time: i64| -> Result<i64, String> {
+            self.snapshot_at(table, time)
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| format!("no snapshot of {} at or before {}", table, format_time(time)))
+        };
+        let (from, to) = (snapshot(from)?, snapshot(to)?);
+        let before = self.rows(table, from).map_err(|e| e.to_string())?;
+        let after = self.rows(table, to).map_err(|e| e.to_string())?;
+        Ok(HistoryDiff {
+            from,
+            to,
+            changes: diff_rows(table, natural_key(table), before, after),
+        })
+    }
+}
+
+/// Attaches the store to a query connection and creates a `history_<table>` view per stored table.
+pub fn attach_history(db: &Connection, path: &Path) -> Result<Vec<String>, Error> {
+    let tables = HistoryStore::open(path)?.tables()?;
+    db.execute(&format!("ATTACH DATABASE ?1 AS {}", SCHEMA), &[path.to_string_lossy().as_ref()])?;
+    for table in tables.iter() {
+        db.execute_batch(&format!("CREATE TEMP VIEW IF NOT EXISTS {} AS SELECT * FROM {}.{}",
+                                  quote(&format!("history_{}", table)), SCHEMA, quote(table)))?;
+    }
+    Ok(tables)
+}
+
+pub fn format_time(time: i64) -> String {
+    match Local.timestamp_opt(time, 0).single() {
+        Some(date) => date.format("%Y-%m-%d %H:%M:%S").to_string(),
+        None => time.to_string(),
+    }
+}
+
+/// Parses unix seconds, `now`, an offset from now such as `-1d` or `-90m`,
+/// RFC 3339, or a local `YYYY-MM-DD[ HH:MM[:SS]]`.
+pub fn parse_time(time: &str) -> Result<i64, String> {
+    let time = time.trim();
+    if time == "now" {
+        return Ok(unix_time());
+    }
+    if let Ok(seconds) = time.parse::<i64>() {
+        return Ok(seconds);
+    }
+    if time.starts_with('-') && time.len() > 2 {
+        let (amount, unit) = time[1..].split_at(time.len() - 2);
+        if let Ok(amount) = amount.parse::<i64>() {
+            let seconds = match unit {
+                "s" => Some(1),
+                "m" => Some(60),
+                "h" => Some(3600),
+                "d" => Some(86400),
+                _ => None,
+            };
+            if let Some(seconds) = seconds {
+                return Ok(unix_time() - amount * seconds);
+            }
+        }
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(time) {
+        return Ok(date.timestamp());
+    }
+    let naive = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M"].iter()
+        .filter_map(|format| NaiveDateTime::parse_from_str(time, format).ok())
+        .next()
+        .or_else(|| NaiveDate::parse_from_str(time, "%Y-%m-%d").ok().and_then(|date| date.and_hms_opt(0, 0, 0)));
+    naive.and_then(|naive| Local.from_local_datetime(&naive).single())
+        .map(|date| date.timestamp())
+        .ok_or_else(|| format!("invalid time '{}'", time))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn live_db(users: &[(&str, i64)]) -> Connection {
+        let db = Connection::open_in_memory().unwrap();
+        db.execute_batch("CREATE TABLE users(username TEXT, uid INTEGER)").unwrap();
+        for (username, uid) in users {
+            db.execute("INSERT INTO users VALUES (?1, ?2)", &[username as &dyn rusqlite::ToSql, uid]).unwrap();
+        }
+        db
+    }
+
+    #[test]
+    fn test_history_store() {
+        let path = std::env::temp_dir().join(format!("siquery_history_{}.db", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mut store = HistoryStore::open(&path).unwrap();
+        store.set_retention(Retention { max_age: None, max_snapshots: Some(2) });
+
+        assert_eq!(store.record(&live_db(&[("root", 0), ("alice", 1000)]), "users", 100).unwrap(), 2);
+        assert_eq!(store.record(&live_db(&[("root", 0), ("bob", 1001)]), "users", 200).unwrap(), 2);
+        assert_eq!(store.record(&live_db(&[("root", 0), ("bob", 1002), ("eve", 0)]), "users", 300).unwrap(), 3);
+        assert_eq!(store.snapshots("users").unwrap(), vec![200, 300]);
+
+        let diff = store.diff("users", 250, 300).unwrap();
+        assert_eq!((diff.from, diff.to), (200, 300));
+        assert_eq!(diff.changes.key, Some("username".to_string()));
+        assert_eq!(diff.changes.added.len(), 1);
+        assert_eq!(diff.changes.added[0]["username"], json!("eve"));
+        assert!(diff.changes.removed.is_empty());
+        assert_eq!(diff.changes.changed.len(), 1);
+        assert_eq!(diff.changes.changed[0].columns(), vec![("uid".to_string(), json!(1001), json!(1002))]);
+        assert!(store.diff("users", 150, 300).is_err());
+
+        let db = Connection::open_in_memory().unwrap();
+        assert_eq!(attach_history(&db, &path).unwrap(), vec!["users".to_string()]);
+        let count: i64 = db.query_row(
+            "SELECT count(*) FROM history_users WHERE snapshot_time = 300 AND uid = 0", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(count, 2);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_parse_time() {
+        assert_eq!(parse_time("1700000000"), Ok(1_700_000_000));
+        assert_eq!(parse_time("2023-11-14T22:13:20Z"), Ok(1_700_000_000));
+        let now = unix_time();
+        let yesterday = parse_time("-1d").unwrap();
+        assert!((now - 86400 - yesterday).abs() <= 1);
+        assert!(parse_time("2023-11-14").is_ok());
+        assert!(parse_time("2023-11-14 10:00").is_ok());
+        assert!(parse_time("yesterday").is_err());
+    }
+}
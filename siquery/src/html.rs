@@ -1,117 +1,283 @@
-use rusqlite::{
-    Rows,
-    types::{
-        Value,
-        Type
-    },
-};
-use horrorshow::{
-    helper::doctype,
-};
+use rusqlite::{Connection, Error, Rows};
+use rusqlite::types::Value;
 use chrono::offset::Local;
-use std::io::prelude::*;
-use std::fs::OpenOptions;
-
-use crate::tables::{
-    SystemInfoData
-};
-
-pub fn map(values: &mut Rows) -> Vec<Vec<String>> {
-    let mut table: Vec<Vec<String>> = Vec::new();
-    let mut row: Vec<String> = Vec::new();
-    loop {
-        if let Ok(Some(res)) = values.next() {
-            for i in 0..res.column_count() {
-                let val = Value::data_type(&res.get_unwrap(i));
-                match val {
-                    Type::Real | Type::Integer => {
-                        row.push(res.get_unwrap::<usize, i64>(i).to_string());
-                    },
-                    Type::Text => {
-                        row.push(res.get_unwrap::<usize, String>(i))
-                    },
-                    _ => {
-                        // Do nothing.
-                    }
-                }
-            }
-            table.push(row);
-            row = Vec::new();
-        } else {
-            break
+use std::fs::File;
+use std::io::{self, prelude::*};
+use std::path::Path;
+
+use crate::query::query_with_params;
+
+/// Sorts a table when one of its headers is clicked, numerically when both cells are numbers.
+const SORT_SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable th").forEach(function (th) {
+  th.addEventListener("click", function () {
+    var table = th.closest("table");
+    var body = table.tBodies[0];
+    var index = Array.prototype.indexOf.call(th.parentNode.children, th);
+    var ascending = th.getAttribute("aria-sort") !== "ascending";
+    table.querySelectorAll("th").forEach(function (h) { h.removeAttribute("aria-sort"); });
+    th.setAttribute("aria-sort", ascending ? "ascending" : "descending");
+    var key = function (row) {
+      var cell = row.children[index];
+      return cell.hasAttribute("data-value") ? parseFloat(cell.getAttribute("data-value")) : cell.textContent;
+    };
+    Array.prototype.slice.call(body.rows).sort(function (a, b) {
+      var x = key(a), y = key(b);
+      var order = (typeof x === "number" && typeof y === "number") ? x - y : String(x).localeCompare(String(y));
+      return ascending ? order : -order;
+    }).forEach(function (row) { body.appendChild(row); });
+  });
+});
+"#;
+
+const STYLE: &str = r#"
+body { font-family: sans-serif; margin: 2em; }
+table { border-collapse: collapse; margin-bottom: 2em; }
+th, td { border: 1px solid #ccc; padding: 0.2em 0.5em; text-align: left; vertical-align: top; }
+table.sortable th { cursor: pointer; background: #eee; }
+th[aria-sort="ascending"]::after { content: " \25B2"; }
+th[aria-sort="descending"]::after { content: " \25BC"; }
+td.null { background: #f8f8f8; }
+code { white-space: pre-wrap; }
+"#;
+
+/// Escapes text for use in element content and attribute values.
+pub fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
         }
     }
-    table
+    escaped
 }
 
-pub fn print_html(columns: Vec<String>, values: &mut Rows, query: &str) {
-    let map = map(values);
-    let table_name = query.split(' ').collect::<Vec<&str>>();
-    let hostname = format!(
-        "{}",
-        SystemInfoData::get_specific()
-        .get(0)
-        .unwrap_or(&SystemInfoData::new()).computer_name
-    );
-    let html_data = format!(
-        "{}",
-        html! {
-            : doctype::HTML;
-            html {
-                head {
-                    title : hostname.clone();
-                }
-                body {
-                    TABLE(frame="hsides", rules="groups", cellpadding="1") {
-                        CAPTION {
-                            : format!(
-                            "Inventory Report of {} - {}",
-                            hostname,
-                            Local::now()
-                            );
-                        }
-                        COLGROUP(align="center");
-                        COLGROUP(align="left");
-                        THEAD(valign="top"){
-                            TR{
-                                TH(colspan="2"){
-                                    : table_name.get(3);
-                                }
-                            }
-                        }
-                        THEAD(valign="top"){
-                            TR {
-                                TH {
-                                    :"labels";
-                                }
-                                TH {
-                                    :"values";
-                                }
-                            }
-                        }
-                        @ for j in 0..map.len() {
-                            TBODY {
-                                @ for i in 0..columns.len() {
-                                    TR {
-                                        TD {
-                                            : columns[i].clone();
-                                        }
-                                        TD {
-                                            : map[j][i].clone();
-                                        }
-                                    }
-                                }
-                            }
-                        }
+struct Section {
+    name: String,
+    query: String,
+    columns: Vec<String>,
+    rows: Vec<Vec<Value>>,
+    error: Option<String>,
+}
+
+/// Builds a single HTML document holding the results of several queries.
+///
+/// ```no_run
+/// use siquery::html::HtmlReport;
+/// use siquery::query::init_db;
+///
+/// let db = init_db();
+/// let mut report = HtmlReport::new(&db);
+/// report.add_table("processes");
+/// report.add_query("listening", "select * from process_open_sockets where state = 'LISTEN'", &[]);
+/// report.write_to("report.html").unwrap();
+/// ```
+pub struct HtmlReport<'a> {
+    db: &'a Connection,
+    title: String,
+    host: Vec<(String, String)>,
+    sections: Vec<Section>,
+}
+
+/// Reads every remaining row of a result set.
+pub(crate) fn collect_rows(response: &mut Rows, column_count: usize) -> Result<Vec<Vec<Value>>, Error> {
+    let mut rows = Vec::new();
+    while let Some(row) = response.next()? {
+        let mut values = Vec::with_capacity(column_count);
+        for i in 0..column_count {
+            values.push(row.get::<usize, Value>(i)?);
+        }
+        rows.push(values);
+    }
+    Ok(rows)
+}
+
+fn query_rows(db: &Connection, query: &str, params: &[(String, String)]) -> Result<(Vec<String>, Vec<Vec<Value>>), Error> {
+    let mut statement = db.prepare(query)?;
+    let columns: Vec<String> = statement.column_names().iter().map(|c| c.to_string()).collect();
+    let mut response = query_with_params(&mut statement, params)?;
+    let rows = collect_rows(&mut response, columns.len())?;
+    Ok((columns, rows))
+}
+
+fn cell(value: &Value) -> String {
+    match value {
+        Value::Null => "<td class=\"null\"></td>".to_string(),
+        Value::Integer(i) => format!("<td data-value=\"{}\">{}</td>", i, i),
+        Value::Real(f) => format!("<td data-value=\"{}\">{}</td>", f, f),
+        Value::Text(s) => format!("<td>{}</td>", escape(s)),
+        Value::Blob(b) => format!("<td>&lt;{} bytes&gt;</td>", b.len()),
+    }
+}
+
+fn anchor(index: usize, name: &str) -> String {
+    let slug: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    format!("s{}-{}", index + 1, slug)
+}
+
+impl<'a> HtmlReport<'a> {
+    /// Starts a report, reading the host summary from `system_info` and `os_version` when registered.
+    pub fn new(db: &'a Connection) -> HtmlReport<'a> {
+        let mut host = Vec::new();
+        for table in &["system_info", "os_version"] {
+            if let Ok((columns, rows)) = query_rows(db, &format!("select * from {}", table), &[]) {
+                if let Some(row) = rows.first() {
+                    for (column, value) in columns.iter().zip(row.iter()) {
+                        host.push((column.clone(), text(value)));
                     }
                 }
             }
+        }
+        let title = host.iter()
+            .find(|(column, _)| column == "computer_name")
+            .map(|(_, name)| format!("Inventory Report of {}", name))
+            .unwrap_or_else(|| "Inventory Report".to_string());
+        HtmlReport {
+            db,
+            title,
+            host,
+            sections: Vec::new(),
+        }
+    }
+
+    pub fn set_title(&mut self, title: &str) -> &mut Self {
+        self.title = title.to_string();
+        self
+    }
+
+    /// Adds a section with every row of `table`.
+    pub fn add_table(&mut self, table: &str) -> &mut Self {
+        let query = format!("select * from {}", table);
+        self.add_query(table, &query, &[])
+    }
+
+    /// Adds a section with the result of `query`. Errors are shown in the section instead of its rows.
+    pub fn add_query(&mut self, name: &str, query: &str, params: &[(String, String)]) -> &mut Self {
+        let section = match query_rows(self.db, query, params) {
+            Ok((columns, rows)) => Section {
+                name: name.to_string(),
+                query: query.to_string(),
+                columns,
+                rows,
+                error: None,
+            },
+            Err(e) => Section {
+                name: name.to_string(),
+                query: query.to_string(),
+                columns: Vec::new(),
+                rows: Vec::new(),
+                error: Some(e.to_string()),
+            },
+        };
+        self.sections.push(section);
+        self
+    }
+
+    /// Adds a section from rows generated elsewhere.
+    pub fn add_rows(&mut self, name: &str, query: &str, columns: Vec<String>, rows: Vec<Vec<Value>>) -> &mut Self {
+        self.sections.push(Section {
+            name: name.to_string(),
+            query: query.to_string(),
+            columns,
+            rows,
+            error: None,
         });
-    write_file(html_data).unwrap_or_else(|e| println!("html printer failed with: {}",e));
+        self
+    }
+
+    pub fn render(&self) -> String {
+        let mut html = String::from("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>{}</title>\n<style>{}</style>\n</head>\n<body>\n", escape(&self.title), STYLE));
+        html.push_str(&format!("<h1>{}</h1>\n<p>Generated {}</p>\n",
+                               escape(&self.title), Local::now().format("%Y-%m-%d %H:%M:%S %z")));
+
+        if !self.host.is_empty() {
+            html.push_str("<h2>Host</h2>\n<table class=\"summary\">\n<tbody>\n");
+            for (column, value) in self.host.iter() {
+                html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", escape(column), escape(value)));
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+
+        html.push_str("<h2>Contents</h2>\n<ol>\n");
+        for (i, section) in self.sections.iter().enumerate() {
+            html.push_str(&format!("<li><a href=\"#{}\">{}</a> ({} rows)</li>\n",
+                                   anchor(i, &section.name), escape(&section.name), section.rows.len()));
+        }
+        html.push_str("</ol>\n");
+
+        for (i, section) in self.sections.iter().enumerate() {
+            html.push_str(&format!("<h2 id=\"{}\">{}</h2>\n<p><code>{}</code></p>\n",
+                                   anchor(i, &section.name), escape(&section.name), escape(&section.query)));
+            if let Some(ref error) = section.error {
+                html.push_str(&format!("<p class=\"error\">{}</p>\n", escape(error)));
+                continue;
+            }
+            html.push_str("<table class=\"sortable\">\n<thead>\n<tr>");
+            for column in section.columns.iter() {
+                html.push_str(&format!("<th>{}</th>", escape(column)));
+            }
+            html.push_str("</tr>\n</thead>\n<tbody>\n");
+            for row in section.rows.iter() {
+                html.push_str("<tr>");
+                for value in row.iter() {
+                    html.push_str(&cell(value));
+                }
+                html.push_str("</tr>\n");
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+        html.push_str(&format!("<script>{}</script>\n</body>\n</html>\n", SORT_SCRIPT));
+        html
+    }
+
+    /// Writes the document to `path`, replacing any existing file.
+    pub fn write_to<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let mut file = File::create(path)?;
+        file.write_all(self.render().as_bytes())
+    }
 }
 
-fn write_file (data: String) -> std::io::Result<()> {
-    let mut file: std::fs::File = OpenOptions::new().append(true).open("inventory.html")?;
-    file.write_all(data.as_bytes())?;
-    Ok(())
-}
\ No newline at end of file
+fn text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.clone(),
+        Value::Blob(b) => format!("<{} bytes>", b.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::init_db;
+
+    #[test]
+    fn test_render_report() {
+        let db = init_db();
+        let mut report = HtmlReport::new(&db);
+        report.set_title("<host>")
+            .add_table("Dummy")
+            .add_query("a & b", "select '<b>x</b>' as \"col<1>\", null as n, 1.5 as r", &[])
+            .add_query("broken", "select * from nope", &[]);
+        let html = report.render();
+        assert!(html.starts_with("<!DOCTYPE html>"));
+        assert_eq!(html.matches("<html").count(), 1);
+        assert!(html.contains("<title>&lt;host&gt;</title>"));
+        assert!(html.contains("<a href=\"#s1-dummy\">Dummy</a> (2 rows)"));
+        assert!(html.contains("<a href=\"#s2-a---b\">a &amp; b</a> (1 rows)"));
+        assert!(html.contains("<th>col&lt;1&gt;</th>"));
+        assert!(html.contains("<td>&lt;b&gt;x&lt;/b&gt;</td><td class=\"null\"></td><td data-value=\"1.5\">1.5</td>"));
+        assert!(html.contains("<td data-value=\"25\">25</td>"));
+        assert!(html.contains("<p class=\"error\">no such table: nope</p>"));
+        assert!(html.ends_with("</html>\n"));
+    }
+}
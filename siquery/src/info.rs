@@ -0,0 +1,241 @@
+//! Tables describing siquery itself: `siquery_info`, `siquery_tables` and `siquery_flags`.
+
+use std::collections::{BTreeSet, HashSet};
+use std::process;
+use std::time::Duration;
+
+use crate::events::unix_time;
+use crate::options::{get_options, CsvOptions, CsvQuote, Decorations, DeniedTables, Options, TablePolicy};
+use crate::query::{get_schema, schema_column_names};
+use crate::tables::{get_table_list, SiqueryFlagsRow, SiqueryInfoRow, SiqueryTablesRow};
+
+include!(concat!(env!("OUT_DIR"), "/table_platforms.rs"));
+
+lazy_static! {
+    static ref START_TIME: i64 = unix_time();
+}
+
+/// Time siquery was initialized, in seconds since the epoch.
+pub fn start_time() -> i64 {
+    *START_TIME
+}
+
+fn build_platform() -> &'static str {
+    if cfg!(target_os = "windows") {
+        "windows"
+    } else if cfg!(target_os = "macos") {
+        "macos"
+    } else if cfg!(target_os = "linux") {
+        "linux"
+    } else {
+        "unknown"
+    }
+}
+
+/// Number of columns of `table`, counted in its schema.
+fn column_count(table: &str) -> i64 {
+    get_schema(table).map(|schema| schema_column_names(&schema).len() as i64).unwrap_or(0)
+}
+
+fn csv_quote_name(quote: CsvQuote) -> &'static str {
+    match quote {
+        CsvQuote::Minimal => "minimal",
+        CsvQuote::All => "all",
+        CsvQuote::NonNumeric => "non-numeric",
+    }
+}
+
+fn delimiter_name(delimiter: u8) -> String {
+    match delimiter {
+        b'\t' => "tab".to_string(),
+        d => (d as char).to_string(),
+    }
+}
+
+fn decorations_names(decorations: &Decorations) -> String {
+    let mut names = Vec::new();
+    if decorations.name {
+        names.push("name");
+    }
+    if decorations.host_identifier {
+        names.push("hostIdentifier");
+    }
+    if decorations.unix_time {
+        names.push("unixTime");
+    }
+    names.join(",")
+}
+
+fn seconds(duration: Option<Duration>) -> String {
+    duration.map(|duration| duration.as_secs().to_string()).unwrap_or_default()
+}
+
+fn megabytes(bytes: Option<u64>) -> String {
+    bytes.map(|bytes| (bytes / 1_048_576).to_string()).unwrap_or_default()
+}
+
+fn names(tables: &HashSet<String>) -> String {
+    let sorted: BTreeSet<&String> = tables.iter().collect();
+    sorted.into_iter().cloned().collect::<Vec<String>>().join(",")
+}
+
+fn allow_names(policy: &TablePolicy) -> String {
+    policy.allow.as_ref().map(names).unwrap_or_default()
+}
+
+fn denied_name(denied: DeniedTables) -> &'static str {
+    match denied {
+        DeniedTables::Omit => "omit",
+        DeniedTables::Stub => "stub",
+    }
+}
+
+/// Library settings as name, value, default value and description.
+fn library_flags(options: &Options) -> Vec<(&'static str, String, String, &'static str)> {
+    let defaults = Options::default();
+    let csv: &CsvOptions = &options.csv;
+    let line_ending = |crlf: bool| if crlf { "crlf" } else { "lf" }.to_string();
+    let root_prefix = |options: &Options| options.root_prefix.as_ref()
+        .map(|prefix| prefix.display().to_string())
+        .unwrap_or_default();
+    vec![
+        ("root_prefix", root_prefix(options), root_prefix(&defaults),
+         "Directory prepended to system configuration files"),
+        ("csv_delimiter", delimiter_name(csv.delimiter), delimiter_name(defaults.csv.delimiter),
+         "Field delimiter of csv output"),
+        ("csv_header", csv.header.to_string(), defaults.csv.header.to_string(),
+         "Whether csv output starts with a header line"),
+        ("csv_line_ending", line_ending(csv.crlf), line_ending(defaults.csv.crlf),
+         "Line ending of csv records"),
+        ("csv_quote", csv_quote_name(csv.quote).to_string(), csv_quote_name(defaults.csv.quote).to_string(),
+         "Which csv fields are quoted"),
+        ("decorations", decorations_names(&options.decorations), decorations_names(&defaults.decorations),
+         "Fields wrapped around ndjson rows"),
+        ("table_timeout", seconds(options.default_table_timeout), seconds(defaults.default_table_timeout),
+         "Seconds a table generator may run, empty when unlimited"),
+        ("allow_tables", allow_names(&options.table_policy), allow_names(&defaults.table_policy),
+         "Tables that may be queried, empty when all are allowed"),
+        ("deny_tables", names(&options.table_policy.deny), names(&defaults.table_policy.deny),
+         "Tables that may not be queried"),
+        ("denied_tables", denied_name(options.table_policy.denied).to_string(),
+         denied_name(defaults.table_policy.denied).to_string(), "Whether denied tables are omitted or stubbed"),
+        ("watchdog_memory_limit", megabytes(options.watchdog.memory), megabytes(defaults.watchdog.memory),
+         "Resident memory growth in MB above which a scheduled query backs off, empty when unwatched"),
+        ("watchdog_cpu_limit", seconds(options.watchdog.cpu_time), seconds(defaults.watchdog.cpu_time),
+         "Seconds of CPU time a scheduled query may use, empty when unwatched"),
+        ("watchdog_max_offences", options.watchdog.max_offences.to_string(), defaults.watchdog.max_offences.to_string(),
+         "Offences after which the watchdog deny-lists a scheduled query"),
+    ]
+}
+
+impl SiqueryInfoRow {
+    pub fn get_specific() -> Vec<SiqueryInfoRow> {
+        vec![SiqueryInfoRow {
+            version: env!("SIQUERY_VERSION").to_string(),
+            revision: env!("SIQUERY_REVISION").to_string(),
+            pid: process::id() as i64,
+            start_time: start_time(),
+            sqlite_version: rusqlite::version().to_string(),
+            build_platform: build_platform().to_string(),
+        }]
+    }
+}
+
+impl SiqueryTablesRow {
+    /// Lists the tables of `tables`, the ones registered on a connection.
+    pub fn get_specific_ex(tables: &[String]) -> Vec<SiqueryTablesRow> {
+        let options = get_options();
+        let tables: BTreeSet<&String> = tables.iter().collect();
+        tables.into_iter().cloned().map(|name| {
+            let platforms = TABLE_PLATFORMS.iter()
+                .find(|(table, _)| *table == name)
+                .map(|(_, platforms)| *platforms)
+                .unwrap_or("linux,macos,windows");
+            SiqueryTablesRow {
+                platforms: platforms.to_string(),
+                column_count: column_count(&name),
+                cache_ttl: options.cache_ttl.get(&name).map(|ttl| ttl.as_secs() as i64).unwrap_or(0),
+                name,
+            }
+        }).collect()
+    }
+
+    /// Lists every table of this build.
+    pub fn get_specific() -> Vec<SiqueryTablesRow> {
+        SiqueryTablesRow::get_specific_ex(&get_table_list())
+    }
+}
+
+impl SiqueryFlagsRow {
+    pub fn get_specific() -> Vec<SiqueryFlagsRow> {
+        let options = get_options();
+        let mut rows: Vec<SiqueryFlagsRow> = library_flags(&options).into_iter()
+            .map(|(name, value, default_value, description)| SiqueryFlagsRow {
+                name: name.to_string(),
+                value,
+                default_value,
+                description: description.to_string(),
+            })
+            .collect();
+        for (name, (value, description)) in options.flags.iter() {
+            rows.push(SiqueryFlagsRow {
+                name: name.clone(),
+                value: value.clone(),
+                default_value: String::new(),
+                description: description.clone(),
+            });
+        }
+        rows
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::options::{set_cache_ttl, set_flag};
+
+    #[test]
+    fn test_siquery_info() {
+        let info = &SiqueryInfoRow::get_specific()[0];
+        assert_eq!(info.version, include_str!("../../VERSION").trim());
+        assert_eq!(info.pid, process::id() as i64);
+        assert!(info.sqlite_version.starts_with("3."));
+        assert!(info.start_time <= unix_time());
+    }
+
+    #[test]
+    fn test_siquery_tables() {
+        set_cache_ttl("siquery_info", Duration::from_secs(30));
+        let tables = SiqueryTablesRow::get_specific();
+        let info = tables.iter().find(|table| table.name == "siquery_info").unwrap();
+        assert_eq!(info.column_count, 6);
+        assert_eq!(info.cache_ttl, 30);
+        assert_eq!(info.platforms, "linux,macos,windows");
+        if let Some(hosts) = tables.iter().find(|table| table.name == "etc_hosts") {
+            assert_eq!(hosts.column_count, 2);
+            assert!(hosts.platforms.contains(build_platform()));
+        }
+    }
+
+    #[test]
+    fn test_connection_tables() {
+        use crate::query::{execute_query_json, init_db_with_tables};
+
+        let tables = vec!["siquery_tables".to_string(), "siquery_info".to_string()];
+        let db = init_db_with_tables(tables.clone());
+        let _other = init_db_with_tables(vec!["siquery_tables".to_string(), "siquery_flags".to_string()]);
+        let rows = execute_query_json(&db, "select name from siquery_tables", &[]).unwrap();
+        let names: Vec<&str> = rows.iter().map(|row| row["name"].as_str().unwrap()).collect();
+        assert_eq!(names, vec!["siquery_info", "siquery_tables"]);
+    }
+
+    #[test]
+    fn test_siquery_flags() {
+        set_flag("info_test", "on", "Flag set by the embedding program");
+        let flags = SiqueryFlagsRow::get_specific();
+        let header = flags.iter().find(|flag| flag.name == "csv_header").unwrap();
+        assert_eq!(header.default_value, "true");
+        let flag = flags.iter().find(|flag| flag.name == "info_test").unwrap();
+        assert_eq!(flag.value, "on");
+    }
+}
@@ -8,16 +8,33 @@ extern crate serde_derive;
 extern crate serde_json;
 #[macro_use]
 extern crate lazy_static;
-#[macro_use]
-extern crate horrorshow;
 
 pub mod vtab;
+pub mod events;
+pub mod logger;
+pub mod profile;
+pub mod options;
 mod common;
 mod utils;
 pub mod printer;
 pub mod query;
 pub mod tables;
 pub mod html;
+pub mod client;
+pub mod compat;
+pub mod diff;
+pub mod history;
+pub mod info;
+pub mod merge;
+pub mod watchdog;
+#[cfg(feature = "columnar")]
+pub mod columnar;
+#[cfg(feature = "remote")]
+pub mod remote;
+#[cfg(feature = "carve")]
+pub mod carve;
+
+pub use crate::client::Client;
 
 cfg_if! {
     if #[cfg(all(target_os = "linux",fuzzing))] {
@@ -0,0 +1,379 @@
+use libc::{
+    c_int, inotify_add_watch, inotify_event, inotify_init1, read, IN_ATTRIB, IN_CLOEXEC,
+    IN_CREATE, IN_DELETE, IN_DELETE_SELF, IN_IGNORED, IN_ISDIR, IN_MODIFY, IN_MOVED_FROM,
+    IN_MOVED_TO, IN_Q_OVERFLOW,
+};
+use glob::glob;
+use std::collections::HashMap;
+use std::ffi::CString;
+use std::fs::read_dir;
+use std::io;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use std::path::{Path, PathBuf};
+use rusqlite::types::Value;
+use std::ptr;
+use std::sync::Mutex;
+use std::thread;
+
+use crate::events::{unix_time, EventBuffer, DEFAULT_EVENTS_CAPACITY};
+use crate::profile::record_warning;
+use crate::query::{Constraint, ConstraintOp};
+use crate::tables::FileEventsRow;
+
+// [1] http://man7.org/linux/man-pages/man7/inotify.7.html
+
+const WATCH_MASK: u32 = IN_CREATE | IN_MODIFY | IN_DELETE | IN_DELETE_SELF |
+    IN_MOVED_FROM | IN_MOVED_TO | IN_ATTRIB;
+
+lazy_static! {
+    static ref FILE_EVENTS: Mutex<EventBuffer<FileEvent>> =
+        Mutex::new(EventBuffer::new(DEFAULT_EVENTS_CAPACITY));
+    static ref DRAIN_ON_READ: Mutex<bool> = Mutex::new(false);
+}
+
+#[derive(Clone, Debug)]
+pub struct FileEvent {
+    pub target_path: String,
+    pub category: String,
+    pub action: &'static str,
+    pub transaction_id: i64,
+}
+
+pub struct FileEventsConfig {
+    /// Paths or glob patterns to watch. A trailing `/**` or `%%` watches recursively.
+    pub paths: Vec<String>,
+    /// Maximum number of buffered events.
+    pub capacity: usize,
+    /// Whether reading the `file_events` table consumes the buffered events.
+    pub drain: bool,
+}
+
+impl Default for FileEventsConfig {
+    fn default() -> FileEventsConfig {
+        FileEventsConfig {
+            paths: Vec::new(),
+            capacity: DEFAULT_EVENTS_CAPACITY,
+            drain: false,
+        }
+    }
+}
+
+struct WatchedPath {
+    path: PathBuf,
+    category: String,
+    recursive: bool,
+}
+
+struct Watcher {
+    fd: c_int,
+    watches: HashMap<c_int, WatchedPath>,
+}
+
+struct RawEvent {
+    wd: c_int,
+    mask: u32,
+    cookie: u32,
+    name: Vec<u8>,
+}
+
+/// Starts watching the configured paths in a background thread.
+///
+/// Events only accumulate while the process keeps running, so this is meant for
+/// long-lived modes such as `--interval`.
+pub fn start(config: FileEventsConfig) -> io::Result<()> {
+    if let Ok(mut buffer) = FILE_EVENTS.lock() {
+        buffer.set_capacity(config.capacity);
+    }
+    if let Ok(mut drain) = DRAIN_ON_READ.lock() {
+        *drain = config.drain;
+    }
+
+    let fd = unsafe { inotify_init1(IN_CLOEXEC) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let mut watcher = Watcher { fd, watches: HashMap::new() };
+    for pattern in config.paths.iter() {
+        let (paths, recursive) = expand_pattern(pattern);
+        for path in paths {
+            watcher.add_watch(&path, pattern, recursive);
+        }
+    }
+    if watcher.watches.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, "no watchable paths"));
+    }
+
+    thread::spawn(move || watcher.run());
+    Ok(())
+}
+
+/// Splits a configured path into the glob matches to watch and whether to recurse.
+fn expand_pattern(pattern: &str) -> (Vec<PathBuf>, bool) {
+    let (pattern, recursive) = if pattern.ends_with("/**") {
+        (&pattern[..pattern.len() - 3], true)
+    } else if pattern.ends_with("%%") {
+        (pattern.trim_end_matches('%').trim_end_matches('/'), true)
+    } else {
+        (pattern, false)
+    };
+    let paths = match glob(pattern) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok()).collect(),
+        Err(_) => vec![PathBuf::from(pattern)],
+    };
+    (paths, recursive)
+}
+
+fn action_name(mask: u32) -> Option<&'static str> {
+    if mask & IN_CREATE != 0 {
+        Some("CREATED")
+    } else if mask & IN_MODIFY != 0 {
+        Some("MODIFIED")
+    } else if mask & (IN_DELETE | IN_DELETE_SELF) != 0 {
+        Some("DELETED")
+    } else if mask & IN_MOVED_FROM != 0 {
+        Some("MOVED_FROM")
+    } else if mask & IN_MOVED_TO != 0 {
+        Some("MOVED_TO")
+    } else if mask & IN_ATTRIB != 0 {
+        Some("ATTRIBUTES")
+    } else {
+        None
+    }
+}
+
+/// Splits a buffer filled by `read(2)` on an inotify descriptor into events. [1]
+fn parse_events(buf: &[u8]) -> Vec<RawEvent> {
+    let mut events = Vec::new();
+    let header_size = mem::size_of::<inotify_event>();
+    let mut offset = 0;
+    while offset + header_size <= buf.len() {
+        let header: inotify_event = unsafe {
+            ptr::read_unaligned(buf[offset..].as_ptr() as *const inotify_event)
+        };
+        let name_start = offset + header_size;
+        let name_end = name_start + header.len as usize;
+        if name_end > buf.len() {
+            break;
+        }
+        let name: Vec<u8> = buf[name_start..name_end].iter()
+            .take_while(|c| **c != 0)
+            .cloned()
+            .collect();
+        events.push(RawEvent {
+            wd: header.wd,
+            mask: header.mask,
+            cookie: header.cookie,
+            name,
+        });
+        offset = name_end;
+    }
+    events
+}
+
+impl Watcher {
+    fn add_watch(&mut self, path: &Path, category: &str, recursive: bool) {
+        let c_path = match CString::new(path.as_os_str().as_bytes()) {
+            Ok(c_path) => c_path,
+            Err(_) => return,
+        };
+        let wd = unsafe { inotify_add_watch(self.fd, c_path.as_ptr(), WATCH_MASK) };
+        if wd < 0 {
+            record_warning("file_events", &format!("could not watch {}: {}", path.display(), io::Error::last_os_error()));
+            return;
+        }
+        self.watches.insert(wd, WatchedPath {
+            path: path.to_path_buf(),
+            category: category.to_string(),
+            recursive,
+        });
+        if recursive {
+            if let Ok(entries) = read_dir(path) {
+                for entry in entries.filter_map(|entry| entry.ok()) {
+                    let child = entry.path();
+                    if child.is_dir() {
+                        self.add_watch(&child, category, recursive);
+                    }
+                }
+            }
+        }
+    }
+
+    fn run(mut self) {
+        let mut buf = [0u8; 4096];
+        loop {
+            let len = unsafe { read(self.fd, buf.as_mut_ptr() as *mut _, buf.len()) };
+            if len < 0 {
+                if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                    continue;
+                }
+                record_warning("file_events", &format!("read failed: {}", io::Error::last_os_error()));
+                return;
+            }
+            for event in parse_events(&buf[..len as usize]) {
+                self.handle(event);
+            }
+        }
+    }
+
+    fn handle(&mut self, event: RawEvent) {
+        if event.mask & IN_Q_OVERFLOW != 0 {
+            record_warning("file_events", "inotify queue overflow, events were lost");
+            return;
+        }
+        if event.mask & IN_IGNORED != 0 {
+            self.watches.remove(&event.wd);
+            return;
+        }
+        let (path, category, recursive) = match self.watches.get(&event.wd) {
+            Some(watched) => {
+                let mut path = watched.path.clone();
+                if !event.name.is_empty() {
+                    path.push(std::ffi::OsStr::from_bytes(&event.name));
+                }
+                (path, watched.category.clone(), watched.recursive)
+            },
+            None => return,
+        };
+        if recursive && event.mask & IN_ISDIR != 0 && event.mask & (IN_CREATE | IN_MOVED_TO) != 0 {
+            self.add_watch(&path, &category, recursive);
+        }
+        if let Some(action) = action_name(event.mask) {
+            if let Ok(mut buffer) = FILE_EVENTS.lock() {
+                buffer.push(unix_time(), FileEvent {
+                    target_path: path.to_string_lossy().into_owned(),
+                    category,
+                    action,
+                    transaction_id: event.cookie as i64,
+                });
+            }
+        }
+    }
+}
+
+/// Earliest time the `time >` and `time >=` constraints of a query allow.
+fn time_lower_bound(constraints: &[Constraint]) -> Option<i64> {
+    constraints.iter().filter(|constraint| constraint.column == "time").filter_map(|constraint| {
+        let (time, fraction) = match constraint.value {
+            Value::Integer(time) => (time, false),
+            Value::Real(time) => (time.floor() as i64, time.fract() != 0.),
+            Value::Text(ref time) => (time.trim().parse().ok()?, false),
+            _ => return None,
+        };
+        match constraint.op {
+            ConstraintOp::Ge if fraction => Some(time.saturating_add(1)),
+            ConstraintOp::Ge => Some(time),
+            ConstraintOp::Gt => Some(time.saturating_add(1)),
+            _ => None,
+        }
+    }).max()
+}
+
+impl FileEventsRow {
+    pub fn get_specific() -> Vec<FileEventsRow> {
+        FileEventsRow::get_constrained(&[])
+    }
+
+    /// Rows of the events allowed by the `time` lower bound of a query, all of them without one.
+    /// With `--drain_events` only those events are consumed, the older ones stay buffered.
+    pub(crate) fn get_constrained(constraints: &[Constraint]) -> Vec<FileEventsRow> {
+        let drain = DRAIN_ON_READ.lock().map(|drain| *drain).unwrap_or(false);
+        let since = time_lower_bound(constraints).unwrap_or(i64::MIN);
+        let events = match FILE_EVENTS.lock() {
+            Ok(mut buffer) => if drain { buffer.drain_since(since) } else { buffer.since(since) },
+            Err(_) => Vec::new(),
+        };
+        events.into_iter().map(|event| {
+            FileEventsRow {
+                target_path: event.data.target_path,
+                category: event.data.category,
+                action: event.data.action.to_string(),
+                transaction_id: event.data.transaction_id,
+                time: event.time,
+                eid: event.eid as i64,
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn raw_event(wd: i32, mask: u32, cookie: u32, name: &[u8], len: u32) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&wd.to_ne_bytes());
+        buf.extend_from_slice(&mask.to_ne_bytes());
+        buf.extend_from_slice(&cookie.to_ne_bytes());
+        buf.extend_from_slice(&len.to_ne_bytes());
+        let mut padded = name.to_vec();
+        padded.resize(len as usize, 0);
+        buf.extend_from_slice(&padded);
+        buf
+    }
+
+    #[test]
+    fn test_parse_events() {
+        let mut buf = raw_event(1, IN_MOVED_FROM, 42, b"a.txt", 16);
+        buf.extend(raw_event(2, IN_MOVED_TO, 42, b"b.txt", 16));
+        buf.extend(raw_event(1, IN_DELETE_SELF, 0, b"", 0));
+        let events = parse_events(&buf);
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].wd, 1);
+        assert_eq!(events[0].name, b"a.txt".to_vec());
+        assert_eq!(events[1].cookie, 42);
+        assert_eq!(action_name(events[1].mask), Some("MOVED_TO"));
+        assert!(events[2].name.is_empty());
+        assert_eq!(action_name(events[2].mask), Some("DELETED"));
+    }
+
+    #[test]
+    fn test_time_lower_bound() {
+        let time = |op, value| Constraint { column: "time", op, value };
+        assert_eq!(time_lower_bound(&[]), None);
+        assert_eq!(time_lower_bound(&[time(ConstraintOp::Ge, Value::Integer(100))]), Some(100));
+        assert_eq!(time_lower_bound(&[time(ConstraintOp::Gt, Value::Integer(100))]), Some(101));
+        assert_eq!(time_lower_bound(&[time(ConstraintOp::Ge, Value::Real(99.5))]), Some(100));
+        assert_eq!(time_lower_bound(&[time(ConstraintOp::Gt, Value::Real(99.5))]), Some(100));
+        assert_eq!(time_lower_bound(&[time(ConstraintOp::Ge, Value::Text("50".to_string())),
+                                      time(ConstraintOp::Gt, Value::Integer(80))]), Some(81));
+    }
+
+    #[test]
+    fn test_file_events_time_constraint() {
+        {
+            let mut buffer = FILE_EVENTS.lock().unwrap();
+            buffer.drain();
+            for (time, path) in [(100, "/tmp/a"), (200, "/tmp/b"), (300, "/tmp/c")].iter() {
+                buffer.push(*time, FileEvent {
+                    target_path: path.to_string(),
+                    category: "tmp".to_string(),
+                    action: "CREATED",
+                    transaction_id: 0,
+                });
+            }
+        }
+        let db = crate::query::init_db();
+        let rows = crate::query::execute_query_json(&db, "select target_path from file_events where time >= 200", &[]).unwrap();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0]["target_path"], "/tmp/b");
+
+        // Draining consumes the events the query returns only.
+        *DRAIN_ON_READ.lock().unwrap() = true;
+        let db = crate::query::init_db();
+        let rows = crate::query::execute_query_json(&db, "select target_path from file_events where time > 200", &[]).unwrap();
+        *DRAIN_ON_READ.lock().unwrap() = false;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0]["target_path"], "/tmp/c");
+        let left: Vec<String> = FILE_EVENTS.lock().unwrap().drain().into_iter().map(|e| e.data.target_path).collect();
+        assert_eq!(left, vec!["/tmp/a", "/tmp/b"]);
+    }
+
+    #[test]
+    fn test_action_name() {
+        assert_eq!(action_name(IN_CREATE | IN_ISDIR), Some("CREATED"));
+        assert_eq!(action_name(IN_MODIFY), Some("MODIFIED"));
+        assert_eq!(action_name(IN_ATTRIB), Some("ATTRIBUTES"));
+        assert_eq!(action_name(IN_IGNORED), None);
+    }
+}
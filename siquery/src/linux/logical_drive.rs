@@ -1,21 +1,258 @@
-use crate::tables::LogicalDrive;
+use std::borrow::Borrow;
+use std::collections::HashSet;
+use std::ffi::CString;
+use std::fs::{self, File};
+use std::io::Read;
+use std::mem;
+use std::os::unix::ffi::OsStrExt;
+use libc::statvfs;
+
+use crate::options::root_path;
+use crate::tables::{LogicalDrive,LogicalDriveIface};
+
+const NETWORK_FILE_SYSTEMS: &[&str] = &[
+    "nfs", "nfs4", "cifs", "smb3", "smbfs", "ncpfs", "afs", "9p", "ceph", "glusterfs", "fuse.glusterfs",
+    "fuse.sshfs", "sshfs", "davfs",
+];
+const COMPRESSING_FILE_SYSTEMS: &[&str] = &["btrfs", "zfs", "f2fs"];
+
+pub struct Reader {}
+impl LogicalDriveIface for Reader {
+    fn get_wmi_drives_info(&self) -> Option<String> {
+        Some(String::from("For windows only"))
+    }
+    fn mounts(&self) -> Option<String> {
+        let mut s = String::new();
+        File::open(root_path("/proc/mounts")).ok()?.read_to_string(&mut s).ok()?;
+        Some(s)
+    }
+    fn block_device(&self, device: &str) -> Option<String> {
+        let path = fs::canonicalize(root_path(device)).ok()?;
+        let name = path.file_name()?.to_str()?.to_string();
+        if root_path("/sys/class/block").join(&name).exists() {
+            Some(name)
+        } else {
+            None
+        }
+    }
+    fn block_attribute(&self, name: &str, attribute: &str) -> Option<String> {
+        let device = root_path("/sys/class/block").join(name);
+        let path = if device.join("partition").exists() {
+            device.join("..").join(attribute)
+        } else {
+            device.join(attribute)
+        };
+        Some(fs::read_to_string(path).ok()?.trim().to_string())
+    }
+    fn filesystem_uuids(&self) -> Vec<(String, String)> {
+        let mut uuids = Vec::new();
+        if let Ok(entries) = fs::read_dir(root_path("/dev/disk/by-uuid")) {
+            for entry in entries.filter_map(|entry| entry.ok()) {
+                let target = fs::read_link(entry.path()).ok();
+                let name = target.as_ref().and_then(|target| target.file_name()).and_then(|name| name.to_str());
+                if let (Some(name), Some(uuid)) = (name, entry.file_name().to_str()) {
+                    uuids.push((name.to_string(), uuid.to_string()));
+                }
+            }
+        }
+        uuids
+    }
+    fn filesystem_stats(&self, mount_point: &str) -> Option<(i64, i64, i64)> {
+        let path = CString::new(root_path(mount_point).as_os_str().as_bytes()).ok()?;
+        let mut st: statvfs = unsafe { mem::zeroed() };
+        if unsafe { statvfs(path.as_ptr(), &mut st) } != 0 {
+            return None;
+        }
+        let fragment = st.f_frsize as i64;
+        Some((st.f_blocks as i64 * fragment, st.f_bavail as i64 * fragment, st.f_namemax as i64))
+    }
+}
+
+/// Undoes the octal escapes of spaces, tabs and backslashes in `/proc/mounts`.
+fn unescape(field: &str) -> String {
+    let mut out = String::new();
+    let mut rest = field;
+    while let Some(i) = rest.find('\\') {
+        out.push_str(&rest[..i]);
+        let code = rest.get(i + 1..i + 4).and_then(|code| u8::from_str_radix(code, 8).ok());
+        match code {
+            Some(code) => {
+                out.push(code as char);
+                rest = &rest[i + 4..];
+            },
+            None => {
+                out.push('\\');
+                rest = &rest[i + 1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Drive type and description of a block device, like the `DriveType` of Windows logical disks.
+fn block_drive_type(reader: &dyn LogicalDriveIface, name: &str) -> (&'static str, &'static str) {
+    if name.starts_with("ram") || name.starts_with("zram") {
+        ("ram", "RAM Disk")
+    } else if name.starts_with("sr") || reader.block_attribute(name, "device/type").as_deref() == Some("5") {
+        ("optical", "CD-ROM Disc")
+    } else if reader.block_attribute(name, "removable").as_deref() == Some("1") {
+        ("removable", "Removable Disk")
+    } else {
+        ("fixed", "Local Fixed Disk")
+    }
+}
 
-#[cfg(not(fuzzing))]    // Since not yet implemented for linux, disable this implementation when fuzzing to allow fuzzing interface_address for windows
 impl LogicalDrive {
-    pub(crate) fn get_specific() -> Vec<LogicalDrive> {
-        // TODO: implement logical_drive table
+    pub(crate) fn new_logical_drive() -> LogicalDrive {
+        LogicalDrive {
+            description: String::new(),
+            drive_type: String::new(),
+            file_system: String::new(),
+            free_space: 0,
+            maximum_component_length: 0,
+            name: String::new(),
+            size: 0,
+            supports_file_based_compression: String::new(),
+            volume_serial_number: String::new(),
+        }
+    }
+
+    /// One drive per mounted block device, at its first mount point, and per network filesystem.
+    /// Loop devices, which back snaps and images, are left out. Network filesystems get no size.
+    pub(crate) fn get_specific_ex(reader: &dyn LogicalDriveIface) -> Vec<LogicalDrive> {
+        let mut drives = Vec::new();
+        let mounts = match reader.mounts() {
+            Some(mounts) => mounts,
+            None => return drives,
+        };
+        let uuids = reader.filesystem_uuids();
+        let mut seen: HashSet<String> = HashSet::new();
+        for line in mounts.lines() {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.len() < 3 {
+                continue;
+            }
+            let device = unescape(fields[0]);
+            let mount_point = unescape(fields[1]);
+            let file_system = fields[2];
+            let mut drive = LogicalDrive::new_logical_drive();
+            let network = NETWORK_FILE_SYSTEMS.contains(&file_system);
+            let (drive_type, description) = if network {
+                ("network", "Network Connection")
+            } else {
+                let name = match reader.block_device(&device) {
+                    Some(name) => name,
+                    None => continue,
+                };
+                if name.starts_with("loop") {
+                    continue;
+                }
+                drive.volume_serial_number = uuids.iter()
+                    .find(|(device_name, _)| *device_name == name)
+                    .map(|(_, uuid)| uuid.clone())
+                    .unwrap_or_default();
+                block_drive_type(reader, &name)
+            };
+            if !seen.insert(device.clone()) {
+                continue;
+            }
+            // statvfs blocks on an unresponsive server, so network filesystems are left unsized.
+            let stats = if network { None } else { reader.filesystem_stats(&mount_point) };
+            if let Some((size, free_space, maximum_component_length)) = stats {
+                drive.size = size;
+                drive.free_space = free_space;
+                drive.maximum_component_length = maximum_component_length;
+            }
+            drive.name = device;
+            drive.drive_type = drive_type.to_string();
+            drive.description = format!("{} mounted on {}", description, mount_point);
+            drive.file_system = file_system.to_string();
+            drive.supports_file_based_compression = COMPRESSING_FILE_SYSTEMS.contains(&file_system).to_string();
+            drives.push(drive);
+        }
+        drives
+    }
 
-        let mut output = Vec::new();
+    pub(crate) fn get_specific() -> Vec<LogicalDrive> {
+        let reader: Box<dyn LogicalDriveIface> = Box::new(Reader{});
+        let out = LogicalDrive::get_specific_ex(reader.borrow());
+        out
+    }
+}
 
-        output.push(
-            LogicalDrive {
-                device_id: String::new(),
-                drive_type: String::new(),
-                free_space: 0,
-                size: 0,
-                file_system: String::new(),
+#[cfg(test)]
+mod tests {
+    use super::*;
+    pub struct Test {}
+    impl LogicalDriveIface for Test {
+        fn get_wmi_drives_info(&self) -> Option<String> {
+            Some(String::from("For windows only"))
+        }
+        fn mounts(&self) -> Option<String> {
+            Some(String::from(include_str!("../../test_data/proc-mounts.txt")))
+        }
+        fn block_device(&self, device: &str) -> Option<String> {
+            match device {
+                "/dev/mapper/vg-root" => Some(String::from("dm-0")),
+                "/dev/nvme0n1p1" | "/dev/sdb1" | "/dev/sr0" | "/dev/ram0" | "/dev/loop3" => {
+                    Some(device.trim_start_matches("/dev/").to_string())
+                },
+                _ => None,
+            }
+        }
+        fn block_attribute(&self, name: &str, attribute: &str) -> Option<String> {
+            match (name, attribute) {
+                ("sdb1", "removable") => Some(String::from("1")),
+                (_, "removable") => Some(String::from("0")),
+                _ => None,
+            }
+        }
+        fn filesystem_uuids(&self) -> Vec<(String, String)> {
+            vec![
+                (String::from("dm-0"), String::from("2f5c7e1a-9d4b-4c1e-8f3a-6b2d0e9c1a7f")),
+                (String::from("nvme0n1p1"), String::from("4A1B-2C3D")),
+            ]
+        }
+        fn filesystem_stats(&self, mount_point: &str) -> Option<(i64, i64, i64)> {
+            match mount_point {
+                "/" => Some((502384795648, 121348456448, 255)),
+                "/home" | "/mnt/share" => Some((1, 1, 1)),
+                _ => Some((0, 0, 255)),
             }
-        );
-        output
+        }
+    }
+    #[test]
+    fn test_logical_drives() {
+        let reader: Box<dyn LogicalDriveIface> = Box::new(Test {});
+        let drives = LogicalDrive::get_specific_ex(reader.borrow());
+        let names: Vec<&str> = drives.iter().map(|drive| drive.name.as_str()).collect();
+        assert_eq!(names, vec!["/dev/mapper/vg-root", "/dev/nvme0n1p1", "/dev/sdb1", "/dev/sr0", "/dev/ram0",
+                               "fileserver:/export/home", "//nas/share"]);
+        assert_eq!(drives[0].drive_type, "fixed");
+        assert_eq!(drives[0].description, "Local Fixed Disk mounted on /");
+        assert_eq!(drives[0].file_system, "ext4");
+        assert_eq!(drives[0].size, 502384795648);
+        assert_eq!(drives[0].free_space, 121348456448);
+        assert_eq!(drives[0].maximum_component_length, 255);
+        assert_eq!(drives[0].volume_serial_number, "2f5c7e1a-9d4b-4c1e-8f3a-6b2d0e9c1a7f");
+        assert_eq!(drives[1].volume_serial_number, "4A1B-2C3D");
+        assert_eq!(drives[2].drive_type, "removable");
+        assert_eq!(drives[2].description, "Removable Disk mounted on /media/user/USB STICK");
+        assert_eq!(drives[3].drive_type, "optical");
+        assert_eq!(drives[4].drive_type, "ram");
+        assert_eq!(drives[5].drive_type, "network");
+        assert_eq!(drives[5].file_system, "nfs4");
+        assert_eq!(drives[5].volume_serial_number, "");
+        assert_eq!(drives[5].size, 0);
+        assert_eq!(drives[5].maximum_component_length, 0);
+        assert_eq!(drives[6].drive_type, "network");
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape("/media/USB\\040STICK"), "/media/USB STICK");
+        assert_eq!(unescape("a\\134b\\011"), "a\\b\t");
+        assert_eq!(unescape("trailing\\"), "trailing\\");
     }
-}
\ No newline at end of file
+}
@@ -1,31 +1,33 @@
 use std::fs::File;
 use std::io::Read;
+use crate::options::root_path;
 #[allow(unused_imports)]
 // TODO implement to_json
 use serde_json;
 #[cfg(feature = "etc_hosts")] use crate::tables::EtcHostsIface;
 #[cfg(feature = "etc_services")] use crate::tables::EtcServicesIface;
 #[cfg(feature = "etc_protocols")] use crate::tables::EtcProtocolsIface;
-#[cfg(feature = "logical_drive")] mod logical_drive;
+#[cfg(feature = "logical_drives")] mod logical_drive;
 #[cfg(feature = "interface_address")] mod interface_address;
 #[cfg(feature = "interface_details")] mod interface_details;
 #[cfg(feature = "os_version")] mod os_version;
 #[cfg(feature = "system_info")] mod system_info;
 #[cfg(feature = "uptime")] mod uptime;
 #[cfg(feature = "process_open_sockets")] mod process_open_sockets;
-#[cfg(feature = "processes")] mod processes;
+#[cfg(feature = "processes")] pub(crate) mod processes;
 #[cfg(feature = "mounts")] mod mounts;
 #[cfg(feature = "groups")] mod groups;
 #[cfg(feature = "users")] mod users;
 #[cfg(feature = "logged_in_users")] mod logged_in_users;
 #[cfg(feature = "proxies")] mod proxies;
+#[cfg(feature = "file_events")] pub mod file_events;
 
 pub struct EtcHostsReader {}
 #[cfg(feature = "etc_hosts")]
 impl EtcHostsIface for EtcHostsReader {
     fn get_hosts_file(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/hosts").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/etc/hosts")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
 }
@@ -35,7 +37,7 @@ pub struct EtcProtocolsReader {}
 impl EtcProtocolsIface for EtcProtocolsReader {
     fn get_protocols_file(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/protocols").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/etc/protocols")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
 }
@@ -45,7 +47,7 @@ pub struct EtcServicesReader {}
 impl EtcServicesIface for EtcServicesReader {
     fn get_services_file(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/services").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/etc/services")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
 }
@@ -1,10 +1,14 @@
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::fs::File;
 use std::io::Read;
 use uname;
 
+use crate::options::root_path;
 use crate::tables::{OsVersion,OsVersionIface};
 
+const OS_RELEASE_FILES: &[&str] = &["/etc/os-release", "/usr/lib/os-release"];
+
 pub struct Reader {
     uname_info: Option<uname::Info>,
 }
@@ -21,9 +25,9 @@ impl OsVersionIface for Reader {
     fn get_os_info(&self) -> Option<String> {
         Some(String::from("For windows only"))
     }
-    fn os_release(&self) -> Option<String> {
+    fn release_file(&self, path: &str) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/os-release").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path(path)).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
     fn os_platform(&self) -> Option<String> {
@@ -32,59 +36,177 @@ impl OsVersionIface for Reader {
             None => None
         }
     }
+    fn kernel_release(&self) -> Option<String> {
+        self.uname_info.as_ref().map(|info| info.release.clone())
+    }
+}
+
+/// Removes the quotes of an os-release value. Inside double quotes a backslash escapes `"`, `\`,
+/// `$` and `` ` ``; outside of quotes it escapes any character.
+fn unquote(value: &str) -> String {
+    let mut out = String::new();
+    let mut quote: Option<char> = None;
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (None, '"') | (None, '\'') => quote = Some(c),
+            (Some(q), c) if c == q => quote = None,
+            (Some('"'), '\\') => match chars.peek() {
+                Some(&next) if "\"\\$`".contains(next) => {
+                    out.push(next);
+                    chars.next();
+                },
+                _ => out.push('\\'),
+            },
+            (None, '\\') => {
+                if let Some(next) = chars.next() {
+                    out.push(next);
+                }
+            },
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Parses the `KEY=value` lines of os-release(5), also used by `/etc/lsb-release`.
+fn parse_release(content: &str) -> HashMap<String, String> {
+    content.lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), unquote(value.trim())))
+        .collect()
+}
+
+/// Major, minor and patch numbers of a version such as `22.04.4`, ignoring any suffix.
+fn version_numbers(version: &str) -> (u32, u32, u32) {
+    let mut numbers = version.split('.').map(|part| {
+        let digits: String = part.chars().take_while(|c| c.is_ascii_digit()).collect();
+        digits.parse::<u32>().unwrap_or(0)
+    });
+    (numbers.next().unwrap_or(0), numbers.next().unwrap_or(0), numbers.next().unwrap_or(0))
+}
+
+/// The `x.y.z` prefix of `detailed` when it refines `version`, as `22.04.4 LTS` refines `22.04`.
+fn refined_version(version: &str, detailed: &str) -> Option<String> {
+    let rest = detailed.trim().strip_prefix(version)?.strip_prefix('.')?;
+    let patch: String = rest.chars().take_while(|c| c.is_ascii_digit() || *c == '.').collect();
+    if patch.is_empty() {
+        None
+    } else {
+        Some(format!("{}.{}", version, patch.trim_end_matches('.')))
+    }
+}
+
+/// Text between the parentheses of `9.3 (Plow)`.
+fn parenthesized(text: &str) -> Option<String> {
+    let start = text.find('(')? + 1;
+    let end = start + text[start..].find(')')?;
+    Some(text[start..end].trim().to_string())
 }
 
 impl OsVersion {
-    pub(crate) fn get_specific_ex(reader: &OsVersionIface) -> Vec<OsVersion> {
-        let mut output : Vec<OsVersion> = Vec::new();
-        let os_release = reader.os_release();
-        let name = match os_release {
-            Some(ref s) => {
-                let n = s.split('\n').find(|line| line.starts_with("NAME"))
-                    .and_then(|line| line.split('=').last())
-                    .and_then(|val| Some(val.replace("\"", "")));
-                n.unwrap_or_else(|| String::from(""))
-            }
+    pub(crate) fn new() -> OsVersion {
+        OsVersion {
+            name: String::new(),
+            platform: String::new(),
+            version: String::new(),
+            major: 0,
+            minor: 0,
+            patch: 0,
+            build: String::new(),
+            id: String::new(),
+            id_like: String::new(),
+            codename: String::new(),
+            kernel_release: String::new(),
+        }
+    }
 
-            None => String::from("")
-        };
+    fn read_os_release(&mut self, fields: &HashMap<String, String>) {
+        let field = |key: &str| fields.get(key).cloned().unwrap_or_default();
+        self.name = fields.get("NAME").cloned().unwrap_or_else(|| String::from("Linux"));
+        self.id = fields.get("ID").cloned().unwrap_or_else(|| String::from("linux"));
+        self.id_like = field("ID_LIKE");
+        self.version = field("VERSION_ID");
+        if let Some(version) = refined_version(&self.version, &field("VERSION")) {
+            self.version = version;
+        }
+        self.build = field("BUILD_ID");
+        self.codename = fields.get("VERSION_CODENAME")
+            .or_else(|| fields.get("UBUNTU_CODENAME"))
+            .cloned()
+            .or_else(|| parenthesized(&field("VERSION")))
+            .unwrap_or_default();
+    }
 
-        let version = match os_release {
-            Some(ref s) => {
-                let n = s.split('\n').find(|line| line.starts_with("VERSION_ID"))
-                    .and_then(|line| line.split('=').last())
-                    .and_then(|val| Some(val.replace("\"", "")));
-                n.unwrap_or_else(|| String::from(""))
-            }
+    fn read_lsb_release(&mut self, fields: &HashMap<String, String>) {
+        let field = |key: &str| fields.get(key).cloned().unwrap_or_default();
+        self.name = field("DISTRIB_ID");
+        self.id = self.name.to_lowercase();
+        self.version = field("DISTRIB_RELEASE");
+        self.codename = field("DISTRIB_CODENAME");
+    }
 
-            None => String::from("")
+    /// Parses `Red Hat Enterprise Linux Server release 6.10 (Santiago)`.
+    fn read_redhat_release(&mut self, content: &str) {
+        let line = content.lines().next().unwrap_or("").trim();
+        let (name, rest) = line.split_once(" release ").unwrap_or((line, ""));
+        self.name = name.to_string();
+        self.id = if name.starts_with("Red Hat") {
+            String::from("rhel")
+        } else {
+            name.split_whitespace().next().unwrap_or("").to_lowercase()
         };
+        self.id_like = String::from("fedora");
+        self.version = rest.split_whitespace().next().unwrap_or("").to_string();
+        self.codename = parenthesized(rest).unwrap_or_default();
+    }
+
+    /// `/etc/debian_version` holds a point release such as `12.5`, or `trixie/sid` on testing.
+    fn read_debian_version(&mut self, content: &str) {
+        let content = content.trim();
+        self.name = String::from("Debian GNU/Linux");
+        self.id = String::from("debian");
+        if content.starts_with(|c: char| c.is_ascii_digit()) {
+            self.version = content.to_string();
+        } else {
+            self.codename = content.split('/').next().unwrap_or("").to_string();
+        }
+    }
 
-        let mut major = 0;
-        let mut minor = 0;
+    /// Reads os-release, then the older lsb-release, redhat-release and debian_version files.
+    pub(crate) fn get_specific_ex(reader: &dyn OsVersionIface) -> Vec<OsVersion> {
+        let mut os_version = OsVersion::new();
+        os_version.platform = reader.os_platform().unwrap_or_else(|| String::from(""));
+        os_version.kernel_release = reader.kernel_release().unwrap_or_default();
+        let debian_version = reader.release_file("/etc/debian_version");
 
-        if !version.is_empty() {
-            let v: Vec<_> = version.split('.').collect();
-            if v.len() == 2 {
-                major = v[0].parse::<u32>().unwrap_or(0);
-                minor = v[1].parse::<u32>().unwrap_or(0);
+        if let Some(os_release) = OS_RELEASE_FILES.iter().find_map(|path| reader.release_file(path)) {
+            os_version.read_os_release(&parse_release(&os_release));
+            if os_version.id == "debian" {
+                let refined = debian_version.as_ref().and_then(|v| refined_version(&os_version.version, v));
+                if let Some(version) = refined {
+                    os_version.version = version;
+                }
             }
+        } else if let Some(lsb_release) = reader.release_file("/etc/lsb-release") {
+            os_version.read_lsb_release(&parse_release(&lsb_release));
+        } else if let Some(redhat_release) = reader.release_file("/etc/redhat-release") {
+            os_version.read_redhat_release(&redhat_release);
+        } else if let Some(ref debian_version) = debian_version {
+            os_version.read_debian_version(debian_version);
         }
 
-        output.push(
-            OsVersion {
-                name,
-                platform: reader.os_platform().unwrap_or_else(|| String::from("")),
-                version,
-                major,
-                minor,
-            }
-        );
-        output
+        let (major, minor, patch) = version_numbers(&os_version.version);
+        os_version.major = major;
+        os_version.minor = minor;
+        os_version.patch = patch;
+        vec![os_version]
     }
 
     pub(crate) fn get_specific() -> Vec<OsVersion> {
-        let reader: Box<OsVersionIface> = Box::new(Reader::new());
+        let reader: Box<dyn OsVersionIface> = Box::new(Reader::new());
         let out = OsVersion::get_specific_ex(reader.borrow());
         out
     }
@@ -93,25 +215,91 @@ impl OsVersion {
 #[cfg(test)]
 mod tests {
     use super::*;
-    pub struct Test {}
+    pub struct Test {
+        files: Vec<(&'static str, &'static str)>,
+    }
     impl OsVersionIface for Test {
         fn get_os_info(&self) -> Option<String> {
             Some(String::from("For windows only"))
         }
-        fn os_release(&self) -> Option<String> {
-            Some(String::from(include_str!("../../test_data/os-release.txt")))
+        fn release_file(&self, path: &str) -> Option<String> {
+            self.files.iter().find(|(file, _)| *file == path).map(|(_, content)| String::from(*content))
         }
         fn os_platform(&self) -> Option<String> {
             Some(String::from("Linux"))
         }
+        fn kernel_release(&self) -> Option<String> {
+            Some(String::from("6.1.0-18-amd64"))
+        }
+    }
+    fn os_version(files: Vec<(&'static str, &'static str)>) -> OsVersion {
+        let reader: Box<dyn OsVersionIface> = Box::new(Test{files});
+        OsVersion::get_specific_ex(reader.borrow()).remove(0)
     }
     #[test]
     fn test_system_info () {
-        let reader: Box<OsVersionIface> = Box::new(Test{});
-        let os_version = &OsVersion::get_specific_ex(reader.borrow())[0];
+        let os_version = os_version(vec![("/etc/os-release", include_str!("../../test_data/os-release.txt"))]);
         assert_eq!(os_version.platform, "Linux");
         assert_eq!(os_version.name, "Ubuntu");
         assert_eq!(os_version.version, "17.10");
         assert_eq!(os_version.major, 17);
-        assert_eq!(os_version.minor, 10);}
-}
\ No newline at end of file
+        assert_eq!(os_version.minor, 10);
+        assert_eq!(os_version.kernel_release, "6.1.0-18-amd64");
+    }
+    #[test]
+    fn test_os_release() {
+        let debian = os_version(vec![
+            ("/usr/lib/os-release", include_str!("../../test_data/os-release-debian.txt")),
+            ("/etc/debian_version", include_str!("../../test_data/debian_version.txt")),
+        ]);
+        assert_eq!((debian.name.as_str(), debian.id.as_str(), debian.codename.as_str()),
+                   ("Debian GNU/Linux", "debian", "bookworm"));
+        assert_eq!((debian.version.as_str(), debian.major, debian.minor, debian.patch), ("12.5", 12, 5, 0));
+
+        let ubuntu = os_version(vec![("/etc/os-release", include_str!("../../test_data/os-release-ubuntu.txt"))]);
+        assert_eq!((ubuntu.id.as_str(), ubuntu.id_like.as_str(), ubuntu.codename.as_str()), ("ubuntu", "debian", "jammy"));
+        assert_eq!((ubuntu.version.as_str(), ubuntu.major, ubuntu.minor, ubuntu.patch), ("22.04.4", 22, 4, 4));
+
+        let rhel = os_version(vec![("/etc/os-release", include_str!("../../test_data/os-release-rhel.txt"))]);
+        assert_eq!((rhel.name.as_str(), rhel.id.as_str(), rhel.id_like.as_str()),
+                   ("Red Hat Enterprise Linux", "rhel", "fedora"));
+        assert_eq!((rhel.version.as_str(), rhel.major, rhel.minor, rhel.codename.as_str()), ("9.3", 9, 3, "Plow"));
+
+        let alpine = os_version(vec![("/etc/os-release", include_str!("../../test_data/os-release-alpine.txt"))]);
+        assert_eq!((alpine.name.as_str(), alpine.id.as_str()), ("Alpine Linux", "alpine"));
+        assert_eq!((alpine.major, alpine.minor, alpine.patch), (3, 19, 1));
+
+        let arch = os_version(vec![("/etc/os-release", include_str!("../../test_data/os-release-arch.txt"))]);
+        assert_eq!((arch.name.as_str(), arch.id.as_str(), arch.build.as_str()), ("Arch Linux", "arch", "rolling"));
+        assert_eq!((arch.version.as_str(), arch.major), ("", 0));
+    }
+    #[test]
+    fn test_legacy_release_files() {
+        let lsb = os_version(vec![
+            ("/etc/lsb-release", include_str!("../../test_data/lsb-release.txt")),
+            ("/etc/debian_version", "wheezy/sid\n"),
+        ]);
+        assert_eq!((lsb.name.as_str(), lsb.id.as_str(), lsb.codename.as_str()), ("Ubuntu", "ubuntu", "precise"));
+        assert_eq!((lsb.major, lsb.minor), (12, 4));
+
+        let redhat = os_version(vec![("/etc/redhat-release", include_str!("../../test_data/redhat-release.txt"))]);
+        assert_eq!((redhat.name.as_str(), redhat.id.as_str(), redhat.codename.as_str()),
+                   ("Red Hat Enterprise Linux Server", "rhel", "Santiago"));
+        assert_eq!((redhat.version.as_str(), redhat.major, redhat.minor), ("6.10", 6, 10));
+
+        let debian = os_version(vec![("/etc/debian_version", "trixie/sid\n")]);
+        assert_eq!((debian.id.as_str(), debian.codename.as_str(), debian.version.as_str()), ("debian", "trixie", ""));
+
+        let unknown = os_version(Vec::new());
+        assert_eq!((unknown.name.as_str(), unknown.platform.as_str()), ("", "Linux"));
+    }
+    #[test]
+    fn test_unquote() {
+        let fields = parse_release("NAME_EXTRA=\"Other\"\nNAME=\"Fancy \\\"OS\\\"\"\n# NAME=comment\nPRETTY_NAME='It'\\''s $HOME'\nID=plain\\ os\n");
+        assert_eq!(fields["NAME"], "Fancy \"OS\"");
+        assert_eq!(fields["NAME_EXTRA"], "Other");
+        assert_eq!(fields["PRETTY_NAME"], "It's $HOME");
+        assert_eq!(fields["ID"], "plain os");
+        assert_eq!(unquote("\"C:\\\\path \\n\""), "C:\\path \\n");
+    }
+}
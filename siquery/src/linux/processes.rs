@@ -339,6 +339,12 @@ impl SimpleProcStat {
         }
         proc_stat
     }
+
+    /// Resident size in bytes, as the processes table reports it, and user plus system time in clock ticks.
+    pub(crate) fn usage(&self) -> (u64, u64) {
+        let ticks = |time: &str| time.parse::<u64>().unwrap_or(0);
+        (self.resident_size.parse().unwrap_or(0), ticks(&self.user_time) + ticks(&self.system_time))
+    }
 }
 
 impl SimpleProcIo {
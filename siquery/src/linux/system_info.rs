@@ -1,15 +1,20 @@
-use std::process::Command;
 use std::borrow::Borrow;
-use std::fs::File;
+use std::collections::HashSet;
+use std::fs::{self, File};
 use std::io::Read;
 use sysconf::raw::{sysconf, SysconfVariable};
+use uname;
 
+use crate::options::root_path;
 use crate::tables::{SystemInfoData,SystemInfoDataIface};
-use crate::utils;
 
 struct CpuInfo {
     cpu_brand: String,
-    cpu_logical_cores: u32
+    cpu_logical_cores: u32,
+    cpu_physical_cores: u32,
+    cpu_sockets: u32,
+    cpu_microcode: String,
+    cpu_flags: String,
 }
 
 pub struct Reader {}
@@ -21,19 +26,16 @@ impl SystemInfoDataIface for Reader {
         Some(String::from("For windows only"))
     }
     fn hostname(&self) -> Option<String> {
-        let output = Command::new("hostname").output().ok()?;
-        let mut hostname = String::from_utf8(output.stdout).ok()?;
-        utils::trim_string(&mut hostname);
-        Some(hostname)
+        uname::uname().ok().map(|info| info.nodename)
     }
     fn meminfo(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/proc/meminfo").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/proc/meminfo")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
     fn cpuinfo(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/proc/cpuinfo").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/proc/cpuinfo")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
     fn cpu_count(&self) -> u32 {
@@ -43,6 +45,18 @@ impl SystemInfoDataIface for Reader {
         }
         cpu_count as u32
     }
+    fn dmi_attribute(&self, name: &str) -> Option<String> {
+        // Serials and the uuid are only readable by root.
+        fs::read_to_string(root_path("/sys/class/dmi/id").join(name)).ok()
+    }
+}
+
+/// Value of a `key : value` line of a `/proc/cpuinfo` block.
+fn cpuinfo_value<'a>(block: &'a str, key: &str) -> Option<&'a str> {
+    block.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(k, _)| k.trim() == key)
+        .map(|(_, value)| value.trim())
 }
 
 impl SystemInfoData {
@@ -51,7 +65,23 @@ impl SystemInfoData {
             computer_name: String::new(),
             cpu_brand: String::new(),
             cpu_logical_cores: 0,
-            physical_memory: 0
+            physical_memory: 0,
+            uuid: String::new(),
+            cpu_physical_cores: 0,
+            cpu_sockets: 0,
+            cpu_microcode: String::new(),
+            cpu_flags: String::new(),
+            hardware_vendor: String::new(),
+            hardware_model: String::new(),
+            hardware_version: String::new(),
+            hardware_serial: String::new(),
+            board_vendor: String::new(),
+            board_model: String::new(),
+            board_version: String::new(),
+            board_serial: String::new(),
+            bios_vendor: String::new(),
+            bios_version: String::new(),
+            bios_date: String::new()
         }
     }
 
@@ -75,19 +105,59 @@ impl SystemInfoData {
         if let Some(cpu_info) = system_info.get_cpu_info(reader) {
             system_info.cpu_brand = cpu_info.cpu_brand;
             system_info.cpu_logical_cores = cpu_info.cpu_logical_cores;
+            system_info.cpu_physical_cores = cpu_info.cpu_physical_cores;
+            system_info.cpu_sockets = cpu_info.cpu_sockets;
+            system_info.cpu_microcode = cpu_info.cpu_microcode;
+            system_info.cpu_flags = cpu_info.cpu_flags;
         };
+
+        let dmi = |name: &str| reader.dmi_attribute(name).map(|value| value.trim().to_string()).unwrap_or_default();
+        system_info.uuid = dmi("product_uuid");
+        system_info.hardware_vendor = dmi("sys_vendor");
+        system_info.hardware_model = dmi("product_name");
+        system_info.hardware_version = dmi("product_version");
+        system_info.hardware_serial = dmi("product_serial");
+        system_info.board_vendor = dmi("board_vendor");
+        system_info.board_model = dmi("board_name");
+        system_info.board_version = dmi("board_version");
+        system_info.board_serial = dmi("board_serial");
+        system_info.bios_vendor = dmi("bios_vendor");
+        system_info.bios_version = dmi("bios_version");
+        system_info.bios_date = dmi("bios_date");
         output.push(system_info);
         output
     }
 
+    /// Brand, microcode and flags of the first processor, and the topology from the `physical id`
+    /// and `core id` of all of them. Without topology lines, as on most ARM systems, every logical
+    /// core is counted as a physical one in a single socket.
     fn get_cpu_info(&mut self, reader: &SystemInfoDataIface) -> Option<CpuInfo> {
         let s = reader.cpuinfo()?;
-        let model_name = s.split('\n').find(|line| line.starts_with("model name"))
-                          .and_then(|line| line.split(':').last())
-                          .and_then(|val| Some(val.trim()));
-        let model_name = String::from(model_name.unwrap_or(""));
+        let blocks: Vec<&str> = s.split("\n\n").filter(|block| cpuinfo_value(block, "processor").is_some()).collect();
+        let first = blocks.first().cloned().unwrap_or("");
+        let field = |key: &str| String::from(cpuinfo_value(first, key).unwrap_or(""));
 
-        Some(CpuInfo {cpu_brand: model_name, cpu_logical_cores: reader.cpu_count()})
+        let mut cpu_logical_cores = reader.cpu_count();
+        if cpu_logical_cores == 0 {
+            cpu_logical_cores = blocks.len() as u32;
+        }
+        let sockets: HashSet<&str> = blocks.iter().filter_map(|block| cpuinfo_value(block, "physical id")).collect();
+        let cores: HashSet<(&str, &str)> = blocks.iter()
+            .filter_map(|block| Some((cpuinfo_value(block, "physical id")?, cpuinfo_value(block, "core id")?)))
+            .collect();
+        let cpu_flags = match cpuinfo_value(first, "flags") {
+            Some(flags) => flags.to_string(),
+            None => field("Features"),
+        };
+
+        Some(CpuInfo {
+            cpu_brand: field("model name"),
+            cpu_logical_cores,
+            cpu_physical_cores: if cores.is_empty() { cpu_logical_cores } else { cores.len() as u32 },
+            cpu_sockets: if sockets.is_empty() { 1 } else { sockets.len() as u32 },
+            cpu_microcode: field("microcode"),
+            cpu_flags,
+        })
     }
 
     pub(crate) fn get_specific() -> Vec<SystemInfoData> {
@@ -100,7 +170,9 @@ impl SystemInfoData {
 #[cfg(test)]
 mod tests {
     use super::*;
-    pub struct Test {}
+    pub struct Test {
+        cpuinfo: &'static str,
+    }
     impl SystemInfoDataIface for Test {
         fn get_wmi_cpu_info(&self) -> Option<String> {
             Some(String::from("For windows only"))
@@ -115,20 +187,55 @@ mod tests {
             Some(String::from(include_str!("../../test_data/meminfo.txt")))
         }
         fn cpuinfo(&self) -> Option<String> {
-            Some(String::from(include_str!("../../test_data/cpuinfo.txt")))
+            Some(String::from(self.cpuinfo))
         }
         fn cpu_count(&self) -> u32 {
             4
         }
+        fn dmi_attribute(&self, name: &str) -> Option<String> {
+            include_str!("../../test_data/dmi-id.txt").lines()
+                .find(|line| line.split(':').next() == Some(name))
+                .map(|line| format!("{}\n", &line[name.len() + 1..]))
+        }
     }
     #[test]
     fn test_system_info() {
-        let reader: Box<SystemInfoDataIface> = Box::new(Test{});
+        let reader: Box<SystemInfoDataIface> = Box::new(Test{cpuinfo: include_str!("../../test_data/cpuinfo.txt")});
         let system_info = &SystemInfoData::get_specific_ex(reader.borrow())[0];
         assert_eq!(system_info.computer_name, "galaxy500");
         assert_eq!(system_info.cpu_brand, "Intel(R) Core(TM) i7-4790 CPU @ 3.60GHz");
         assert_eq!(system_info.cpu_logical_cores, 4);
         assert_eq!(system_info.physical_memory, 16769040384);
+        assert_eq!(system_info.cpu_physical_cores, 4);
+        assert_eq!(system_info.cpu_sockets, 1);
+        assert_eq!(system_info.cpu_microcode, "0x22");
+        assert!(system_info.cpu_flags.starts_with("fpu vme de pse"));
+        assert!(system_info.cpu_flags.ends_with("arat pln pts"));
+        assert_eq!(system_info.uuid, "4c4c4544-0042-3510-8052-b4c04f4b4e32");
+        assert_eq!(system_info.hardware_vendor, "LENOVO");
+        assert_eq!(system_info.hardware_model, "20XWCTO1WW");
+        assert_eq!(system_info.hardware_version, "ThinkPad X1 Carbon Gen 9");
+        assert_eq!(system_info.hardware_serial, "PF3ABCDE");
+        assert_eq!(system_info.board_model, "20XWCTO1WW");
+        assert_eq!(system_info.board_version, "SDK0J40697 WIN");
+        assert_eq!(system_info.bios_version, "N32ET86W (1.62 )");
+        assert_eq!(system_info.bios_date, "03/14/2023");
+    }
+    #[test]
+    fn test_cpu_topology() {
+        let reader: Box<SystemInfoDataIface> = Box::new(Test{cpuinfo: include_str!("../../test_data/cpuinfo-2-sockets.txt")});
+        let system_info = &SystemInfoData::get_specific_ex(reader.borrow())[0];
+        assert_eq!(system_info.cpu_brand, "Intel(R) Xeon(R) Gold 6130 CPU @ 2.10GHz");
+        assert_eq!(system_info.cpu_sockets, 2);
+        assert_eq!(system_info.cpu_physical_cores, 4);
+        assert_eq!(system_info.cpu_microcode, "0x2006e05");
+        assert!(system_info.cpu_flags.ends_with("avx512bw avx512vl"));
+    }
+    #[test]
+    fn test_cpuinfo_value() {
+        let block = "processor\t: 0\nmodel name\t: ARMv8 Processor rev 1 (v8l)\nFeatures\t: fp asimd evtstrm crc32\n";
+        assert_eq!(cpuinfo_value(block, "model name"), Some("ARMv8 Processor rev 1 (v8l)"));
+        assert_eq!(cpuinfo_value(block, "Features"), Some("fp asimd evtstrm crc32"));
+        assert_eq!(cpuinfo_value(block, "flags"), None);
     }
 }
-
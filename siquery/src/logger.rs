@@ -0,0 +1,271 @@
+use chrono::offset::Local;
+use serde_json::{Value as serdValue, Map};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+#[cfg(unix)]
+use std::os::unix::net::UnixDatagram;
+
+/// Destination for the results of scheduled or repeated queries.
+pub trait ResultLogger {
+    fn name(&self) -> &str;
+    fn log(&mut self, query_name: &str, rows: &[Map<String, serdValue>]) -> io::Result<()>;
+}
+
+/// Formats one result row the way every logger emits it.
+fn format_row(query_name: &str, unix_time: i64, row: &Map<String, serdValue>) -> String {
+    let line = json!({
+        "name": query_name,
+        "unixTime": unix_time,
+        "columns": row,
+    });
+    line.to_string()
+}
+
+/// Writes newline-delimited JSON to a file, rotating it once it grows past `max_size`.
+///
+/// Rotated files are renamed `<path>.1`, `<path>.2`, ... and only `max_files` of them are kept.
+pub struct FileLogger {
+    path: PathBuf,
+    max_size: u64,
+    max_files: u32,
+    file: Option<File>,
+    size: u64,
+}
+
+impl FileLogger {
+    pub fn new(path: PathBuf, max_size: u64, max_files: u32) -> FileLogger {
+        FileLogger {
+            path,
+            max_size,
+            max_files,
+            file: None,
+            size: 0,
+        }
+    }
+
+    fn open(&mut self) -> io::Result<()> {
+        if self.file.is_none() {
+            let file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+            self.size = file.metadata()?.len();
+            self.file = Some(file);
+        }
+        Ok(())
+    }
+
+    fn rotated_path(&self, index: u32) -> PathBuf {
+        let mut name = self.path.as_os_str().to_owned();
+        name.push(format!(".{}", index));
+        PathBuf::from(name)
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        self.file = None;
+        if self.max_files == 0 {
+            return fs::remove_file(&self.path);
+        }
+        let oldest = self.rotated_path(self.max_files);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (1..self.max_files).rev() {
+            let from = self.rotated_path(index);
+            if from.exists() {
+                fs::rename(&from, self.rotated_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.rotated_path(1))
+    }
+}
+
+impl ResultLogger for FileLogger {
+    fn name(&self) -> &str {
+        "filesystem"
+    }
+
+    fn log(&mut self, query_name: &str, rows: &[Map<String, serdValue>]) -> io::Result<()> {
+        let unix_time = Local::now().timestamp();
+        for row in rows {
+            let mut line = format_row(query_name, unix_time, row);
+            line.push('\n');
+            self.open()?;
+            if self.size > 0 && self.size + line.len() as u64 > self.max_size {
+                self.rotate()?;
+                self.open()?;
+            }
+            if let Some(ref mut file) = self.file {
+                file.write_all(line.as_bytes())?;
+                self.size += line.len() as u64;
+            }
+        }
+        if let Some(ref mut file) = self.file {
+            file.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Sends each result row to the local syslog socket using RFC 5424 framing.
+#[cfg(unix)]
+pub struct SyslogLogger {
+    socket_path: PathBuf,
+    socket: Option<UnixDatagram>,
+    hostname: String,
+}
+
+#[cfg(unix)]
+impl SyslogLogger {
+    /// Facility `user` (1) and severity `informational` (6).
+    const PRIORITY: u8 = 1 * 8 + 6;
+    const APP_NAME: &'static str = "siquery";
+
+    pub fn new() -> SyslogLogger {
+        SyslogLogger::with_socket(PathBuf::from("/dev/log"))
+    }
+
+    pub fn with_socket(socket_path: PathBuf) -> SyslogLogger {
+        let mut buf = [0u8; 256];
+        let hostname = nix::unistd::gethostname(&mut buf)
+            .ok()
+            .and_then(|name| name.to_str().ok())
+            .unwrap_or("-")
+            .to_string();
+        SyslogLogger {
+            socket_path,
+            socket: None,
+            hostname,
+        }
+    }
+
+    fn format_message(&self, msg: &str) -> String {
+        // <PRI>VERSION TIMESTAMP HOSTNAME APP-NAME PROCID MSGID STRUCTURED-DATA MSG
+        format!(
+            "<{}>1 {} {} {} {} - - {}",
+            Self::PRIORITY,
+            Local::now().format("%Y-%m-%dT%H:%M:%S%.6f%:z"),
+            self.hostname,
+            Self::APP_NAME,
+            std::process::id(),
+            msg
+        )
+    }
+}
+
+#[cfg(unix)]
+impl ResultLogger for SyslogLogger {
+    fn name(&self) -> &str {
+        "syslog"
+    }
+
+    fn log(&mut self, query_name: &str, rows: &[Map<String, serdValue>]) -> io::Result<()> {
+        if self.socket.is_none() {
+            let socket = UnixDatagram::unbound()?;
+            socket.connect(&self.socket_path)?;
+            self.socket = Some(socket);
+        }
+        let unix_time = Local::now().timestamp();
+        for row in rows {
+            let message = self.format_message(&format_row(query_name, unix_time, row));
+            let sent = match self.socket {
+                Some(ref socket) => socket.send(message.as_bytes()),
+                None => Ok(0),
+            };
+            if let Err(e) = sent {
+                // Reconnect on the next batch, syslogd may have been restarted.
+                self.socket = None;
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fans results out to every configured logger.
+pub struct Loggers {
+    loggers: Vec<Box<dyn ResultLogger>>,
+}
+
+impl Loggers {
+    pub fn new() -> Loggers {
+        Loggers { loggers: Vec::new() }
+    }
+
+    pub fn add(&mut self, logger: Box<dyn ResultLogger>) {
+        self.loggers.push(logger);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.loggers.is_empty()
+    }
+
+    /// Sends rows to every logger. A failing logger is skipped so the others, and the
+    /// collection itself, keep going, and its error is returned with its name.
+    pub fn log(&mut self, query_name: &str, rows: &[Map<String, serdValue>]) -> Vec<(String, io::Error)> {
+        let mut errors = Vec::new();
+        for logger in self.loggers.iter_mut() {
+            if let Err(e) = logger.log(query_name, rows) {
+                errors.push((logger.name().to_string(), e));
+            }
+        }
+        errors
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn rows(count: usize) -> Vec<Map<String, serdValue>> {
+        (0..count).map(|i| {
+            let mut row = Map::new();
+            row.insert("pid".to_string(), json!(i));
+            row
+        }).collect()
+    }
+
+    #[test]
+    fn test_file_logger_rotation() {
+        let dir = env::temp_dir().join(format!("siquery_logger_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("results.log");
+        let mut logger = FileLogger::new(path.clone(), 100, 2);
+        for _ in 0..6 {
+            logger.log("processes", &rows(1)).unwrap();
+        }
+        assert!(path.exists());
+        assert!(logger.rotated_path(1).exists());
+        assert!(logger.rotated_path(2).exists());
+        assert!(!logger.rotated_path(3).exists());
+        let content = fs::read_to_string(&path).unwrap();
+        let line: serdValue = serde_json::from_str(content.lines().next().unwrap()).unwrap();
+        assert_eq!(line["name"], "processes");
+        assert_eq!(line["columns"]["pid"], 0);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_syslog_framing() {
+        let logger = SyslogLogger::with_socket(PathBuf::from("/nonexistent"));
+        let message = logger.format_message("{}");
+        assert!(message.starts_with("<14>1 "));
+        assert!(message.ends_with(&format!("siquery {} - - {{}}", std::process::id())));
+    }
+
+    #[test]
+    fn test_loggers_report_failures() {
+        struct Failing {}
+        impl ResultLogger for Failing {
+            fn name(&self) -> &str { "failing" }
+            fn log(&mut self, _: &str, _: &[Map<String, serdValue>]) -> io::Result<()> {
+                Err(io::Error::new(io::ErrorKind::Other, "unavailable"))
+            }
+        }
+        let mut loggers = Loggers::new();
+        loggers.add(Box::new(Failing {}));
+        loggers.add(Box::new(Failing {}));
+        assert_eq!(loggers.log("users", &rows(1)).len(), 2);
+    }
+}
@@ -1,5 +1,6 @@
 use std::fs::File;
 use std::io::Read;
+use crate::options::root_path;
 #[allow(unused_imports)]
 // TODO implement to_json
 use serde_json;
@@ -26,7 +27,7 @@ pub struct EtcHostsReader {}
 impl EtcHostsIface for EtcHostsReader {
     fn get_hosts_file(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/hosts").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/etc/hosts")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
 }
@@ -36,7 +37,7 @@ pub struct EtcProtocolsReader {}
 impl EtcProtocolsIface for EtcProtocolsReader {
     fn get_protocols_file(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/protocols").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/etc/protocols")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
 }
@@ -46,7 +47,7 @@ pub struct EtcServicesReader {}
 impl EtcServicesIface for EtcServicesReader {
     fn get_services_file(&self) -> Option<String> {
         let mut s = String::new();
-        File::open("/etc/services").ok()?.read_to_string(&mut s).ok()?;
+        File::open(root_path("/etc/services")).ok()?.read_to_string(&mut s).ok()?;
         Some(s)
     }
 }
@@ -46,6 +46,12 @@ impl OsVersion {
                 version,
                 major,
                 minor,
+                patch: 0,
+                build: String::new(),
+                id: String::new(),
+                id_like: String::new(),
+                codename: String::new(),
+                kernel_release: String::new(),
             }
         );
         output
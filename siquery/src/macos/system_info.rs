@@ -7,7 +7,7 @@ use crate::utils;
 pub struct Reader {}
 impl SystemInfoDataIface for Reader {
     fn hostname(&self) -> Option<String> {
-        let output = Command::new("hostname").output().ok()?;
+        let output = utils::command_output(&mut Command::new("hostname"))?;
         let mut hostname = String::from_utf8(output.stdout).ok()?;
         utils::trim_string(&mut hostname);
         Some(hostname)
@@ -30,6 +30,9 @@ impl SystemInfoDataIface for Reader {
     fn meminfo(&self) -> Option<String> {
         Some(String::new())
     }
+    fn dmi_attribute(&self, _name: &str) -> Option<String> {
+        None
+    }
 }
 
 impl SystemInfoData {
@@ -38,7 +41,23 @@ impl SystemInfoData {
             computer_name: String::new(),
             cpu_brand: String::new(),
             cpu_logical_cores: 0,
-            physical_memory: 0
+            physical_memory: 0,
+            uuid: String::new(),
+            cpu_physical_cores: 0,
+            cpu_sockets: 0,
+            cpu_microcode: String::new(),
+            cpu_flags: String::new(),
+            hardware_vendor: String::new(),
+            hardware_model: String::new(),
+            hardware_version: String::new(),
+            hardware_serial: String::new(),
+            board_vendor: String::new(),
+            board_model: String::new(),
+            board_version: String::new(),
+            board_serial: String::new(),
+            bios_vendor: String::new(),
+            bios_version: String::new(),
+            bios_date: String::new()
         }
     }
 
@@ -80,6 +99,9 @@ mod tests {
         fn meminfo(&self) -> Option<String> {
             Some(String::new())
         }
+        fn dmi_attribute(&self, _name: &str) -> Option<String> {
+            None
+        }
     }
     #[test]
     fn test_system_info () {
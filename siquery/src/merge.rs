@@ -0,0 +1,211 @@
+use rusqlite::{Connection, NO_PARAMS, ToSql};
+use rusqlite::types::Value;
+use serde_json::{Value as serdValue, Map};
+use std::fs;
+use std::path::Path;
+
+use crate::events::unix_time;
+use crate::utils::quote_identifier as quote;
+
+/// Column naming the host of merged rows. Tables such as `logged_in_users` and `proxies` have a
+/// `host` column of their own.
+const HOST_COLUMN: &str = "host_identifier";
+
+/// Results of one host, as printed by `siquery --json -a <tables>`.
+pub struct HostResults {
+    pub host: String,
+    pub source: String,
+    pub tables: Map<String, serdValue>,
+    /// Problems worth reporting that did not stop the load, such as the host fallback.
+    pub warnings: Vec<String>,
+}
+
+/// Reads a json result file and finds its host in `system_info.computer_name`,
+/// falling back to the file name with a warning.
+pub fn load_host_results(path: &Path) -> Result<HostResults, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    let tables = match serde_json::from_str(&content) {
+        Ok(serdValue::Object(tables)) => tables,
+        Ok(_) => return Err(format!("{}: expected an object of tables", path.display())),
+        Err(e) => return Err(format!("{}: {}", path.display(), e)),
+    };
+    let mut warnings = Vec::new();
+    let host = tables.get("system_info")
+        .and_then(|rows| rows.get(0))
+        .and_then(|row| row.get("computer_name"))
+        .and_then(|name| name.as_str())
+        .map(|name| name.to_string())
+        .unwrap_or_else(|| {
+            let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            warnings.push(format!("{}: no system_info.computer_name, using '{}' as host", path.display(), name));
+            name
+        });
+    Ok(HostResults {
+        host,
+        source: path.display().to_string(),
+        tables,
+        warnings,
+    })
+}
+
+fn sql_type(value: &serdValue) -> Option<&'static str> {
+    match value {
+        serdValue::Null => None,
+        serdValue::Number(n) if n.is_f64() => Some("REAL"),
+        serdValue::Number(_) | serdValue::Bool(_) => Some("INTEGER"),
+        _ => Some("TEXT"),
+    }
+}
+
+fn sql_value(value: Option<&serdValue>) -> Value {
+    match value {
+        None | Some(serdValue::Null) => Value::Null,
+        Some(serdValue::Bool(b)) => Value::Integer(*b as i64),
+        Some(serdValue::Number(n)) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Real(n.as_f64().unwrap_or(0.0)),
+        },
+        Some(serdValue::String(s)) => Value::Text(s.clone()),
+        Some(other) => Value::Text(other.to_string()),
+    }
+}
+
+fn table_columns(db: &Connection, table: &str) -> Result<Vec<String>, rusqlite::Error> {
+    let mut statement = db.prepare(&format!("PRAGMA table_info({})", quote(table)))?;
+    let names = statement.query_map(NO_PARAMS, |row| row.get::<usize, String>(1))?;
+    names.collect()
+}
+
+/// Creates or widens `table` so it has a `host_identifier` column and every column of `rows`.
+fn prepare_table(db: &Connection, table: &str, rows: &[serdValue]) -> Result<Vec<String>, rusqlite::Error> {
+    let mut columns: Vec<(String, &'static str)> = Vec::new();
+    for row in rows.iter().filter_map(|row| row.as_object()) {
+        for (name, value) in row.iter() {
+            match columns.iter_mut().find(|(column, _)| column == name) {
+                Some(column) => {
+                    if column.1 == "" {
+                        column.1 = sql_type(value).unwrap_or("");
+                    }
+                },
+                None => columns.push((name.clone(), sql_type(value).unwrap_or(""))),
+            }
+        }
+    }
+    db.execute_batch(&format!("CREATE TABLE IF NOT EXISTS {}({} TEXT NOT NULL)", quote(table), HOST_COLUMN))?;
+    let existing = table_columns(db, table)?;
+    for (name, sql_type) in columns.iter().filter(|(name, _)| !existing.contains(name)) {
+        db.execute_batch(&format!("ALTER TABLE {} ADD COLUMN {} {}", quote(table), quote(name), sql_type))?;
+    }
+    Ok(columns.into_iter().map(|(name, _)| name).collect())
+}
+
+fn insert_rows(db: &Connection, host: &str, table: &str, rows: &[serdValue]) -> Result<usize, rusqlite::Error> {
+    let columns = prepare_table(db, table, rows)?;
+    db.execute(&format!("DELETE FROM {} WHERE {} = ?1", quote(table), HOST_COLUMN), &[host])?;
+    let names: Vec<String> = columns.iter().map(|name| quote(name)).collect();
+    let placeholders: Vec<String> = (0..=columns.len()).map(|i| format!("?{}", i + 1)).collect();
+    let mut insert = db.prepare(&format!("INSERT INTO {}({}{}{}) VALUES ({})",
+                                         quote(table),
+                                         HOST_COLUMN,
+                                         if names.is_empty() { "" } else { ", " },
+                                         names.join(", "),
+                                         placeholders.join(", ")))?;
+    let mut count = 0;
+    for row in rows.iter().filter_map(|row| row.as_object()) {
+        let mut values = vec![Value::Text(host.to_string())];
+        values.extend(columns.iter().map(|name| sql_value(row.get(name))));
+        insert.execute(&values)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// Adds the results of one host to `db`, replacing rows merged earlier for the same host.
+///
+/// Every table gets a leading `host_identifier` column, and `merged_hosts` records where each host came from.
+/// Returns the number of rows inserted.
+pub fn merge_host(db: &Connection, results: &HostResults) -> Result<usize, String> {
+    let merge = || -> Result<usize, rusqlite::Error> {
+        db.execute_batch(
+            "CREATE TABLE IF NOT EXISTS merged_hosts(host_identifier TEXT PRIMARY KEY, source TEXT, merged_at INTEGER)")?;
+        let mut count = 0;
+        for (table, rows) in results.tables.iter() {
+            if let Some(rows) = rows.as_array() {
+                count += insert_rows(db, &results.host, table, rows)?;
+            }
+        }
+        db.execute("INSERT OR REPLACE INTO merged_hosts(host_identifier, source, merged_at) VALUES (?1, ?2, ?3)",
+                   &[&results.host as &dyn ToSql, &results.source, &unix_time()])?;
+        Ok(count)
+    };
+    db.execute_batch("BEGIN").map_err(|e| e.to_string())?;
+    match merge() {
+        Ok(count) => {
+            db.execute_batch("COMMIT").map_err(|e| e.to_string())?;
+            Ok(count)
+        },
+        Err(e) => {
+            let _ = db.execute_batch("ROLLBACK");
+            Err(format!("{}: {}", results.source, e))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results(host_file: &str, json: &str) -> HostResults {
+        let path = std::env::temp_dir().join(format!("siquery_merge_{}_{}.json", std::process::id(), host_file));
+        fs::write(&path, json).unwrap();
+        let results = load_host_results(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        results
+    }
+
+    #[test]
+    fn test_merge_hosts() {
+        let db = Connection::open_in_memory().unwrap();
+        let first = results("a", r#"{
+            "system_info": [{"computer_name": "alpha", "cpu_logical_cores": 4}],
+            "os_version": [{"name": "Ubuntu", "major": 22}]
+        }"#);
+        let second = results("b", r#"{
+            "os_version": [{"name": "Windows", "major": 10, "build": "19045"}]
+        }"#);
+        assert_eq!(first.host, "alpha");
+        assert!(second.host.starts_with("siquery_merge_"));
+        assert!(first.warnings.is_empty());
+        assert_eq!(second.warnings.len(), 1);
+        assert!(second.warnings[0].contains("no system_info.computer_name"));
+        assert_eq!(merge_host(&db, &first).unwrap(), 2);
+        assert_eq!(merge_host(&db, &second).unwrap(), 1);
+        // Merging a host again replaces its rows.
+        assert_eq!(merge_host(&db, &first).unwrap(), 2);
+
+        let rows: Vec<(String, String, i64, Option<String>)> = {
+            let mut statement = db.prepare("SELECT host_identifier, name, major, build FROM os_version ORDER BY major").unwrap();
+            let rows = statement.query_map(NO_PARAMS, |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)))
+                .unwrap();
+            rows.map(|row| row.unwrap()).collect()
+        };
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], (second.host.clone(), "Windows".to_string(), 10, Some("19045".to_string())));
+        assert_eq!(rows[1], ("alpha".to_string(), "Ubuntu".to_string(), 22, None));
+        let hosts: i64 = db.query_row("SELECT count(*) FROM merged_hosts", NO_PARAMS, |row| row.get(0)).unwrap();
+        assert_eq!(hosts, 2);
+    }
+
+    #[test]
+    fn test_merge_host_column() {
+        let db = Connection::open_in_memory().unwrap();
+        let results = results("c", r#"{
+            "system_info": [{"computer_name": "gamma"}],
+            "logged_in_users": [{"user": "eve", "host": "10.0.0.7", "tty": "pts/0"}]
+        }"#);
+        assert_eq!(merge_host(&db, &results).unwrap(), 2);
+        let row: (String, String) = db.query_row("SELECT host_identifier, host FROM logged_in_users", NO_PARAMS,
+                                                 |row| Ok((row.get(0)?, row.get(1)?))).unwrap();
+        assert_eq!(row, ("gamma".to_string(), "10.0.0.7".to_string()));
+    }
+}
@@ -0,0 +1,342 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::Duration;
+
+use crate::watchdog::WatchdogLimits;
+
+/// Process-wide settings that change how tables are generated and printed.
+#[derive(Clone, Debug)]
+pub struct Options {
+    /// Directory prepended to system configuration files such as `/etc/hosts`,
+    /// to inspect a mounted image instead of the running host.
+    pub root_prefix: Option<PathBuf>,
+    /// How long a generated table may be reused before it is generated again.
+    pub cache_ttl: HashMap<String, Duration>,
+    /// How long a table generator may run before its rows are given up, by table name.
+    pub table_timeout: HashMap<String, Duration>,
+    /// Deadline of tables without their own timeout. `None` lets generators run to completion.
+    pub default_table_timeout: Option<Duration>,
+    /// Formatting of the csv printer.
+    pub csv: CsvOptions,
+    /// Fields wrapped around each row printed in ndjson mode.
+    pub decorations: Decorations,
+    /// Which tables may be queried.
+    pub table_policy: TablePolicy,
+    /// Directory of carved archives, next to the history store when not set.
+    pub carve_dir: Option<PathBuf>,
+    /// Limits of carves started from the `carves` table, the defaults of `CarveLimits` when not set.
+    pub carve_max_files: Option<usize>,
+    pub carve_max_bytes: Option<u64>,
+    /// Limits applied to scheduled queries by the watchdog.
+    pub watchdog: WatchdogLimits,
+    /// Settings of the program embedding siquery, shown in `siquery_flags`: name to value and description.
+    pub flags: BTreeMap<String, (String, String)>,
+}
+
+/// Which csv fields are quoted. Fields holding the delimiter, a quote or a line break are always quoted.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum CsvQuote {
+    Minimal,
+    All,
+    /// Every text field, leaving numbers bare.
+    NonNumeric,
+}
+
+impl CsvQuote {
+    pub fn parse(policy: &str) -> Result<CsvQuote, String> {
+        match policy {
+            "minimal" => Ok(CsvQuote::Minimal),
+            "all" => Ok(CsvQuote::All),
+            "non-numeric" | "non_numeric" => Ok(CsvQuote::NonNumeric),
+            _ => Err(format!("unknown quoting policy '{}', expected minimal, all or non-numeric", policy)),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CsvOptions {
+    pub delimiter: u8,
+    pub header: bool,
+    /// Ends records with CRLF as RFC 4180 does, or with LF.
+    pub crlf: bool,
+    pub quote: CsvQuote,
+}
+
+impl Default for CsvOptions {
+    fn default() -> CsvOptions {
+        CsvOptions {
+            delimiter: b',',
+            header: true,
+            crlf: true,
+            quote: CsvQuote::Minimal,
+        }
+    }
+}
+
+/// Parses a delimiter given as a single ASCII character, `\t` or `tab`.
+pub fn parse_delimiter(delimiter: &str) -> Result<u8, String> {
+    match delimiter {
+        "\\t" | "\t" | "tab" => Ok(b'\t'),
+        d if d.len() == 1 && d.is_ascii() && d != "\"" && d != "\r" && d != "\n" => Ok(d.as_bytes()[0]),
+        _ => Err(format!("csv delimiter '{}' must be a single ASCII character", delimiter)),
+    }
+}
+
+/// osquery-style result log fields added around ndjson rows, which then move under `columns`.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Decorations {
+    pub name: bool,
+    pub host_identifier: bool,
+    pub unix_time: bool,
+}
+
+impl Decorations {
+    /// Parses a comma separated list of `name`, `hostIdentifier` and `unixTime`.
+    pub fn parse(fields: &str) -> Result<Decorations, String> {
+        let mut decorations = Decorations::default();
+        for field in fields.split(',').map(|field| field.trim()).filter(|field| !field.is_empty()) {
+            match field {
+                "name" => decorations.name = true,
+                "hostIdentifier" => decorations.host_identifier = true,
+                "unixTime" => decorations.unix_time = true,
+                _ => return Err(format!("unknown decoration '{}'", field)),
+            }
+        }
+        Ok(decorations)
+    }
+
+    pub fn any(&self) -> bool {
+        self.name || self.host_identifier || self.unix_time
+    }
+}
+
+/// What a connection does with the tables denied by the `TablePolicy`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeniedTables {
+    /// Denied tables are not registered, querying them fails with "no such table".
+    Omit,
+    /// Denied tables are registered, but querying them fails with "table disabled by policy".
+    Stub,
+}
+
+impl DeniedTables {
+    pub fn parse(mode: &str) -> Result<DeniedTables, String> {
+        match mode {
+            "omit" => Ok(DeniedTables::Omit),
+            "stub" => Ok(DeniedTables::Stub),
+            _ => Err(format!("unknown denied tables mode '{}', expected omit or stub", mode)),
+        }
+    }
+}
+
+/// Runtime allow and deny lists of tables, on top of the tables compiled in.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TablePolicy {
+    /// When set, only these tables are allowed.
+    pub allow: Option<HashSet<String>>,
+    /// Tables denied even when allowed.
+    pub deny: HashSet<String>,
+    pub denied: DeniedTables,
+}
+
+impl TablePolicy {
+    pub fn allows(&self, table_name: &str) -> bool {
+        let allowed = match self.allow {
+            Some(ref allow) => allow.contains(table_name),
+            None => true,
+        };
+        allowed && !self.deny.contains(table_name)
+    }
+}
+
+impl Default for TablePolicy {
+    fn default() -> TablePolicy {
+        TablePolicy {
+            allow: None,
+            deny: HashSet::new(),
+            denied: DeniedTables::Omit,
+        }
+    }
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            root_prefix: None,
+            cache_ttl: HashMap::new(),
+            table_timeout: HashMap::new(),
+            default_table_timeout: None,
+            csv: CsvOptions::default(),
+            decorations: Decorations::default(),
+            table_policy: TablePolicy::default(),
+            carve_dir: None,
+            carve_max_files: None,
+            carve_max_bytes: None,
+            watchdog: WatchdogLimits::default(),
+            flags: BTreeMap::new(),
+        }
+    }
+}
+
+lazy_static! {
+    static ref OPTIONS: RwLock<Options> = RwLock::new(Options::default());
+}
+
+pub fn get_options() -> Options {
+    OPTIONS.read().map(|options| options.clone()).unwrap_or_default()
+}
+
+pub fn set_root_prefix(prefix: Option<PathBuf>) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.root_prefix = prefix;
+    }
+}
+
+pub fn set_cache_ttl(table_name: &str, ttl: Duration) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.cache_ttl.insert(table_name.to_string(), ttl);
+    }
+}
+
+pub fn cache_ttl(table_name: &str) -> Option<Duration> {
+    OPTIONS.read().ok()?.cache_ttl.get(table_name).cloned()
+}
+
+pub fn set_table_timeout(table_name: &str, timeout: Duration) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.table_timeout.insert(table_name.to_string(), timeout);
+    }
+}
+
+pub fn set_default_table_timeout(timeout: Option<Duration>) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.default_table_timeout = timeout;
+    }
+}
+
+pub fn table_timeout(table_name: &str) -> Option<Duration> {
+    let options = OPTIONS.read().ok()?;
+    options.table_timeout.get(table_name).cloned().or(options.default_table_timeout)
+}
+
+pub fn set_csv_options(csv: CsvOptions) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.csv = csv;
+    }
+}
+
+pub fn csv_options() -> CsvOptions {
+    OPTIONS.read().map(|options| options.csv).unwrap_or_default()
+}
+
+pub fn set_decorations(decorations: Decorations) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.decorations = decorations;
+    }
+}
+
+pub fn decorations() -> Decorations {
+    OPTIONS.read().map(|options| options.decorations).unwrap_or_default()
+}
+
+pub fn set_table_policy(policy: TablePolicy) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.table_policy = policy;
+    }
+}
+
+pub fn table_policy() -> TablePolicy {
+    OPTIONS.read().map(|options| options.table_policy.clone()).unwrap_or_default()
+}
+
+/// Whether the table policy lets `table_name` be queried.
+pub fn table_allowed(table_name: &str) -> bool {
+    OPTIONS.read().map(|options| options.table_policy.allows(table_name)).unwrap_or(true)
+}
+
+pub fn set_carve_dir(dir: Option<PathBuf>) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.carve_dir = dir;
+    }
+}
+
+pub fn set_carve_limits(max_files: Option<usize>, max_bytes: Option<u64>) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.carve_max_files = max_files;
+        options.carve_max_bytes = max_bytes;
+    }
+}
+
+pub fn set_watchdog_limits(limits: WatchdogLimits) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.watchdog = limits;
+    }
+}
+
+pub fn watchdog_limits() -> WatchdogLimits {
+    OPTIONS.read().map(|options| options.watchdog).unwrap_or_default()
+}
+
+/// Records a setting of the embedding program so `siquery_flags` can report it.
+pub fn set_flag(name: &str, value: &str, description: &str) {
+    if let Ok(mut options) = OPTIONS.write() {
+        options.flags.insert(name.to_string(), (value.to_string(), description.to_string()));
+    }
+}
+
+/// Resolves an absolute system path against the configured root prefix.
+pub fn root_path(path: &str) -> PathBuf {
+    let prefix = OPTIONS.read().ok().and_then(|options| options.root_prefix.clone());
+    match prefix {
+        Some(prefix) => prefix.join(Path::new(path).strip_prefix("/").unwrap_or(Path::new(path))),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_root_path() {
+        assert_eq!(root_path("/etc/hosts"), PathBuf::from("/etc/hosts"));
+        set_root_prefix(Some(PathBuf::from("/mnt/image")));
+        assert_eq!(root_path("/etc/hosts"), PathBuf::from("/mnt/image/etc/hosts"));
+        set_root_prefix(None);
+    }
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert_eq!(parse_delimiter("\\t"), Ok(b'\t'));
+        assert!(parse_delimiter("\"").is_err());
+        assert!(parse_delimiter("||").is_err());
+        assert_eq!(CsvQuote::parse("non-numeric"), Ok(CsvQuote::NonNumeric));
+        assert!(CsvQuote::parse("some").is_err());
+    }
+
+    #[test]
+    fn test_parse_decorations() {
+        assert_eq!(Decorations::parse("").unwrap(), Decorations::default());
+        let decorations = Decorations::parse("name, unixTime").unwrap();
+        assert!(decorations.name && decorations.unix_time && !decorations.host_identifier);
+        assert!(Decorations::parse("name,host").is_err());
+    }
+
+    #[test]
+    fn test_table_policy() {
+        let mut policy = TablePolicy::default();
+        assert!(policy.allows("process_envs"));
+        policy.deny.insert("process_envs".to_string());
+        assert!(!policy.allows("process_envs"));
+        assert!(policy.allows("processes"));
+        policy.allow = Some(vec!["processes".to_string(), "process_envs".to_string()].into_iter().collect());
+        assert!(policy.allows("processes"));
+        assert!(!policy.allows("process_envs"));
+        assert!(!policy.allows("users"));
+        assert_eq!(DeniedTables::parse("stub"), Ok(DeniedTables::Stub));
+        assert!(DeniedTables::parse("hide").is_err());
+    }
+}
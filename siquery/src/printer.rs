@@ -1,45 +1,68 @@
-use csv::{WriterBuilder, Terminator};
 use rusqlite::{Rows, Connection, Row as RusqliteRow, types::{Value, Type}};
 use prettytable::{Table, row::Row, cell::Cell};
-use crate::tables::get_table_list;
+use crate::tables::{get_table_list, SystemInfoData};
 use crate::query::{get_schema, execute_query};
 use serde_json::{Value as serdValue, Map};
+use crate::options::{csv_options, decorations, CsvOptions, CsvQuote, Decorations};
+use crate::events::unix_time;
+use std::io::{self, Write};
+use crate::html::HtmlReport;
+
+fn csv_field(out: &mut String, value: &Value, options: &CsvOptions) {
+    let (text, numeric) = match value {
+        // NULL is the only value written as a bare empty field.
+        Value::Null => return,
+        Value::Integer(i) => (i.to_string(), true),
+        Value::Real(f) => (f.to_string(), true),
+        Value::Text(s) => (s.clone(), false),
+        Value::Blob(b) => (String::from_utf8_lossy(b).into_owned(), false),
+    };
+    let delimiter = options.delimiter as char;
+    let quote = match options.quote {
+        CsvQuote::All => true,
+        CsvQuote::NonNumeric => !numeric,
+        CsvQuote::Minimal => text.is_empty(),
+    } || text.contains(|c| c == delimiter || c == '"' || c == '\r' || c == '\n');
+    if quote {
+        out.push('"');
+        out.push_str(&text.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(&text);
+    }
+}
+
+/// Formats one csv record, telling NULL (an empty field) from an empty string (`""`).
+pub(crate) fn csv_record(values: &[Value], options: &CsvOptions) -> String {
+    let mut out = String::new();
+    for (i, value) in values.iter().enumerate() {
+        if i > 0 {
+            out.push(options.delimiter as char);
+        }
+        csv_field(&mut out, value, options);
+    }
+    out.push_str(if options.crlf { "\r\n" } else { "\n" });
+    out
+}
 
 pub fn print_csv(columns: Vec<String>, values: &mut Rows) {
-    let mut row: Vec<String> = Vec::new();
-    //init writer
-    let mut wtr = WriterBuilder::new()
-        .delimiter(b'|')
-        .has_headers(true)
-        .double_quote(true)
-        .terminator(Terminator::CRLF)
-        .from_writer(vec![]);
-    //write header first
-    wtr.write_record(columns).expect("could not write columns");
-    loop {
-        if let Ok(Some(res)) = values.next(){
-            for i in 0..res.column_count() {
-                let val = Value::data_type(&res.get_unwrap(i));
-                match val {
-                    Type::Real | Type::Integer => {
-                        row.push(res.get_unwrap::<usize,i64>(i).to_string());
-                    },
-                    Type::Text => {
-                        row.push(res.get_unwrap::<usize,String>(i))
-                    },
-                    _ => {
-                        // Do nothing.
-                    }
-                }
-            }
-            // write row values
-            wtr.write_record(row).expect("could not write row");
-            row = Vec::new();
-        } else {
-            break
+    let options = csv_options();
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    if options.header {
+        let header: Vec<Value> = columns.iter().map(|c| Value::Text(c.clone())).collect();
+        if out.write_all(csv_record(&header, &options).as_bytes()).is_err() {
+            return;
+        }
+    }
+    while let Ok(Some(res)) = values.next() {
+        let row: Vec<Value> = (0..columns.len()).map(|i| res.get_unwrap(i)).collect();
+        // Stop quietly when the reading end of a pipe goes away.
+        if out.write_all(csv_record(&row, &options).as_bytes()).is_err() {
+            return;
         }
     }
-    println!("{}", String::from_utf8(wtr.into_inner().unwrap()).unwrap());
+    let _ = out.flush();
 }
 
 pub fn print_pretty(columns: Vec<String>, values: &mut Rows) {
@@ -73,6 +96,63 @@ pub fn print_pretty(columns: Vec<String>, values: &mut Rows) {
 }
 
 pub fn print_json(table_name: String, col_names: &Vec<String>, values: &mut Rows) -> Vec<Map<String,serdValue>> {
+    let writer = rows_to_json(col_names, values);
+    if table_name.is_empty() {
+        println!("{}", serde_json::to_string_pretty(&writer).unwrap());
+    }
+
+    writer
+}
+
+pub(crate) fn host_identifier() -> String {
+    SystemInfoData::get_specific()
+        .get(0)
+        .map(|info| info.computer_name.clone())
+        .unwrap_or_default()
+}
+
+/// Wraps a row with the requested decorations, or returns it unchanged when there are none.
+fn decorate(row: Map<String, serdValue>, name: &str, host: &str, decorations: &Decorations) -> serdValue {
+    if !decorations.any() {
+        return serdValue::Object(row);
+    }
+    let mut line = Map::new();
+    if decorations.name {
+        line.insert("name".to_string(), json!(name));
+    }
+    if decorations.host_identifier {
+        line.insert("hostIdentifier".to_string(), json!(host));
+    }
+    if decorations.unix_time {
+        line.insert("unixTime".to_string(), json!(unix_time()));
+    }
+    line.insert("columns".to_string(), serdValue::Object(row));
+    serdValue::Object(line)
+}
+
+/// Writes one compact JSON object per row as rows are read, without buffering the result set.
+pub fn print_ndjson(name: &str, col_names: &Vec<String>, values: &mut Rows) {
+    let decorations = decorations();
+    let host = if decorations.host_identifier { host_identifier() } else { String::new() };
+    let stdout = io::stdout();
+    let mut out = io::BufWriter::new(stdout.lock());
+    // Stop quietly when the reading end of a pipe goes away.
+    if write_ndjson(&mut out, name, col_names, values, &host, &decorations).is_ok() {
+        let _ = out.flush();
+    }
+}
+
+fn write_ndjson<W: Write>(out: &mut W, name: &str, col_names: &Vec<String>, values: &mut Rows, host: &str,
+                          decorations: &Decorations) -> io::Result<()> {
+    while let Ok(Some(res)) = values.next() {
+        let line = decorate(format_to_json(col_names, &res), name, host, decorations);
+        serde_json::to_writer(&mut *out, &line)?;
+        out.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+pub(crate) fn rows_to_json(col_names: &Vec<String>, values: &mut Rows) -> Vec<Map<String,serdValue>> {
     let mut writer: Vec<Map<String,serdValue>> = Vec::new();
     let mut _value: Map<String, serdValue> = Map::new();
     loop {
@@ -83,10 +163,6 @@ pub fn print_json(table_name: String, col_names: &Vec<String>, values: &mut Rows
             break
         }
     }
-    if table_name.is_empty() {
-        println!("{}", serde_json::to_string_pretty(&writer).unwrap());
-    }
-
     writer
 }
 
@@ -138,17 +214,105 @@ pub fn print_schema(table: String) {
     }
 }
 
-pub fn print_table_by_name(db: Connection, table: String, mode: u8) {
+pub fn print_table_by_name(db: &Connection, table: String, mode: u8) {
     let v: Vec<_> = table.split(',').collect();
+    if mode == 3 {
+        let mut report = HtmlReport::new(db);
+        for t in get_table_list().iter().filter(|t| v.contains(&t.as_str())) {
+            report.add_table(t);
+        }
+        print!("{}", report.render());
+        return;
+    }
+    if mode == 4 {
+        for t in get_table_list().iter().filter(|t| v.contains(&t.as_str())) {
+            execute_query(db, &format!("select * from {}", t), t.to_string(), mode);
+        }
+        return;
+    }
     let mut json_table: Map<String, serdValue> = Map::new();
     for t in get_table_list().iter() {
         if let Some(table_name) = v.iter().find(|&&x| x == *t) {
             let query = format!("select * from {}", table_name);
-            let writer = execute_query(&db, &query, table_name.to_string() , mode);
+            let writer = execute_query(db, &query, table_name.to_string() , mode);
             json_table.insert(table_name.to_string(), json!(writer));
         }
     }
-    if mode != 3 {
+    if mode == 1 {
         println!("{}", serde_json::to_string_pretty(&json_table).unwrap());
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(data: &str, delimiter: u8) -> Vec<Vec<String>> {
+        csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .from_reader(data.as_bytes())
+            .records()
+            .map(|record| record.unwrap().iter().map(|field| field.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_csv_record() {
+        let row = vec![
+            Value::Integer(7),
+            Value::Text("a,\"b\"\nc".to_string()),
+            Value::Text(String::new()),
+            Value::Null,
+            Value::Real(1.5),
+        ];
+        let mut options = CsvOptions::default();
+        assert_eq!(csv_record(&row, &options), "7,\"a,\"\"b\"\"\nc\",\"\",,1.5\r\n");
+        assert_eq!(parse(&csv_record(&row, &options), b','),
+                   vec![vec!["7", "a,\"b\"\nc", "", "", "1.5"]]);
+
+        options.quote = CsvQuote::NonNumeric;
+        options.crlf = false;
+        assert_eq!(csv_record(&row, &options), "7,\"a,\"\"b\"\"\nc\",\"\",,1.5\n");
+        options.quote = CsvQuote::All;
+        options.delimiter = b'\t';
+        assert_eq!(csv_record(&row, &options), "\"7\"\t\"a,\"\"b\"\"\nc\"\t\"\"\t\t\"1.5\"\n");
+        assert_eq!(parse(&csv_record(&row, &options), b'\t'),
+                   vec![vec!["7", "a,\"b\"\nc", "", "", "1.5"]]);
+    }
+
+    #[test]
+    fn test_decorate() {
+        let mut row = Map::new();
+        row.insert("pid".to_string(), json!(1));
+        let plain = decorate(row.clone(), "processes", "alpha", &Decorations::default());
+        assert_eq!(plain, serdValue::Object(row.clone()));
+
+        let all = Decorations { name: true, host_identifier: true, unix_time: true };
+        let line = decorate(row.clone(), "processes", "alpha", &all);
+        let keys: Vec<&String> = line.as_object().unwrap().keys().collect();
+        assert_eq!(keys, vec!["columns", "hostIdentifier", "name", "unixTime"]);
+        assert_eq!(line["name"], "processes");
+        assert_eq!(line["hostIdentifier"], "alpha");
+        assert!(line["unixTime"].as_i64().unwrap() > 0);
+        assert_eq!(line["columns"], serdValue::Object(row.clone()));
+
+        let name_only = Decorations { name: true, ..Decorations::default() };
+        let line = decorate(row, "processes", "alpha", &name_only);
+        assert_eq!(line, json!({"name": "processes", "columns": {"pid": 1}}));
+    }
+
+    #[test]
+    fn test_write_ndjson() {
+        let db = Connection::open_in_memory().unwrap();
+        let mut statement = db.prepare("select 1 as pid, 'init' as name union all select 2, 'kthreadd'").unwrap();
+        let col_names: Vec<String> = statement.column_names().iter().map(|name| name.to_string()).collect();
+        let mut rows = statement.query(rusqlite::NO_PARAMS).unwrap();
+        let mut out = Vec::new();
+        let decorations = Decorations { name: true, ..Decorations::default() };
+        write_ndjson(&mut out, "processes", &col_names, &mut rows, "", &decorations).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(),
+                   "{\"columns\":{\"name\":\"init\",\"pid\":1},\"name\":\"processes\"}\n\
+                    {\"columns\":{\"name\":\"kthreadd\",\"pid\":2},\"name\":\"processes\"}\n");
+    }
+}
@@ -0,0 +1,184 @@
+use rusqlite::types::Value;
+use prettytable::{Table, row::Row, cell::Cell};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::tables::SiqueryTableStatsRow;
+
+lazy_static! {
+    static ref TABLE_STATS: Mutex<BTreeMap<String, TableStats>> = Mutex::new(BTreeMap::new());
+    static ref WARNINGS: Mutex<Vec<(String, String)>> = Mutex::new(Vec::new());
+}
+
+/// Generation statistics accumulated for one table.
+#[derive(Clone, Debug, Default)]
+pub struct TableStats {
+    pub generations: u64,
+    pub wall_time: Duration,
+    pub rows: u64,
+    pub bytes: u64,
+    pub cache_hits: u64,
+    /// Generations given up because they ran past the table timeout.
+    pub timeouts: u64,
+}
+
+fn value_size(value: &Value) -> u64 {
+    match value {
+        Value::Null => 0,
+        Value::Integer(_) | Value::Real(_) => 8,
+        Value::Text(s) => s.len() as u64,
+        Value::Blob(b) => b.len() as u64,
+    }
+}
+
+/// Records one call to a table generator.
+pub fn record_generation(table_name: &str, wall_time: Duration, rows: &Vec<Vec<Value>>) {
+    let bytes: u64 = rows.iter()
+        .map(|row| row.iter().map(value_size).sum::<u64>())
+        .sum();
+    if let Ok(mut stats) = TABLE_STATS.lock() {
+        let entry = stats.entry(table_name.to_string()).or_insert_with(TableStats::default);
+        entry.generations += 1;
+        entry.wall_time += wall_time;
+        entry.rows += rows.len() as u64;
+        entry.bytes += bytes;
+    }
+}
+
+/// Records a scan served from rows the cursor had already generated.
+pub fn record_cache_hit(table_name: &str) {
+    if let Ok(mut stats) = TABLE_STATS.lock() {
+        stats.entry(table_name.to_string()).or_insert_with(TableStats::default).cache_hits += 1;
+    }
+}
+
+/// Records a generation given up after `timeout`, leaving a warning for `take_warnings`.
+pub fn record_timeout(table_name: &str, timeout: Duration) {
+    if let Ok(mut stats) = TABLE_STATS.lock() {
+        stats.entry(table_name.to_string()).or_insert_with(TableStats::default).timeouts += 1;
+    }
+    record_warning(table_name, &format!("generation timed out after {:.3}s, no rows returned",
+                                        wall_time_ms(timeout) / 1000.0));
+}
+
+pub fn record_warning(table_name: &str, message: &str) {
+    if let Ok(mut warnings) = WARNINGS.lock() {
+        warnings.push((table_name.to_string(), message.to_string()));
+    }
+}
+
+/// Returns the warnings recorded since the last call, prefixed with their table.
+pub fn take_warnings() -> Vec<String> {
+    match WARNINGS.lock() {
+        Ok(mut warnings) => warnings.drain(..).map(|(table, message)| format!("{}: {}", table, message)).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Returns the warnings of one table recorded since the last call, leaving the others.
+pub fn take_table_warnings(table_name: &str) -> Vec<String> {
+    match WARNINGS.lock() {
+        Ok(mut warnings) => {
+            let (taken, kept) = warnings.drain(..).partition(|(table, _)| table == table_name);
+            *warnings = kept;
+            taken.into_iter().map(|(_, message)| message).collect()
+        },
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn get_table_stats() -> Vec<(String, TableStats)> {
+    match TABLE_STATS.lock() {
+        Ok(stats) => stats.iter().map(|(name, s)| (name.clone(), s.clone())).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn reset_table_stats() {
+    if let Ok(mut stats) = TABLE_STATS.lock() {
+        stats.clear();
+    }
+}
+
+fn wall_time_ms(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + duration.subsec_nanos() as f64 / 1_000_000.0
+}
+
+/// Prints the per-table generation summary shown by `--profile`.
+pub fn print_profile() {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![
+        Cell::new("table"),
+        Cell::new("generations"),
+        Cell::new("wall_time_ms"),
+        Cell::new("rows"),
+        Cell::new("bytes"),
+        Cell::new("cache_hits"),
+        Cell::new("timeouts"),
+    ]));
+    for (name, stats) in get_table_stats() {
+        table.add_row(Row::new(vec![
+            Cell::new(&name),
+            Cell::new(&stats.generations.to_string()),
+            Cell::new(&format!("{:.3}", wall_time_ms(stats.wall_time))),
+            Cell::new(&stats.rows.to_string()),
+            Cell::new(&stats.bytes.to_string()),
+            Cell::new(&stats.cache_hits.to_string()),
+            Cell::new(&stats.timeouts.to_string()),
+        ]));
+    }
+    println!("{}", table);
+}
+
+impl SiqueryTableStatsRow {
+    pub fn get_specific() -> Vec<SiqueryTableStatsRow> {
+        get_table_stats().into_iter().map(|(name, stats)| {
+            SiqueryTableStatsRow {
+                name,
+                generations: stats.generations as i64,
+                wall_time_ms: wall_time_ms(stats.wall_time),
+                rows: stats.rows as i64,
+                bytes: stats.bytes as i64,
+                cache_hits: stats.cache_hits as i64,
+                timeouts: stats.timeouts as i64,
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_generation() {
+        let rows = vec![
+            vec![Value::Integer(1), Value::Text("init".to_string())],
+            vec![Value::Integer(2), Value::Null],
+        ];
+        record_generation("profile_test", Duration::from_millis(3), &rows);
+        record_cache_hit("profile_test");
+        let stats = get_table_stats().into_iter()
+            .find(|(name, _)| name == "profile_test")
+            .map(|(_, stats)| stats)
+            .unwrap();
+        assert_eq!(stats.generations, 1);
+        assert_eq!(stats.rows, 2);
+        assert_eq!(stats.bytes, 20);
+        assert_eq!(stats.cache_hits, 1);
+        assert_eq!(wall_time_ms(stats.wall_time), 3.0);
+    }
+
+    #[test]
+    fn test_record_timeout() {
+        record_timeout("profile_timeout_test", Duration::from_millis(1500));
+        let stats = get_table_stats().into_iter()
+            .find(|(name, _)| name == "profile_timeout_test")
+            .map(|(_, stats)| stats)
+            .unwrap();
+        assert_eq!(stats.timeouts, 1);
+        assert_eq!(take_table_warnings("profile_timeout_test"),
+                   vec!["generation timed out after 1.500s, no rows returned".to_string()]);
+    }
+}
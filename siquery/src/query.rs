@@ -1,10 +1,19 @@
 use crate::tables::*;
 use crate::vtab::*;
-use rusqlite::{version_number, Connection, Error, NO_PARAMS};
+use rusqlite::{version_number, Connection, Error, Rows, Statement, ToSql};
 use rusqlite::types::Value;
 use crate::printer::*;
-use crate::html::print_html;
+use crate::html::{collect_rows, HtmlReport};
+use crate::profile::{record_generation, record_cache_hit, record_timeout, record_warning};
+use crate::options::{cache_ttl, table_allowed, table_policy, table_timeout, DeniedTables};
+use crate::utils::set_deadline;
+use crate::info::start_time;
 use serde_json::{Value as serdValue, Map};
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
 
 fn select_all<T>(table: &Vec<T>) -> Vec<Vec<Value>> where T:Table+Sized {
     let mut res: Vec<Vec<Value>> = Vec::new();
@@ -58,7 +67,281 @@ fn select<T>(table: &Vec<T>, columns: Vec<String>) -> Vec<Vec<Value>> where T:Ta
     res
 }
 
+lazy_static! {
+    static ref TABLE_CACHE: Mutex<HashMap<String, (Instant, Vec<Vec<Value>>)>> = Mutex::new(HashMap::new());
+    /// Tables whose last generation ran past its timeout and has not returned yet.
+    static ref OVERDUE: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+}
+
+fn cached_table(name: &str, ttl: Duration) -> Option<Vec<Vec<Value>>> {
+    let cache = TABLE_CACHE.lock().ok()?;
+    let (generated, rows) = cache.get(name)?;
+    if generated.elapsed() < ttl {
+        Some(rows.clone())
+    } else {
+        None
+    }
+}
+
+/// Generates a table and records its generation statistics.
+///
+/// Tables with a cache TTL are served from the last generation while it is fresh. Tables
+/// denied by the table policy return no rows.
 pub fn query_table(name: &str, columns: Vec<String>) -> Vec<Vec<Value>> {
+    if !table_allowed(name) {
+        record_warning(name, "table disabled by policy, no rows returned");
+        return Vec::new();
+    }
+    let ttl = cache_ttl(name);
+    if let Some(ttl) = ttl {
+        if let Some(rows) = cached_table(name, ttl) {
+            record_cache_hit(name);
+            return rows;
+        }
+    }
+    let start = Instant::now();
+    let res = match table_timeout(name) {
+        Some(timeout) => generate_with_timeout(name, columns, timeout),
+        None => Some(generate_table(name, columns)),
+    };
+    let finished = res.is_some();
+    let res = res.unwrap_or_default();
+    record_generation(name, start.elapsed(), &res);
+    // A generation given up on is not cached, its empty rows would hide the table for the whole TTL.
+    if ttl.is_some() && finished {
+        if let Ok(mut cache) = TABLE_CACHE.lock() {
+            cache.insert(name.to_string(), (Instant::now(), res.clone()));
+        }
+    }
+    res
+}
+
+/// Clears the overdue mark of a table once its worker is done, even if the generator panicked.
+struct OverdueGuard(String);
+
+impl Drop for OverdueGuard {
+    fn drop(&mut self) {
+        if let Ok(mut overdue) = OVERDUE.lock() {
+            overdue.remove(&self.0);
+        }
+    }
+}
+
+/// Operator of a constraint handed to a table generator.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ConstraintOp {
+    Eq,
+    Gt,
+    Ge,
+    Lt,
+    Le,
+    Like,
+    Glob,
+}
+
+impl ConstraintOp {
+    pub fn sql(self) -> &'static str {
+        match self {
+            ConstraintOp::Eq => "=",
+            ConstraintOp::Gt => ">",
+            ConstraintOp::Ge => ">=",
+            ConstraintOp::Lt => "<",
+            ConstraintOp::Le => "<=",
+            ConstraintOp::Like => "LIKE",
+            ConstraintOp::Glob => "GLOB",
+        }
+    }
+}
+
+/// A `WHERE` term of a query on one column of a table, with its value.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Constraint {
+    pub column: &'static str,
+    pub op: ConstraintOp,
+    pub value: Value,
+}
+
+/// A constraint a table generator handles itself.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ConstraintSpec {
+    pub column: &'static str,
+    pub op: ConstraintOp,
+    /// The generator fully applies the constraint, so SQLite does not check the rows against it.
+    pub omit: bool,
+}
+
+const fn spec(column: &'static str, op: ConstraintOp, omit: bool) -> ConstraintSpec {
+    ConstraintSpec { column, op, omit }
+}
+
+/// `carve = 1` starts a carve of the matching paths, the `carve` column being the archive.
+#[cfg(feature = "carve")]
+const CARVES_CONSTRAINTS: &[ConstraintSpec] = &[
+    spec("path", ConstraintOp::Eq, false),
+    spec("path", ConstraintOp::Like, false),
+    spec("path", ConstraintOp::Glob, false),
+    spec("carve", ConstraintOp::Eq, true),
+];
+
+/// `time` lower bounds select the buffered events to read, or to drain with `--drain_events`.
+#[cfg(feature = "file_events")]
+const FILE_EVENTS_CONSTRAINTS: &[ConstraintSpec] = &[
+    spec("time", ConstraintOp::Gt, false),
+    spec("time", ConstraintOp::Ge, false),
+];
+
+/// Constraints the generator of `name` is given, see `query_table_constrained`. Other
+/// constraints are only checked by SQLite on the generated rows.
+pub(crate) fn table_constraints(name: &str) -> &'static [ConstraintSpec] {
+    match name {
+        #[cfg(feature = "file_events")]
+        "file_events" => FILE_EVENTS_CONSTRAINTS,
+        #[cfg(feature = "carve")]
+        "carves" => CARVES_CONSTRAINTS,
+        _ => &[],
+    }
+}
+
+/// Generates a table for the constraints of a query on it, see `table_constraints`.
+///
+/// Without constraints this is `query_table`. Constrained generations are neither cached nor
+/// bound by the table timeout, as their rows and side effects depend on the query.
+pub(crate) fn query_table_constrained(name: &str, constraints: &[Constraint]) -> Vec<Vec<Value>> {
+    if constraints.is_empty() {
+        return query_table(name, vec![]);
+    }
+    if !table_allowed(name) {
+        record_warning(name, "table disabled by policy, no rows returned");
+        return Vec::new();
+    }
+    let start = Instant::now();
+    let res = match name {
+        #[cfg(feature = "file_events")]
+        "file_events" => select(&FileEventsRow::get_constrained(constraints), vec![]),
+        #[cfg(feature = "carve")]
+        "carves" => select(&CarvesRow::get_constrained(constraints), vec![]),
+        _ => generate_table(name, vec![]),
+    };
+    record_generation(name, start.elapsed(), &res);
+    res
+}
+
+/// Column names of a `CREATE TABLE` statement. Definitions are split at the commas between its
+/// outer parentheses that are neither quoted nor nested, as in `DECIMAL(10, 2)` or `DEFAULT 'a,b'`.
+pub(crate) fn schema_column_names(schema: &str) -> Vec<String> {
+    let start = match schema.find('(') {
+        Some(start) => start + 1,
+        None => return Vec::new(),
+    };
+    let mut definitions = vec![String::new()];
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    for c in schema[start..].chars() {
+        match (quote, c) {
+            // A doubled quote closes and reopens the quote, keeping both characters.
+            (Some(q), _) if c == q || (q == '[' && c == ']') => quote = None,
+            (Some(_), _) => {},
+            (None, '"') | (None, '\'') | (None, '`') | (None, '[') => quote = Some(c),
+            (None, '(') => depth += 1,
+            (None, ')') if depth == 0 => break,
+            (None, ')') => depth -= 1,
+            (None, ',') if depth == 0 => {
+                definitions.push(String::new());
+                continue;
+            },
+            _ => {},
+        }
+        definitions.last_mut().unwrap().push(c);
+    }
+    definitions.iter()
+        .map(|definition| definition.trim())
+        .filter(|definition| !definition.is_empty())
+        .map(|definition| column_name(definition))
+        .collect()
+}
+
+/// First token of a column definition, unquoted.
+fn column_name(definition: &str) -> String {
+    let mut chars = definition.chars();
+    let close = match chars.next() {
+        Some('"') => '"',
+        Some('\'') => '\'',
+        Some('`') => '`',
+        Some('[') => ']',
+        _ => return definition.split_whitespace().next().unwrap_or("").to_string(),
+    };
+    let mut name = String::new();
+    let mut chars = chars.peekable();
+    while let Some(c) = chars.next() {
+        if c == close {
+            if close != ']' && chars.peek() == Some(&close) {
+                chars.next();
+            } else {
+                break;
+            }
+        }
+        name.push(c);
+    }
+    name
+}
+
+/// Generates `siquery_tables` for the tables registered on one connection.
+pub(crate) fn query_registered_tables(tables: &[String]) -> Vec<Vec<Value>> {
+    let start = Instant::now();
+    let res = select(&SiqueryTablesRow::get_specific_ex(tables), vec![]);
+    record_generation("siquery_tables", start.elapsed(), &res);
+    res
+}
+
+/// Generates a table on a worker thread and gives up on it after `timeout`, returning `None`.
+///
+/// Subprocesses started through `utils::command_output` are killed at the deadline. A worker
+/// blocked elsewhere is left to finish on its own, and the table returns no rows until it has.
+fn generate_with_timeout(name: &str, columns: Vec<String>, timeout: Duration) -> Option<Vec<Vec<Value>>> {
+    if OVERDUE.lock().map(|overdue| overdue.contains(name)).unwrap_or(false) {
+        record_warning(name, "previous generation is still running, no rows returned");
+        return None;
+    }
+    let (sender, receiver) = mpsc::channel();
+    let table = name.to_string();
+    let deadline = Instant::now() + timeout;
+    let worker = thread::Builder::new().name(format!("siquery-{}", name)).spawn(move || {
+        // The guard takes the lock after the sender is dropped, so it runs after the caller has
+        // either received the rows or marked the table overdue, see below.
+        let _guard = OverdueGuard(table.clone());
+        let sender = sender;
+        set_deadline(Some(deadline));
+        let _ = sender.send(generate_table(&table, columns));
+    });
+    if let Err(e) = worker {
+        record_warning(name, &format!("could not start a generation thread: {}", e));
+        return None;
+    }
+    match receiver.recv_timeout(timeout) {
+        Ok(rows) => Some(rows),
+        Err(RecvTimeoutError::Timeout) => {
+            let mut overdue = match OVERDUE.lock() {
+                Ok(overdue) => overdue,
+                Err(_) => return None,
+            };
+            // The worker may have sent its rows between the timeout and taking the lock.
+            if let Ok(rows) = receiver.try_recv() {
+                return Some(rows);
+            }
+            overdue.insert(name.to_string());
+            drop(receiver);
+            drop(overdue);
+            record_timeout(name, timeout);
+            None
+        },
+        Err(RecvTimeoutError::Disconnected) => {
+            record_warning(name, "generation failed, no rows returned");
+            None
+        }
+    }
+}
+
+fn generate_table(name: &str, columns: Vec<String>) -> Vec<Vec<Value>> {
     let res = match name {
         #[cfg(feature = "etc_hosts")]
         "etc_hosts" => {
@@ -270,6 +553,42 @@ pub fn query_table(name: &str, columns: Vec<String>) -> Vec<Vec<Value>> {
             let table = LaunchdOverridesRow::get_specific();
             select(&table, columns)
         },
+        #[cfg(feature = "file_events")]
+        "file_events" => {
+            let table = FileEventsRow::get_specific();
+            select(&table, columns)
+        },
+        #[cfg(feature = "carve")]
+        "carves" => {
+            let table = CarvesRow::get_specific();
+            select(&table, columns)
+        },
+        "siquery_table_stats" => {
+            let table = SiqueryTableStatsRow::get_specific();
+            select(&table, columns)
+        },
+        "siquery_info" => {
+            let table = SiqueryInfoRow::get_specific();
+            select(&table, columns)
+        },
+        "siquery_tables" => {
+            let table = SiqueryTablesRow::get_specific();
+            select(&table, columns)
+        },
+        "siquery_flags" => {
+            let table = SiqueryFlagsRow::get_specific();
+            select(&table, columns)
+        },
+        #[cfg(test)]
+        "slow_dummy" => {
+            thread::sleep(Duration::from_millis(300));
+            generate_table("Dummy", columns)
+        },
+        #[cfg(test)]
+        "panicking_dummy" => {
+            thread::sleep(Duration::from_millis(100));
+            panic!("panicking_dummy");
+        },
         _ => { // for tests only
             let table: Vec<Dummy> = vec![
                 Dummy{a:25, b:25},
@@ -281,13 +600,25 @@ pub fn query_table(name: &str, columns: Vec<String>) -> Vec<Vec<Value>> {
 }
 
 pub fn init_db()-> Connection {
+    init_db_with_tables(get_table_list())
+}
+
+/// Creates a connection exposing only the given tables.
+///
+/// Tables denied by the table policy are left out, or registered as stubs that fail when queried.
+pub fn init_db_with_tables(tables: Vec<String>) -> Connection {
     let db = Connection::open_in_memory().unwrap();
-    load_module(&db).unwrap();
-    register_tables(&db, get_table_list());
+    register_tables(&db, tables);
     db
 }
 
 fn register_tables(db: &Connection, tables: Vec<String>) {
+    start_time();
+    let policy = table_policy();
+    let tables: Vec<String> = tables.into_iter()
+        .filter(|table| policy.denied == DeniedTables::Stub || policy.allows(table))
+        .collect();
+    load_module_with_tables(db, tables.clone()).unwrap();
     let version = version_number();
     if version < 3008012 {
         println!("version: '{}' is not supported", version);
@@ -577,6 +908,37 @@ pub fn get_schema(table_name: &str) -> Option<String> {
             let column_types = LaunchdOverridesRow::get_columns_type();
             _schema = create_schema(&column_names, &column_types)
         },
+        #[cfg(feature = "file_events")]
+        "file_events" => {
+            let column_names = FileEventsRow::get_columns_name();
+            let column_types = FileEventsRow::get_columns_type();
+            _schema = create_schema(&column_names, &column_types)
+        },
+        "carves" => {
+            let column_names = CarvesRow::get_columns_name();
+            let column_types = CarvesRow::get_columns_type();
+            _schema = create_schema(&column_names, &column_types)
+        },
+        "siquery_table_stats" => {
+            let column_names = SiqueryTableStatsRow::get_columns_name();
+            let column_types = SiqueryTableStatsRow::get_columns_type();
+            _schema = create_schema(&column_names, &column_types)
+        },
+        "siquery_info" => {
+            let column_names = SiqueryInfoRow::get_columns_name();
+            let column_types = SiqueryInfoRow::get_columns_type();
+            _schema = create_schema(&column_names, &column_types)
+        },
+        "siquery_tables" => {
+            let column_names = SiqueryTablesRow::get_columns_name();
+            let column_types = SiqueryTablesRow::get_columns_type();
+            _schema = create_schema(&column_names, &column_types)
+        },
+        "siquery_flags" => {
+            let column_names = SiqueryFlagsRow::get_columns_name();
+            let column_types = SiqueryFlagsRow::get_columns_type();
+            _schema = create_schema(&column_names, &column_types)
+        },
         _ => {
             let column_names = Dummy::get_columns_name();
             let column_types = Dummy::get_columns_type();
@@ -587,6 +949,36 @@ pub fn get_schema(table_name: &str) -> Option<String> {
 }
 
 pub fn execute_query(db: &Connection, query: &str, table_name: String, flag: u8) -> Vec<Map<String,serdValue>>{
+    execute_query_with_params(db, query, table_name, flag, &[])
+}
+
+/// Value bound to a `:name` placeholder. Values that are plain integers are bound as
+/// integers so they compare naturally against INTEGER columns, anything else as text.
+fn param_value(value: &str) -> Value {
+    match value.parse::<i64>() {
+        Ok(i) if i.to_string() == value => Value::Integer(i),
+        _ => Value::Text(value.to_string()),
+    }
+}
+
+/// Runs a statement, binding each `(name, value)` pair the statement refers to.
+/// Names may be given with or without the leading ':'.
+pub(crate) fn query_with_params<'a>(statement: &'a mut Statement, params: &[(String, String)]) -> Result<Rows<'a>, Error> {
+    let mut bound: Vec<(String, Value)> = Vec::new();
+    for (name, value) in params.iter() {
+        let name = if name.starts_with(':') { name.clone() } else { format!(":{}", name) };
+        if statement.parameter_index(&name)?.is_some() {
+            bound.push((name, param_value(value)));
+        }
+    }
+    let named: Vec<(&str, &dyn ToSql)> = bound.iter()
+        .map(|(name, value)| (name.as_str(), value as &dyn ToSql))
+        .collect();
+    statement.query_named(&named)
+}
+
+pub fn execute_query_with_params(db: &Connection, query: &str, table_name: String, flag: u8,
+                                 params: &[(String, String)]) -> Vec<Map<String,serdValue>>{
     let mut table_result: Vec<Vec<Value>> = Vec::new();
     let mut writer: Vec<Map<String,serdValue>> = Vec::new();
     let mut row: Vec<Value> = Vec::new();
@@ -603,11 +995,28 @@ pub fn execute_query(db: &Connection, query: &str, table_name: String, flag: u8)
             }
             table_result.push(row);
 
-            let mut response = statement_res.query(NO_PARAMS).unwrap();
+            let mut response = match query_with_params(&mut statement_res, params) {
+                Ok(response) => response,
+                Err(e) => {
+                    println!("{}", e);
+                    return writer;
+                }
+            };
             if flag == 2 {
                 print_csv(col_name_internal, &mut response);
             } else if flag == 3 {
-                print_html(col_name_internal, &mut response, query);
+                let name = if table_name.is_empty() { "query".to_string() } else { table_name };
+                match collect_rows(&mut response, col_name_internal.len()) {
+                    Ok(rows) => {
+                        let mut report = HtmlReport::new(db);
+                        report.add_rows(&name, query, col_name_internal, rows);
+                        print!("{}", report.render());
+                    },
+                    Err(e) => println!("{}", e),
+                }
+            } else if flag == 4 {
+                let name = if table_name.is_empty() { query.to_string() } else { table_name };
+                print_ndjson(&name, &col_name_internal, &mut response);
             } else if flag == 1 {
                 writer = print_json(table_name, &col_name_internal, &mut response);
             } else {
@@ -625,10 +1034,182 @@ pub fn execute_query(db: &Connection, query: &str, table_name: String, flag: u8)
     writer
 }
 
+/// Runs a query and returns its rows as JSON objects, without printing them.
+pub fn execute_query_json(db: &Connection, query: &str, params: &[(String, String)]) -> Result<Vec<Map<String,serdValue>>, Error> {
+    let mut statement = db.prepare(&query)?;
+    let col_names: Vec<String> = statement.column_names().iter().map(|c| c.to_string()).collect();
+    let mut response = query_with_params(&mut statement, params)?;
+    Ok(rows_to_json(&col_names, &mut response))
+}
+
+/// Splits a script into statements on ';', ignoring separators inside quotes and comments.
+pub fn split_statements(sql: &str) -> Vec<String> {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' | '`' | '[' => {
+                let end = if c == '[' { ']' } else { c };
+                current.push(c);
+                while let Some(q) = chars.next() {
+                    current.push(q);
+                    if q == end {
+                        // A doubled quote is an escaped quote.
+                        if end != ']' && chars.peek() == Some(&end) {
+                            current.push(chars.next().unwrap());
+                            continue;
+                        }
+                        break;
+                    }
+                }
+            },
+            '-' if chars.peek() == Some(&'-') => {
+                while let Some(q) = chars.next() {
+                    if q == '\n' {
+                        current.push('\n');
+                        break;
+                    }
+                }
+            },
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                while let Some(q) = chars.next() {
+                    if previous == '*' && q == '/' {
+                        break;
+                    }
+                    previous = q;
+                }
+                current.push(' ');
+            },
+            ';' => {
+                if !current.trim().is_empty() {
+                    statements.push(current.trim().to_string());
+                }
+                current.clear();
+            },
+            _ => current.push(c),
+        }
+    }
+    if !current.trim().is_empty() {
+        statements.push(current.trim().to_string());
+    }
+    statements
+}
+
+/// Runs every statement of a script in order, labelling each result set.
+///
+/// In json mode the results are printed as one array of `{"query", "results"}` objects,
+/// and in ndjson mode rows are streamed with the statement as their name.
+pub fn execute_statements(db: &Connection, sql: &str, flag: u8, params: &[(String, String)]) {
+    let mut json_results: Vec<serdValue> = Vec::new();
+    for statement in split_statements(sql) {
+        if flag == 1 {
+            let results = execute_query_with_params(db, &statement, statement.clone(), flag, params);
+            json_results.push(json!({"query": statement, "results": results}));
+        } else if flag == 4 {
+            execute_query_with_params(db, &statement, statement.clone(), flag, params);
+        } else {
+            println!("-- {}", statement);
+            execute_query_with_params(db, &statement, "".to_string(), flag, params);
+        }
+    }
+    if flag == 1 {
+        println!("{}", serde_json::to_string_pretty(&json_results).unwrap());
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_split_statements(){
+        let script = "select 1; select ';' as a -- trailing; comment\n;\n/* a; b */ select \"x;\"\n";
+        assert_eq!(split_statements(script), vec!["select 1", "select ';' as a", "select \"x;\""]);
+        assert_eq!(split_statements("select 'it''s;'"), vec!["select 'it''s;'"]);
+        assert!(split_statements(" ; ;").is_empty());
+    }
+
+    #[test]
+    fn test_query_params(){
+        let db = init_db();
+        let params = vec![("a".to_string(), "25".to_string()), (":unused".to_string(), "x".to_string())];
+        let rows = execute_query_json(&db, "select b from Dummy where a = :a", &params).unwrap();
+        assert_eq!(rows.len(), 2);
+        let params = vec![("name".to_string(), "' or 1=1 --".to_string())];
+        let rows = execute_query_json(&db, "select :name as name", &params).unwrap();
+        assert_eq!(rows[0]["name"], "' or 1=1 --");
+    }
+
+    #[test]
+    fn test_generation_timeout() {
+        use crate::options::{set_cache_ttl, set_table_timeout};
+        use crate::profile::take_table_warnings;
+
+        set_table_timeout("slow_dummy", Duration::from_millis(50));
+        set_cache_ttl("slow_dummy", Duration::from_secs(60));
+        assert!(query_table("slow_dummy", vec![]).is_empty());
+        // The worker is still sleeping, so no second one is started.
+        assert!(query_table("slow_dummy", vec![]).is_empty());
+        let warnings = take_table_warnings("slow_dummy");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("timed out"));
+        assert!(warnings[1].contains("still running"));
+
+        // The empty rows of the timed out generations were not cached.
+        thread::sleep(Duration::from_millis(500));
+        set_table_timeout("slow_dummy", Duration::from_secs(10));
+        assert_eq!(query_table("slow_dummy", vec![]).len(), 2);
+    }
+
+    #[test]
+    fn test_generation_panic_after_timeout() {
+        use crate::options::set_table_timeout;
+        use crate::profile::take_table_warnings;
+
+        set_table_timeout("panicking_dummy", Duration::from_millis(20));
+        assert!(query_table("panicking_dummy", vec![]).is_empty());
+        thread::sleep(Duration::from_millis(300));
+        assert!(query_table("panicking_dummy", vec![]).is_empty());
+        let warnings = take_table_warnings("panicking_dummy");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("timed out"));
+        assert!(!warnings[1].contains("still running"));
+    }
+
+    #[test]
+    fn test_table_policy() {
+        use crate::options::{set_table_policy, TablePolicy};
+
+        let tables = vec!["policy_dummy".to_string(), "policy_denied".to_string()];
+        let mut policy = TablePolicy::default();
+        policy.deny.insert("policy_denied".to_string());
+        set_table_policy(policy.clone());
+        let db = init_db_with_tables(tables.clone());
+        assert_eq!(execute_query_json(&db, "select * from policy_dummy", &[]).unwrap().len(), 2);
+        let error = execute_query_json(&db, "select * from policy_denied", &[]).unwrap_err();
+        assert!(error.to_string().contains("no such table"));
+        assert!(query_table("policy_denied", vec![]).is_empty());
+        assert_eq!(query_table("policy_dummy", vec![]).len(), 2);
+
+        policy.denied = DeniedTables::Stub;
+        set_table_policy(policy);
+        let db = init_db_with_tables(tables);
+        let error = execute_query_json(&db, "select * from policy_denied", &[]).unwrap_err();
+        assert!(error.to_string().contains("table disabled by policy"));
+        set_table_policy(TablePolicy::default());
+    }
+
+    #[test]
+    fn test_schema_column_names() {
+        assert_eq!(schema_column_names("CREATE TABLE x(\"a\" TEXT, \"b\" INTEGER);"), vec!["a", "b"]);
+        assert_eq!(schema_column_names("CREATE TABLE x(\"a\"\"b\" TEXT, c TEXT DEFAULT 'x,\"y', d DECIMAL(10, 2), [e,f] INT)"),
+                   vec!["a\"b", "c", "d", "e,f"]);
+        assert!(schema_column_names("CREATE TABLE x()").is_empty());
+    }
+
     #[test]
     fn test_schema_creation(){
         let schema =  create_schema(&vec!["name", "id"], &vec!["\" TEXT", "\" INTEGER"] );
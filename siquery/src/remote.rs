@@ -0,0 +1,568 @@
+//! Client of the osquery remote API, as served by TLS fleet managers such as Fleet.
+//!
+//! A node enrolls with the shared enroll secret and receives a node key, which every later
+//! request carries. The key is kept in a file so a restarted node does not enroll again; when
+//! the server answers `node_invalid`, the key is dropped and the node enrolls once more.
+
+use chrono::Utc;
+use rusqlite::Connection;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use serde_json::{Value as serdValue, Map};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::events::unix_time;
+use crate::query::execute_query_json;
+
+pub const ENROLL_PATH: &str = "/api/v1/osquery/enroll";
+pub const CONFIG_PATH: &str = "/api/v1/osquery/config";
+pub const LOG_PATH: &str = "/api/v1/osquery/log";
+pub const DISTRIBUTED_READ_PATH: &str = "/api/v1/osquery/distributed/read";
+pub const DISTRIBUTED_WRITE_PATH: &str = "/api/v1/osquery/distributed/write";
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    /// The request failed or the server answered with an error status.
+    Http(String),
+    Tls(String),
+    /// The server answered with something the remote API does not allow.
+    Protocol(String),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Io(e) => write!(f, "{}", e),
+            Error::Http(msg) => write!(f, "{}", msg),
+            Error::Tls(msg) => write!(f, "tls: {}", msg),
+            Error::Protocol(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        Error::Io(e)
+    }
+}
+
+impl From<ureq::Error> for Error {
+    fn from(e: ureq::Error) -> Error {
+        Error::Http(e.to_string())
+    }
+}
+
+/// Where and how a node reaches its server.
+#[derive(Clone, Debug)]
+pub struct RemoteConfig {
+    /// Base URL of the server, such as `https://fleet.example.com:8080`.
+    pub server: String,
+    pub enroll_secret: String,
+    /// Name the node enrolls under, the host name when empty.
+    pub host_identifier: String,
+    /// File holding the node key between runs.
+    pub node_key_path: PathBuf,
+    /// PEM bundle of the certificates trusted for the server, instead of the bundled web roots.
+    pub tls_server_certs: Option<PathBuf>,
+    pub timeout: Duration,
+}
+
+impl Default for RemoteConfig {
+    fn default() -> RemoteConfig {
+        RemoteConfig {
+            server: String::new(),
+            enroll_secret: String::new(),
+            host_identifier: String::new(),
+            node_key_path: default_node_key_path(),
+            tls_server_certs: None,
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Default node key location, next to the history store.
+pub fn default_node_key_path() -> PathBuf {
+    crate::history::default_history_path().with_file_name("node_key")
+}
+
+/// Query of the `schedule` of a configuration, or of one of its packs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScheduledQuery {
+    /// Name results are logged under, `pack/<pack>/<query>` for queries of packs.
+    pub name: String,
+    pub query: String,
+    /// Seconds between two runs.
+    pub interval: u64,
+}
+
+/// Result of a distributed query, sent back with `distributed_write`.
+#[derive(Clone, Debug, Default)]
+pub struct DistributedResult {
+    pub id: String,
+    pub rows: Vec<Map<String, serdValue>>,
+    /// Why the query failed, it then has no rows.
+    pub error: Option<String>,
+}
+
+/// osquery sends every column value as a string, so servers expect them that way.
+fn string_row(row: &Map<String, serdValue>) -> serdValue {
+    let row: Map<String, serdValue> = row.iter()
+        .map(|(column, value)| {
+            let value = match value {
+                serdValue::String(s) => s.clone(),
+                serdValue::Null => String::new(),
+                v => v.to_string(),
+            };
+            (column.clone(), serdValue::String(value))
+        })
+        .collect();
+    serdValue::Object(row)
+}
+
+fn tls_config(certs: &Path) -> Result<rustls::ClientConfig> {
+    let mut roots = rustls::RootCertStore::empty();
+    let pem = CertificateDer::pem_file_iter(certs)
+        .map_err(|e| Error::Tls(format!("{}: {}", certs.display(), e)))?;
+    for cert in pem {
+        let cert = cert.map_err(|e| Error::Tls(format!("{}: {}", certs.display(), e)))?;
+        roots.add(cert).map_err(|e| Error::Tls(format!("{}: {}", certs.display(), e)))?;
+    }
+    if roots.is_empty() {
+        return Err(Error::Tls(format!("{}: no certificate found", certs.display())));
+    }
+    Ok(rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth())
+}
+
+fn read_node_key(path: &Path) -> Option<String> {
+    let key = fs::read_to_string(path).ok()?;
+    let key = key.trim();
+    if key.is_empty() {
+        None
+    } else {
+        Some(key.to_string())
+    }
+}
+
+/// Writes the node key to a file only the owner can read, from its creation on.
+fn write_node_key(path: &Path, key: &str) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(0o600);
+        // The mode only applies to new files; a key written by an older version may be wider.
+        if path.exists() {
+            fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+        }
+    }
+    options.open(path)?.write_all(key.as_bytes())
+}
+
+fn node_invalid(response: &serdValue) -> bool {
+    response.get("node_invalid").and_then(|invalid| invalid.as_bool()) == Some(true)
+}
+
+/// Details sent on enrollment, which servers use to fill in the host.
+pub fn host_details(db: &Connection) -> Map<String, serdValue> {
+    let mut details = Map::new();
+    for (name, query) in [
+        ("os_version", "select * from os_version"),
+        ("system_info", "select * from system_info"),
+        ("osquery_info", "select version, pid, start_time, build_platform from siquery_info"),
+    ].iter() {
+        if let Some(row) = execute_query_json(db, query, &[]).ok().and_then(|rows| rows.into_iter().next()) {
+            details.insert(name.to_string(), string_row(&row));
+        }
+    }
+    details
+}
+
+/// Queries of the `schedule` and inline `packs` of a configuration, skipping malformed entries.
+pub fn schedule(config: &serdValue) -> Vec<ScheduledQuery> {
+    let entry = |name: String, query: &serdValue| -> Option<ScheduledQuery> {
+        Some(ScheduledQuery {
+            name,
+            query: query.get("query")?.as_str()?.to_string(),
+            interval: match query.get("interval")? {
+                serdValue::String(interval) => interval.parse().ok()?,
+                interval => interval.as_u64()?,
+            },
+        })
+    };
+    let mut queries = Vec::new();
+    if let Some(schedule) = config.get("schedule").and_then(|schedule| schedule.as_object()) {
+        queries.extend(schedule.iter().filter_map(|(name, query)| entry(name.clone(), query)));
+    }
+    if let Some(packs) = config.get("packs").and_then(|packs| packs.as_object()) {
+        for (pack, content) in packs.iter() {
+            if let Some(pack_queries) = content.get("queries").and_then(|queries| queries.as_object()) {
+                queries.extend(pack_queries.iter()
+                    .filter_map(|(name, query)| entry(format!("pack/{}/{}", pack, name), query)));
+            }
+        }
+    }
+    queries
+}
+
+/// Node talking to an osquery remote API server.
+pub struct RemoteClient {
+    config: RemoteConfig,
+    agent: ureq::Agent,
+    node_key: Option<String>,
+    host_details: Map<String, serdValue>,
+}
+
+impl RemoteClient {
+    pub fn new(config: RemoteConfig) -> Result<RemoteClient> {
+        let mut builder = ureq::AgentBuilder::new().timeout(config.timeout);
+        if let Some(ref certs) = config.tls_server_certs {
+            builder = builder.tls_config(Arc::new(tls_config(certs)?));
+        }
+        let mut config = config;
+        if config.host_identifier.is_empty() {
+            config.host_identifier = crate::printer::host_identifier();
+        }
+        Ok(RemoteClient {
+            node_key: read_node_key(&config.node_key_path),
+            agent: builder.build(),
+            config,
+            host_details: Map::new(),
+        })
+    }
+
+    pub fn node_key(&self) -> Option<&str> {
+        self.node_key.as_deref()
+    }
+
+    pub fn host_identifier(&self) -> &str {
+        &self.config.host_identifier
+    }
+
+    /// Sets the details sent on the next enrollment, see `host_details`.
+    pub fn set_host_details(&mut self, details: Map<String, serdValue>) {
+        self.host_details = details;
+    }
+
+    /// Posts `body` and returns the json answer. Servers such as Fleet reject a node key with a 401
+    /// carrying `{"node_invalid": true}`, which is returned like a successful answer.
+    fn post(&self, path: &str, body: serdValue) -> Result<serdValue> {
+        let url = format!("{}{}", self.config.server.trim_end_matches('/'), path);
+        let response = match self.agent.post(&url).send_json(body) {
+            Ok(response) => response,
+            Err(ureq::Error::Status(status, response)) => {
+                let answer: Option<serdValue> = response.into_json().ok();
+                return match answer {
+                    Some(answer) if node_invalid(&answer) => Ok(answer),
+                    _ => Err(Error::Http(format!("{}: status code {}", url, status))),
+                };
+            },
+            Err(e) => return Err(e.into()),
+        };
+        response.into_json().map_err(|e| Error::Protocol(format!("{}: {}", url, e)))
+    }
+
+    /// Returns the node key, enrolling first when there is none.
+    pub fn enroll(&mut self) -> Result<String> {
+        if let Some(ref key) = self.node_key {
+            return Ok(key.clone());
+        }
+        let response = self.post(ENROLL_PATH, json!({
+            "enroll_secret": self.config.enroll_secret,
+            "host_identifier": self.config.host_identifier,
+            "host_details": self.host_details,
+        }))?;
+        if node_invalid(&response) {
+            return Err(Error::Protocol("enrollment refused, check the enroll secret".to_string()));
+        }
+        let key = response.get("node_key")
+            .and_then(|key| key.as_str())
+            .filter(|key| !key.is_empty())
+            .ok_or_else(|| Error::Protocol("enroll response has no node_key".to_string()))?
+            .to_string();
+        write_node_key(&self.config.node_key_path, &key)?;
+        self.node_key = Some(key.clone());
+        Ok(key)
+    }
+
+    /// Forgets the node key, so the next request enrolls again.
+    pub fn reset_node_key(&mut self) -> Result<()> {
+        self.node_key = None;
+        match fs::remove_file(&self.config.node_key_path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(Error::Io(e)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Sends a request carrying the node key, enrolling again once if the server rejects the key.
+    fn request(&mut self, path: &str, body: Map<String, serdValue>) -> Result<serdValue> {
+        for _ in 0..2 {
+            let mut body = body.clone();
+            body.insert("node_key".to_string(), serdValue::String(self.enroll()?));
+            let response = self.post(path, serdValue::Object(body))?;
+            if node_invalid(&response) {
+                self.reset_node_key()?;
+                continue;
+            }
+            return Ok(response);
+        }
+        Err(Error::Protocol("node key rejected right after enrolling".to_string()))
+    }
+
+    /// Fetches the configuration of the node.
+    pub fn config(&mut self) -> Result<serdValue> {
+        self.request(CONFIG_PATH, Map::new())
+    }
+
+    /// Sends `result` or `status` log lines.
+    pub fn log(&mut self, log_type: &str, data: Vec<serdValue>) -> Result<()> {
+        let mut body = Map::new();
+        body.insert("log_type".to_string(), json!(log_type));
+        body.insert("data".to_string(), serdValue::Array(data));
+        self.request(LOG_PATH, body).map(|_| ())
+    }
+
+    /// Logs the rows of a scheduled query as an osquery snapshot result.
+    pub fn log_snapshot(&mut self, name: &str, rows: &[Map<String, serdValue>]) -> Result<()> {
+        let now = Utc::now();
+        let line = json!({
+            "name": name,
+            "hostIdentifier": self.config.host_identifier,
+            "calendarTime": now.format("%a %b %e %H:%M:%S %Y UTC").to_string(),
+            "unixTime": unix_time(),
+            "epoch": 0,
+            "counter": 0,
+            "numerics": false,
+            "action": "snapshot",
+            "snapshot": rows.iter().map(string_row).collect::<Vec<serdValue>>(),
+        });
+        self.log("result", vec![line])
+    }
+
+    /// Fetches the pending distributed queries, as id and SQL.
+    pub fn distributed_read(&mut self) -> Result<BTreeMap<String, String>> {
+        let response = self.request(DISTRIBUTED_READ_PATH, Map::new())?;
+        let queries = match response.get("queries") {
+            Some(serdValue::Object(queries)) => queries,
+            None | Some(serdValue::Null) => return Ok(BTreeMap::new()),
+            Some(_) => return Err(Error::Protocol("distributed queries are not an object".to_string())),
+        };
+        Ok(queries.iter()
+            .filter_map(|(id, query)| query.as_str().map(|query| (id.clone(), query.to_string())))
+            .collect())
+    }
+
+    /// Sends the results of distributed queries.
+    pub fn distributed_write(&mut self, results: &[DistributedResult]) -> Result<()> {
+        let mut queries = Map::new();
+        let mut statuses = Map::new();
+        let mut messages = Map::new();
+        for result in results.iter() {
+            queries.insert(result.id.clone(), result.rows.iter().map(string_row).collect());
+            statuses.insert(result.id.clone(), json!(if result.error.is_some() { 1 } else { 0 }));
+            if let Some(ref error) = result.error {
+                messages.insert(result.id.clone(), json!(error));
+            }
+        }
+        let mut body = Map::new();
+        body.insert("queries".to_string(), serdValue::Object(queries));
+        body.insert("statuses".to_string(), serdValue::Object(statuses));
+        body.insert("messages".to_string(), serdValue::Object(messages));
+        self.request(DISTRIBUTED_WRITE_PATH, body).map(|_| ())
+    }
+
+    /// Runs the pending distributed queries on `db` and sends their results back.
+    ///
+    /// Returns the number of queries answered.
+    pub fn run_distributed(&mut self, db: &Connection) -> Result<usize> {
+        let queries = self.distributed_read()?;
+        if queries.is_empty() {
+            return Ok(0);
+        }
+        let results: Vec<DistributedResult> = queries.into_iter()
+            .map(|(id, query)| match execute_query_json(db, &query, &[]) {
+                Ok(rows) => DistributedResult { id, rows, error: None },
+                Err(e) => DistributedResult { id, rows: Vec::new(), error: Some(e.to_string()) },
+            })
+            .collect();
+        self.distributed_write(&results)?;
+        Ok(results.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::query::init_db;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Mutex;
+    use std::thread;
+
+    /// Minimal osquery remote API server: one enrolled key at a time, invalidated on demand.
+    struct MockServer {
+        enrollments: usize,
+        node_key: String,
+        invalidate: bool,
+        requests: Vec<(String, serdValue)>,
+    }
+
+    impl MockServer {
+        /// Answers like Fleet: 401 with `node_invalid` for a bad secret or key.
+        fn respond(&mut self, path: &str, body: serdValue) -> (u16, serdValue) {
+            self.requests.push((path.to_string(), body.clone()));
+            if path == ENROLL_PATH {
+                if body["enroll_secret"] != "secret" {
+                    return (401, json!({"node_invalid": true}));
+                }
+                self.enrollments += 1;
+                self.node_key = format!("key{}", self.enrollments);
+                return (200, json!({"node_key": self.node_key}));
+            }
+            if body["node_key"] != self.node_key.as_str() || self.invalidate {
+                self.invalidate = false;
+                return (401, json!({"node_invalid": true}));
+            }
+            let answer = match path {
+                CONFIG_PATH => json!({
+                    "schedule": {"uptime": {"query": "select 1 as up", "interval": 60}},
+                    "packs": {"base": {"queries": {"hosts": {"query": "select 2", "interval": "300"}}}},
+                }),
+                DISTRIBUTED_READ_PATH => json!({"queries": {
+                    "q1": "select 1 as one, 'x' as text",
+                    "q2": "select * from no_such_table",
+                }}),
+                _ => json!({}),
+            };
+            (200, answer)
+        }
+    }
+
+    fn serve(server: Arc<Mutex<MockServer>>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(_) => continue,
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let path = request_line.split_whitespace().nth(1).unwrap_or("").to_string();
+                let mut length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    let header = header.to_ascii_lowercase();
+                    if let Some(value) = header.strip_prefix("content-length:") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                let mut body = vec![0; length];
+                reader.read_exact(&mut body).unwrap();
+                let body = serde_json::from_slice(&body).unwrap_or(serdValue::Null);
+                let (status, response) = server.lock().unwrap().respond(&path, body);
+                let response = response.to_string();
+                let reason = if status == 200 { "OK" } else { "Unauthorized" };
+                write!(stream, "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+                                Connection: close\r\n\r\n{}", status, reason, response.len(), response).unwrap();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[test]
+    fn test_remote_client() {
+        let server = Arc::new(Mutex::new(MockServer {
+            enrollments: 0,
+            node_key: String::new(),
+            invalidate: false,
+            requests: Vec::new(),
+        }));
+        let node_key_path = std::env::temp_dir().join(format!("siquery_node_key_{}", std::process::id()));
+        let _ = fs::remove_file(&node_key_path);
+        let config = RemoteConfig {
+            server: serve(server.clone()),
+            enroll_secret: "secret".to_string(),
+            host_identifier: "test-host".to_string(),
+            node_key_path: node_key_path.clone(),
+            ..RemoteConfig::default()
+        };
+
+        let mut client = RemoteClient::new(config.clone()).unwrap();
+        let mut details = Map::new();
+        details.insert("system_info".to_string(), json!({"hostname": "test-host"}));
+        client.set_host_details(details);
+        let queries = schedule(&client.config().unwrap());
+        assert_eq!(queries.len(), 2);
+        assert_eq!(queries[0], ScheduledQuery { name: "uptime".to_string(), query: "select 1 as up".to_string(),
+                                                interval: 60 });
+        assert_eq!(queries[1].name, "pack/base/hosts");
+        assert_eq!(queries[1].interval, 300);
+        assert_eq!(fs::read_to_string(&node_key_path).unwrap(), "key1");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&node_key_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+
+        let db = init_db();
+        assert_eq!(client.run_distributed(&db).unwrap(), 2);
+        {
+            let server = server.lock().unwrap();
+            let (path, write) = server.requests.last().unwrap();
+            assert_eq!(path, DISTRIBUTED_WRITE_PATH);
+            assert_eq!(write["node_key"], "key1");
+            assert_eq!(write["queries"]["q1"], json!([{"one": "1", "text": "x"}]));
+            assert_eq!(write["statuses"]["q1"], 0);
+            assert_eq!(write["statuses"]["q2"], 1);
+            assert!(write["messages"]["q2"].as_str().unwrap().contains("no such table"));
+        }
+
+        // A rejected key is replaced by enrolling again, and the request is retried.
+        server.lock().unwrap().invalidate = true;
+        client.log_snapshot("uptime", &[]).unwrap();
+        assert_eq!(client.node_key(), Some("key2"));
+        {
+            let server = server.lock().unwrap();
+            let (path, log) = server.requests.last().unwrap();
+            assert_eq!(path, LOG_PATH);
+            assert_eq!(log["node_key"], "key2");
+            assert_eq!(log["data"][0]["name"], "uptime");
+            assert_eq!(log["data"][0]["hostIdentifier"], "test-host");
+            assert_eq!(server.enrollments, 2);
+            let (_, enroll) = server.requests.iter().find(|(path, _)| path == ENROLL_PATH).unwrap();
+            assert_eq!(enroll["host_details"]["system_info"]["hostname"], "test-host");
+        }
+
+        // A new client reuses the stored key instead of enrolling.
+        let mut client = RemoteClient::new(config.clone()).unwrap();
+        assert_eq!(client.node_key(), Some("key2"));
+        client.config().unwrap();
+        assert_eq!(server.lock().unwrap().enrollments, 2);
+
+        let mut client = RemoteClient::new(RemoteConfig { enroll_secret: "wrong".to_string(), ..config }).unwrap();
+        client.reset_node_key().unwrap();
+        assert!(client.config().unwrap_err().to_string().contains("enrollment refused"));
+        let _ = fs::remove_file(&node_key_path);
+    }
+}
@@ -370,6 +370,22 @@ pub struct SystemInfoData {
     pub cpu_brand: String,
     pub cpu_logical_cores: u32,
     pub physical_memory: i64,
+    pub uuid: String,
+    pub cpu_physical_cores: u32,
+    pub cpu_sockets: u32,
+    pub cpu_microcode: String,
+    pub cpu_flags: String,
+    pub hardware_vendor: String,
+    pub hardware_model: String,
+    pub hardware_version: String,
+    pub hardware_serial: String,
+    pub board_vendor: String,
+    pub board_model: String,
+    pub board_version: String,
+    pub board_serial: String,
+    pub bios_vendor: String,
+    pub bios_version: String,
+    pub bios_date: String,
 }}
 
 #[cfg(feature = "system_info")]
@@ -380,6 +396,8 @@ pub trait SystemInfoDataIface {
     fn meminfo(&self) -> Option<String>;
     fn cpuinfo(&self) -> Option<String>;
     fn cpu_count(&self) -> u32;
+    /// Attribute of `/sys/class/dmi/id`, such as `sys_vendor` or `board_serial`.
+    fn dmi_attribute(&self, name: &str) -> Option<String>;
 }
 
 #[cfg(feature = "system_info")]
@@ -389,6 +407,22 @@ impl SystemInfoData {
     const CPU_BRAND_ID: u64 = 0x00000002;
     const CPU_LOGICAL_CORES_ID: u64 = 0x00000004;
     const PHYSICAL_MEMORY_ID: u64 = 0x00000008;
+    const UUID_ID: u64 = 0x00000010;
+    const CPU_PHYSICAL_CORES_ID: u64 = 0x00000020;
+    const CPU_SOCKETS_ID: u64 = 0x00000040;
+    const CPU_MICROCODE_ID: u64 = 0x00000080;
+    const CPU_FLAGS_ID: u64 = 0x00000100;
+    const HARDWARE_VENDOR_ID: u64 = 0x00000200;
+    const HARDWARE_MODEL_ID: u64 = 0x00000400;
+    const HARDWARE_VERSION_ID: u64 = 0x00000800;
+    const HARDWARE_SERIAL_ID: u64 = 0x00001000;
+    const BOARD_VENDOR_ID: u64 = 0x00002000;
+    const BOARD_MODEL_ID: u64 = 0x00004000;
+    const BOARD_VERSION_ID: u64 = 0x00008000;
+    const BOARD_SERIAL_ID: u64 = 0x00010000;
+    const BIOS_VENDOR_ID: u64 = 0x00020000;
+    const BIOS_VERSION_ID: u64 = 0x00040000;
+    const BIOS_DATE_ID: u64 = 0x00080000;
 }
 
 #[cfg(feature = "system_info")]
@@ -397,7 +431,24 @@ impl Table for SystemInfoData {
         "computer_name",
         "cpu_brand",
         "cpu_logical_cores",
-        "physical_memory"];
+        "physical_memory",
+        "uuid",
+        "cpu_physical_cores",
+        "cpu_sockets",
+        "cpu_microcode",
+        "cpu_flags",
+        "hardware_vendor",
+        "hardware_model",
+        "hardware_version",
+        "hardware_serial",
+        "board_vendor",
+        "board_model",
+        "board_version",
+        "board_serial",
+        "bios_vendor",
+        "bios_version",
+        "bios_date",
+    ];
 
     fn get_by_name(&self, _name: &str) -> Value {
         match _name {
@@ -405,6 +456,22 @@ impl Table for SystemInfoData {
             "cpu_brand" => Value::from(self.cpu_brand.to_owned()),
             "cpu_logical_cores" => Value::from(self.cpu_logical_cores),
             "physical_memory" => Value::from(self.physical_memory),
+            "uuid" => Value::from(self.uuid.to_owned()),
+            "cpu_physical_cores" => Value::from(self.cpu_physical_cores),
+            "cpu_sockets" => Value::from(self.cpu_sockets),
+            "cpu_microcode" => Value::from(self.cpu_microcode.to_owned()),
+            "cpu_flags" => Value::from(self.cpu_flags.to_owned()),
+            "hardware_vendor" => Value::from(self.hardware_vendor.to_owned()),
+            "hardware_model" => Value::from(self.hardware_model.to_owned()),
+            "hardware_version" => Value::from(self.hardware_version.to_owned()),
+            "hardware_serial" => Value::from(self.hardware_serial.to_owned()),
+            "board_vendor" => Value::from(self.board_vendor.to_owned()),
+            "board_model" => Value::from(self.board_model.to_owned()),
+            "board_version" => Value::from(self.board_version.to_owned()),
+            "board_serial" => Value::from(self.board_serial.to_owned()),
+            "bios_vendor" => Value::from(self.bios_vendor.to_owned()),
+            "bios_version" => Value::from(self.bios_version.to_owned()),
+            "bios_date" => Value::from(self.bios_date.to_owned()),
             _ => Value::from("".to_owned())
         }
     }
@@ -415,6 +482,22 @@ impl Table for SystemInfoData {
             Self::CPU_BRAND_ID => Value::from(self.cpu_brand.to_owned()),
             Self::CPU_LOGICAL_CORES_ID => Value::from(self.cpu_logical_cores),
             Self::PHYSICAL_MEMORY_ID => Value::from(self.physical_memory),
+            Self::UUID_ID => Value::from(self.uuid.to_owned()),
+            Self::CPU_PHYSICAL_CORES_ID => Value::from(self.cpu_physical_cores),
+            Self::CPU_SOCKETS_ID => Value::from(self.cpu_sockets),
+            Self::CPU_MICROCODE_ID => Value::from(self.cpu_microcode.to_owned()),
+            Self::CPU_FLAGS_ID => Value::from(self.cpu_flags.to_owned()),
+            Self::HARDWARE_VENDOR_ID => Value::from(self.hardware_vendor.to_owned()),
+            Self::HARDWARE_MODEL_ID => Value::from(self.hardware_model.to_owned()),
+            Self::HARDWARE_VERSION_ID => Value::from(self.hardware_version.to_owned()),
+            Self::HARDWARE_SERIAL_ID => Value::from(self.hardware_serial.to_owned()),
+            Self::BOARD_VENDOR_ID => Value::from(self.board_vendor.to_owned()),
+            Self::BOARD_MODEL_ID => Value::from(self.board_model.to_owned()),
+            Self::BOARD_VERSION_ID => Value::from(self.board_version.to_owned()),
+            Self::BOARD_SERIAL_ID => Value::from(self.board_serial.to_owned()),
+            Self::BIOS_VENDOR_ID => Value::from(self.bios_vendor.to_owned()),
+            Self::BIOS_VERSION_ID => Value::from(self.bios_version.to_owned()),
+            Self::BIOS_DATE_ID => Value::from(self.bios_date.to_owned()),
             _ => Value::from("".to_owned())
         }
     }
@@ -425,6 +508,22 @@ impl Table for SystemInfoData {
             "cpu_brand" => Self::CPU_BRAND_ID,
             "cpu_logical_cores" => Self::CPU_LOGICAL_CORES_ID,
             "physical_memory" => Self::PHYSICAL_MEMORY_ID,
+            "uuid" => Self::UUID_ID,
+            "cpu_physical_cores" => Self::CPU_PHYSICAL_CORES_ID,
+            "cpu_sockets" => Self::CPU_SOCKETS_ID,
+            "cpu_microcode" => Self::CPU_MICROCODE_ID,
+            "cpu_flags" => Self::CPU_FLAGS_ID,
+            "hardware_vendor" => Self::HARDWARE_VENDOR_ID,
+            "hardware_model" => Self::HARDWARE_MODEL_ID,
+            "hardware_version" => Self::HARDWARE_VERSION_ID,
+            "hardware_serial" => Self::HARDWARE_SERIAL_ID,
+            "board_vendor" => Self::BOARD_VENDOR_ID,
+            "board_model" => Self::BOARD_MODEL_ID,
+            "board_version" => Self::BOARD_VERSION_ID,
+            "board_serial" => Self::BOARD_SERIAL_ID,
+            "bios_vendor" => Self::BIOS_VENDOR_ID,
+            "bios_version" => Self::BIOS_VERSION_ID,
+            "bios_date" => Self::BIOS_DATE_ID,
             _ => 0
         }
     }
@@ -603,13 +702,21 @@ pub struct OsVersion {
     pub version: String,
     pub major: u32,
     pub minor: u32,
+    pub patch: u32,
+    pub build: String,
+    pub id: String,
+    pub id_like: String,
+    pub codename: String,
+    pub kernel_release: String,
 }}
 
 #[cfg(feature = "os_version")]
 pub trait OsVersionIface {
     fn get_os_info(&self) -> Option<String>;
-    fn os_release(&self) -> Option<String>;
+    /// Content of a release file such as `/etc/os-release`.
+    fn release_file(&self, path: &str) -> Option<String>;
     fn os_platform(&self) -> Option<String>;
+    fn kernel_release(&self) -> Option<String>;
 }
 
 #[cfg(feature = "os_version")]
@@ -620,6 +727,12 @@ impl OsVersion {
     const VERSION_ID: u64 = 0x00000004;
     const MAJOR_ID: u64 = 0x00000008;
     const MINOR_ID: u64 = 0x00000010;
+    const PATCH_ID: u64 = 0x00000020;
+    const BUILD_ID: u64 = 0x00000040;
+    const ID_ID: u64 = 0x00000080;
+    const ID_LIKE_ID: u64 = 0x00000100;
+    const CODENAME_ID: u64 = 0x00000200;
+    const KERNEL_RELEASE_ID: u64 = 0x00000400;
 }
 
 #[cfg(feature = "os_version")]
@@ -629,7 +742,14 @@ impl Table for OsVersion {
         "platform",
         "version",
         "major",
-        "minor"];
+        "minor",
+        "patch",
+        "build",
+        "id",
+        "id_like",
+        "codename",
+        "kernel_release",
+    ];
 
     fn get_by_name(&self, _name: &str) -> Value {
         match _name {
@@ -638,6 +758,12 @@ impl Table for OsVersion {
             "version" => Value::from(self.version.to_owned()),
             "major" => Value::from(self.major),
             "minor" => Value::from(self.minor),
+            "patch" => Value::from(self.patch),
+            "build" => Value::from(self.build.to_owned()),
+            "id" => Value::from(self.id.to_owned()),
+            "id_like" => Value::from(self.id_like.to_owned()),
+            "codename" => Value::from(self.codename.to_owned()),
+            "kernel_release" => Value::from(self.kernel_release.to_owned()),
             _ => Value::from("".to_owned())
         }
     }
@@ -649,6 +775,12 @@ impl Table for OsVersion {
             Self::VERSION_ID => Value::from(self.version.to_owned()),
             Self::MAJOR_ID => Value::from(self.major),
             Self::MINOR_ID => Value::from(self.minor),
+            Self::PATCH_ID => Value::from(self.patch),
+            Self::BUILD_ID => Value::from(self.build.to_owned()),
+            Self::ID_ID => Value::from(self.id.to_owned()),
+            Self::ID_LIKE_ID => Value::from(self.id_like.to_owned()),
+            Self::CODENAME_ID => Value::from(self.codename.to_owned()),
+            Self::KERNEL_RELEASE_ID => Value::from(self.kernel_release.to_owned()),
             _ => Value::from("".to_owned())
         }
     }
@@ -660,6 +792,12 @@ impl Table for OsVersion {
             "version" => Self::VERSION_ID,
             "major" => Self::MAJOR_ID,
             "minor" => Self::MINOR_ID,
+            "patch" => Self::PATCH_ID,
+            "build" => Self::BUILD_ID,
+            "id" => Self::ID_ID,
+            "id_like" => Self::ID_LIKE_ID,
+            "codename" => Self::CODENAME_ID,
+            "kernel_release" => Self::KERNEL_RELEASE_ID,
             _ => 0
         }
     }
@@ -683,6 +821,15 @@ pub struct LogicalDrive {
 #[cfg(feature = "logical_drives")]
 pub trait LogicalDriveIface {
     fn get_wmi_drives_info(&self) -> Option<String>;
+    fn mounts(&self) -> Option<String>;
+    /// Name under `/sys/class/block` of a mounted device, following symlinks such as `/dev/mapper/*`.
+    fn block_device(&self, device: &str) -> Option<String>;
+    /// Attribute of a block device, read from its parent disk for a partition.
+    fn block_attribute(&self, name: &str, attribute: &str) -> Option<String>;
+    /// Filesystem UUIDs of `/dev/disk/by-uuid`, by block device name.
+    fn filesystem_uuids(&self) -> Vec<(String, String)>;
+    /// Size, available space and maximum file name length of a mounted filesystem.
+    fn filesystem_stats(&self, mount_point: &str) -> Option<(i64, i64, i64)>;
 }
 
 #[cfg(feature = "logical_drives")]
@@ -4007,6 +4154,393 @@ impl Table for LaunchdOverridesRow {
     }
 }
 
+#[cfg(feature = "file_events")]
+table_properties!{
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct FileEventsRow {
+    pub target_path: String,
+    pub category: String,
+    pub action: String,
+    pub transaction_id: i64,
+    pub time: i64,
+    pub eid: i64,
+}}
+
+#[cfg(feature = "file_events")]
+impl FileEventsRow {
+    const TARGET_PATH_ID: u64 = 0x00000001;
+    const CATEGORY_ID: u64 = 0x00000002;
+    const ACTION_ID: u64 = 0x00000004;
+    const TRANSACTION_ID_ID: u64 = 0x00000008;
+    const TIME_ID: u64 = 0x00000010;
+    const EID_ID: u64 = 0x00000020;
+}
+
+#[cfg(feature = "file_events")]
+impl Table for FileEventsRow {
+    const COLUMN_NAMES: &'static [&'static str] = &[
+        "target_path",
+        "category",
+        "action",
+        "transaction_id",
+        "time",
+        "eid",
+    ];
+
+    fn get_by_name(&self, _name: &str) -> Value {
+        match _name {
+            "target_path" => Value::from(self.target_path.to_owned()),
+            "category" => Value::from(self.category.to_owned()),
+            "action" => Value::from(self.action.to_owned()),
+            "transaction_id" => Value::from(self.transaction_id),
+            "time" => Value::from(self.time),
+            "eid" => Value::from(self.eid),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_by_id(&self, _id: u64) -> Value {
+        match _id {
+            Self::TARGET_PATH_ID => Value::from(self.target_path.to_owned()),
+            Self::CATEGORY_ID => Value::from(self.category.to_owned()),
+            Self::ACTION_ID => Value::from(self.action.to_owned()),
+            Self::TRANSACTION_ID_ID => Value::from(self.transaction_id),
+            Self::TIME_ID => Value::from(self.time),
+            Self::EID_ID => Value::from(self.eid),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_id(&self, _name: &str) -> u64 {
+        match _name {
+            "target_path" => Self::TARGET_PATH_ID,
+            "category" => Self::CATEGORY_ID,
+            "action" => Self::ACTION_ID,
+            "transaction_id" => Self::TRANSACTION_ID_ID,
+            "time" => Self::TIME_ID,
+            "eid" => Self::EID_ID,
+            _ => 0
+        }
+    }
+}
+
+table_properties!{
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SiqueryTableStatsRow {
+    pub name: String,
+    pub generations: i64,
+    pub wall_time_ms: f64,
+    pub rows: i64,
+    pub bytes: i64,
+    pub cache_hits: i64,
+    pub timeouts: i64,
+}}
+
+impl SiqueryTableStatsRow {
+    const NAME_ID: u64 = 0x00000001;
+    const GENERATIONS_ID: u64 = 0x00000002;
+    const WALL_TIME_MS_ID: u64 = 0x00000004;
+    const ROWS_ID: u64 = 0x00000008;
+    const BYTES_ID: u64 = 0x00000010;
+    const CACHE_HITS_ID: u64 = 0x00000020;
+    const TIMEOUTS_ID: u64 = 0x00000040;
+}
+
+impl Table for SiqueryTableStatsRow {
+    const COLUMN_NAMES: &'static [&'static str] = &[
+        "name",
+        "generations",
+        "wall_time_ms",
+        "rows",
+        "bytes",
+        "cache_hits",
+        "timeouts",
+    ];
+
+    fn get_by_name(&self, _name: &str) -> Value {
+        match _name {
+            "name" => Value::from(self.name.to_owned()),
+            "generations" => Value::from(self.generations),
+            "wall_time_ms" => Value::from(self.wall_time_ms),
+            "rows" => Value::from(self.rows),
+            "bytes" => Value::from(self.bytes),
+            "cache_hits" => Value::from(self.cache_hits),
+            "timeouts" => Value::from(self.timeouts),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_by_id(&self, _id: u64) -> Value {
+        match _id {
+            Self::NAME_ID => Value::from(self.name.to_owned()),
+            Self::GENERATIONS_ID => Value::from(self.generations),
+            Self::WALL_TIME_MS_ID => Value::from(self.wall_time_ms),
+            Self::ROWS_ID => Value::from(self.rows),
+            Self::BYTES_ID => Value::from(self.bytes),
+            Self::CACHE_HITS_ID => Value::from(self.cache_hits),
+            Self::TIMEOUTS_ID => Value::from(self.timeouts),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_id(&self, _name: &str) -> u64 {
+        match _name {
+            "name" => Self::NAME_ID,
+            "generations" => Self::GENERATIONS_ID,
+            "wall_time_ms" => Self::WALL_TIME_MS_ID,
+            "rows" => Self::ROWS_ID,
+            "bytes" => Self::BYTES_ID,
+            "cache_hits" => Self::CACHE_HITS_ID,
+            "timeouts" => Self::TIMEOUTS_ID,
+            _ => 0
+        }
+    }
+}
+
+table_properties!{
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SiqueryInfoRow {
+    pub version: String,
+    pub revision: String,
+    pub pid: i64,
+    pub start_time: i64,
+    pub sqlite_version: String,
+    pub build_platform: String,
+}}
+
+impl SiqueryInfoRow {
+    const VERSION_ID: u64 = 0x00000001;
+    const REVISION_ID: u64 = 0x00000002;
+    const PID_ID: u64 = 0x00000004;
+    const START_TIME_ID: u64 = 0x00000008;
+    const SQLITE_VERSION_ID: u64 = 0x00000010;
+    const BUILD_PLATFORM_ID: u64 = 0x00000020;
+}
+
+impl Table for SiqueryInfoRow {
+    const COLUMN_NAMES: &'static [&'static str] = &[
+        "version",
+        "revision",
+        "pid",
+        "start_time",
+        "sqlite_version",
+        "build_platform",
+    ];
+
+    fn get_by_name(&self, _name: &str) -> Value {
+        match _name {
+            "version" => Value::from(self.version.to_owned()),
+            "revision" => Value::from(self.revision.to_owned()),
+            "pid" => Value::from(self.pid),
+            "start_time" => Value::from(self.start_time),
+            "sqlite_version" => Value::from(self.sqlite_version.to_owned()),
+            "build_platform" => Value::from(self.build_platform.to_owned()),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_by_id(&self, _id: u64) -> Value {
+        match _id {
+            Self::VERSION_ID => Value::from(self.version.to_owned()),
+            Self::REVISION_ID => Value::from(self.revision.to_owned()),
+            Self::PID_ID => Value::from(self.pid),
+            Self::START_TIME_ID => Value::from(self.start_time),
+            Self::SQLITE_VERSION_ID => Value::from(self.sqlite_version.to_owned()),
+            Self::BUILD_PLATFORM_ID => Value::from(self.build_platform.to_owned()),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_id(&self, _name: &str) -> u64 {
+        match _name {
+            "version" => Self::VERSION_ID,
+            "revision" => Self::REVISION_ID,
+            "pid" => Self::PID_ID,
+            "start_time" => Self::START_TIME_ID,
+            "sqlite_version" => Self::SQLITE_VERSION_ID,
+            "build_platform" => Self::BUILD_PLATFORM_ID,
+            _ => 0
+        }
+    }
+}
+
+table_properties!{
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SiqueryTablesRow {
+    pub name: String,
+    pub platforms: String,
+    pub column_count: i64,
+    pub cache_ttl: i64,
+}}
+
+impl SiqueryTablesRow {
+    const NAME_ID: u64 = 0x00000001;
+    const PLATFORMS_ID: u64 = 0x00000002;
+    const COLUMN_COUNT_ID: u64 = 0x00000004;
+    const CACHE_TTL_ID: u64 = 0x00000008;
+}
+
+impl Table for SiqueryTablesRow {
+    const COLUMN_NAMES: &'static [&'static str] = &[
+        "name",
+        "platforms",
+        "column_count",
+        "cache_ttl",
+    ];
+
+    fn get_by_name(&self, _name: &str) -> Value {
+        match _name {
+            "name" => Value::from(self.name.to_owned()),
+            "platforms" => Value::from(self.platforms.to_owned()),
+            "column_count" => Value::from(self.column_count),
+            "cache_ttl" => Value::from(self.cache_ttl),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_by_id(&self, _id: u64) -> Value {
+        match _id {
+            Self::NAME_ID => Value::from(self.name.to_owned()),
+            Self::PLATFORMS_ID => Value::from(self.platforms.to_owned()),
+            Self::COLUMN_COUNT_ID => Value::from(self.column_count),
+            Self::CACHE_TTL_ID => Value::from(self.cache_ttl),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_id(&self, _name: &str) -> u64 {
+        match _name {
+            "name" => Self::NAME_ID,
+            "platforms" => Self::PLATFORMS_ID,
+            "column_count" => Self::COLUMN_COUNT_ID,
+            "cache_ttl" => Self::CACHE_TTL_ID,
+            _ => 0
+        }
+    }
+}
+
+table_properties!{
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct SiqueryFlagsRow {
+    pub name: String,
+    pub value: String,
+    pub default_value: String,
+    pub description: String,
+}}
+
+impl SiqueryFlagsRow {
+    const NAME_ID: u64 = 0x00000001;
+    const VALUE_ID: u64 = 0x00000002;
+    const DEFAULT_VALUE_ID: u64 = 0x00000004;
+    const DESCRIPTION_ID: u64 = 0x00000008;
+}
+
+impl Table for SiqueryFlagsRow {
+    const COLUMN_NAMES: &'static [&'static str] = &[
+        "name",
+        "value",
+        "default_value",
+        "description",
+    ];
+
+    fn get_by_name(&self, _name: &str) -> Value {
+        match _name {
+            "name" => Value::from(self.name.to_owned()),
+            "value" => Value::from(self.value.to_owned()),
+            "default_value" => Value::from(self.default_value.to_owned()),
+            "description" => Value::from(self.description.to_owned()),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_by_id(&self, _id: u64) -> Value {
+        match _id {
+            Self::NAME_ID => Value::from(self.name.to_owned()),
+            Self::VALUE_ID => Value::from(self.value.to_owned()),
+            Self::DEFAULT_VALUE_ID => Value::from(self.default_value.to_owned()),
+            Self::DESCRIPTION_ID => Value::from(self.description.to_owned()),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_id(&self, _name: &str) -> u64 {
+        match _name {
+            "name" => Self::NAME_ID,
+            "value" => Self::VALUE_ID,
+            "default_value" => Self::DEFAULT_VALUE_ID,
+            "description" => Self::DESCRIPTION_ID,
+            _ => 0
+        }
+    }
+}
+
+table_properties!{
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CarvesRow {
+    pub time: i64,
+    pub carve: String,
+    pub path: String,
+    pub size: i64,
+    pub mtime: i64,
+    pub sha256: String,
+}}
+
+impl CarvesRow {
+    const TIME_ID: u64 = 0x00000001;
+    const CARVE_ID: u64 = 0x00000002;
+    const PATH_ID: u64 = 0x00000004;
+    const SIZE_ID: u64 = 0x00000008;
+    const MTIME_ID: u64 = 0x00000010;
+    const SHA256_ID: u64 = 0x00000020;
+}
+
+impl Table for CarvesRow {
+    const COLUMN_NAMES: &'static [&'static str] = &[
+        "time",
+        "carve",
+        "path",
+        "size",
+        "mtime",
+        "sha256",
+    ];
+
+    fn get_by_name(&self, _name: &str) -> Value {
+        match _name {
+            "time" => Value::from(self.time),
+            "carve" => Value::from(self.carve.to_owned()),
+            "path" => Value::from(self.path.to_owned()),
+            "size" => Value::from(self.size),
+            "mtime" => Value::from(self.mtime),
+            "sha256" => Value::from(self.sha256.to_owned()),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_by_id(&self, _id: u64) -> Value {
+        match _id {
+            Self::TIME_ID => Value::from(self.time),
+            Self::CARVE_ID => Value::from(self.carve.to_owned()),
+            Self::PATH_ID => Value::from(self.path.to_owned()),
+            Self::SIZE_ID => Value::from(self.size),
+            Self::MTIME_ID => Value::from(self.mtime),
+            Self::SHA256_ID => Value::from(self.sha256.to_owned()),
+            _ => Value::from("".to_owned())
+        }
+    }
+
+    fn get_id(&self, _name: &str) -> u64 {
+        match _name {
+            "time" => Self::TIME_ID,
+            "carve" => Self::CARVE_ID,
+            "path" => Self::PATH_ID,
+            "size" => Self::SIZE_ID,
+            "mtime" => Self::MTIME_ID,
+            "sha256" => Self::SHA256_ID,
+            _ => 0
+        }
+    }
+}
+
 pub fn get_table_list() -> Vec<String> {
     vec![
         #[cfg(feature = "etc_hosts")]
@@ -4093,6 +4627,14 @@ pub fn get_table_list() -> Vec<String> {
             "launchd".to_string(),
         #[cfg(feature = "launchd_overrides")]
             "launchd_overrides".to_string(),
+        #[cfg(feature = "file_events")]
+            "file_events".to_string(),
+        #[cfg(feature = "carve")]
+            "carves".to_string(),
+        "siquery_table_stats".to_string(),
+        "siquery_info".to_string(),
+        "siquery_tables".to_string(),
+        "siquery_flags".to_string(),
         #[cfg(test)]
             "Dummy".to_string(),
     ]
@@ -1,3 +1,9 @@
+use std::cell::Cell;
+use std::io::Read;
+use std::process::{Command, Output, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
 /// Remove trailing '\n' at the end of a string.
 pub fn trim_string(s: &mut String) {
     while s.ends_with('\n') || s.ends_with('\r') || s.ends_with(',') {
@@ -6,4 +12,73 @@ pub fn trim_string(s: &mut String) {
     }
 }
 
+/// Quotes an SQL identifier such as a table or column name.
+pub fn quote_identifier(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
 
+thread_local! {
+    /// Deadline of the table generation running on this thread.
+    static DEADLINE: Cell<Option<Instant>> = Cell::new(None);
+}
+
+pub(crate) fn set_deadline(deadline: Option<Instant>) {
+    DEADLINE.with(|d| d.set(deadline));
+}
+
+pub(crate) fn deadline() -> Option<Instant> {
+    DEADLINE.with(|d| d.get())
+}
+
+fn read_pipe<R: Read + Send + 'static>(pipe: Option<R>) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut buffer = Vec::new();
+        if let Some(mut pipe) = pipe {
+            let _ = pipe.read_to_end(&mut buffer);
+        }
+        buffer
+    })
+}
+
+/// Runs `command` like `Command::output`, killing the child when the generation deadline passes.
+pub(crate) fn command_output(command: &mut Command) -> Option<Output> {
+    let deadline = match deadline() {
+        Some(deadline) => deadline,
+        None => return command.output().ok(),
+    };
+    let mut child = command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped()).spawn().ok()?;
+    let stdout = read_pipe(child.stdout.take());
+    let stderr = read_pipe(child.stderr.take());
+    loop {
+        if let Some(status) = child.try_wait().ok()? {
+            return Some(Output {
+                status,
+                stdout: stdout.join().unwrap_or_default(),
+                stderr: stderr.join().unwrap_or_default(),
+            });
+        }
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            return None;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_output_deadline() {
+        let output = command_output(Command::new("echo").arg("hello")).unwrap();
+        assert_eq!(output.stdout, b"hello\n");
+
+        set_deadline(Some(Instant::now() + Duration::from_millis(100)));
+        let start = Instant::now();
+        assert!(command_output(Command::new("sleep").arg("10")).is_none());
+        assert!(start.elapsed() < Duration::from_secs(5));
+        set_deadline(None);
+    }
+}
@@ -1,5 +1,5 @@
 use rusqlite::vtab::{
-    sqlite3_vtab, sqlite3_vtab_cursor, Context, IndexInfo,
+    sqlite3_vtab, sqlite3_vtab_cursor, Context, IndexInfo, IndexConstraintOp,
     VTab, VTabConnection, VTabCursor, Values, read_only_module,
     dequote, Module, CreateVTab};
 
@@ -8,22 +8,51 @@ use rusqlite::{Connection, Result, Error};
 use std::os::raw::c_int;
 use std::str;
 
-use crate::query::{query_table, get_schema};
+use crate::query::{
+    query_table_constrained, query_registered_tables, get_schema, schema_column_names, table_constraints,
+    Constraint, ConstraintOp};
+use crate::options::table_allowed;
+use crate::profile::record_cache_hit;
 
 pub fn load_module(conn: &Connection) -> Result<()> {
-    let aux: Option<()> = None;
-    conn.create_module("siquery", &SIQUERY_MODULE, aux)
+    conn.create_module("siquery", &SIQUERY_MODULE, None)
+}
+
+/// Loads the module along with the tables registered on `conn`, listed by its `siquery_tables`.
+pub(crate) fn load_module_with_tables(conn: &Connection, tables: Vec<String>) -> Result<()> {
+    conn.create_module("siquery", &SIQUERY_MODULE, Some(tables))
 }
 
 lazy_static! {
     static ref SIQUERY_MODULE: &'static Module<SiqueryTab> = read_only_module::<SiqueryTab>();
 }
 
+/// Bits of `idx_num` per constraint handed to the generator: the position of its
+/// `ConstraintSpec` in `table_constraints`, plus one, in the order of the filter arguments.
+const CONSTRAINT_BITS: usize = 4;
+const MAX_CONSTRAINTS: usize = 7;
+
+fn constraint_op(op: IndexConstraintOp) -> Option<ConstraintOp> {
+    match op {
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_EQ => Some(ConstraintOp::Eq),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_GT => Some(ConstraintOp::Gt),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_GE => Some(ConstraintOp::Ge),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_LT => Some(ConstraintOp::Lt),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_LE => Some(ConstraintOp::Le),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_LIKE => Some(ConstraintOp::Like),
+        IndexConstraintOp::SQLITE_INDEX_CONSTRAINT_GLOB => Some(ConstraintOp::Glob),
+        _ => None,
+    }
+}
+
 #[repr(C)]
 struct SiqueryTab {
     /// Base class. Must be first
     base: sqlite3_vtab,
     table_name: String,
+    columns: Vec<String>,
+    /// Tables of the connection, kept by `siquery_tables` only.
+    connection_tables: Option<Vec<String>>,
 }
 
 impl SiqueryTab {
@@ -42,12 +71,12 @@ impl SiqueryTab {
 }
 
 unsafe impl VTab for SiqueryTab {
-    type Aux = ();
+    type Aux = Vec<String>;
     type Cursor = SiqueryTabCursor;
 
     fn connect(
         _: &mut VTabConnection,
-        _aux: Option<&()>,
+        _aux: Option<&Vec<String>>,
         _args: &[&[u8]],
     ) -> Result<(String, SiqueryTab)> {
         if _args.len() < 4 {
@@ -57,6 +86,8 @@ unsafe impl VTab for SiqueryTab {
         let mut vtab = SiqueryTab {
             base: sqlite3_vtab::default(),
             table_name: String::new(),
+            columns: Vec::new(),
+            connection_tables: None,
         };
         let schema;
         let args= &_args[3..];
@@ -76,12 +107,41 @@ unsafe impl VTab for SiqueryTab {
             }
         }
 
+        if vtab.table_name == "siquery_tables" {
+            vtab.connection_tables = _aux.cloned();
+        }
         schema = get_schema(vtab.table_name.as_str());
+        vtab.columns = schema.as_ref().map(|schema| schema_column_names(schema)).unwrap_or_default();
         Ok((schema.unwrap().to_owned(), vtab))
     }
 
     fn best_index(&self, info: &mut IndexInfo) -> Result<()> {
-        info.set_estimated_cost(1_000_000.);
+        // Failing while the statement is prepared reports the error before any row is printed.
+        if !table_allowed(self.table_name.as_str()) {
+            return Err(Error::ModuleError("table disabled by policy".to_owned()));
+        }
+        // Hands the usable constraints the generator handles to `filter`, see `table_constraints`.
+        let specs = table_constraints(self.table_name.as_str());
+        let mut pushed = Vec::new();
+        for (i, constraint) in info.constraints().enumerate() {
+            if !constraint.is_usable() || pushed.len() == MAX_CONSTRAINTS {
+                continue;
+            }
+            let column = self.columns.get(constraint.column() as usize);
+            let op = constraint_op(constraint.operator());
+            if let Some(position) = specs.iter().position(|spec| Some(spec.op) == op && Some(spec.column) == column.map(|c| c.as_str())) {
+                pushed.push((i, position));
+            }
+        }
+        let mut idx_num = 0;
+        for (argv, (i, position)) in pushed.iter().enumerate() {
+            let mut usage = info.constraint_usage(*i);
+            usage.set_argv_index(argv as c_int + 1);
+            usage.set_omit(specs[*position].omit);
+            idx_num |= (*position as c_int + 1) << (argv * CONSTRAINT_BITS);
+        }
+        info.set_idx_num(idx_num);
+        info.set_estimated_cost(1_000_000. / (1 + pushed.len()) as f64);
         Ok(())
     }
 
@@ -97,6 +157,8 @@ struct SiqueryTabCursor {
     base: sqlite3_vtab_cursor,
     /// table is in memory
     table_in_memory: bool,
+    /// constraints the rows in memory were generated for
+    constraints: Vec<Constraint>,
     /// The rowid
     row_id: i64,
     /// columns name
@@ -116,10 +178,27 @@ unsafe impl VTabCursor for SiqueryTabCursor {
         _args: &Values,
     ) -> Result<()> {
         let siquery_table = unsafe {&*(self.base.pVtab as * const SiqueryTab)};
-        // register table in memory
-        if !self.table_in_memory {
-            self.rows = query_table(siquery_table.table_name.as_str(), vec![]);
+        if !table_allowed(siquery_table.table_name.as_str()) {
+            return Err(Error::ModuleError("table disabled by policy".to_owned()));
+        }
+        let specs = table_constraints(siquery_table.table_name.as_str());
+        let mut constraints = Vec::new();
+        for i in 0.._args.len() {
+            let position = (_idx_num >> (i * CONSTRAINT_BITS)) as usize & ((1 << CONSTRAINT_BITS) - 1);
+            if let Some(spec) = position.checked_sub(1).and_then(|position| specs.get(position)) {
+                constraints.push(Constraint { column: spec.column, op: spec.op, value: _args.get(i)? });
+            }
+        }
+        // register table in memory, again when a join gives other constraint values
+        if !self.table_in_memory || constraints != self.constraints {
+            self.rows = match siquery_table.connection_tables {
+                Some(ref tables) => query_registered_tables(tables),
+                None => query_table_constrained(siquery_table.table_name.as_str(), &constraints),
+            };
+            self.constraints = constraints;
             self.table_in_memory = true;
+        } else {
+            record_cache_hit(siquery_table.table_name.as_str());
         }
         self.row_id = 0;
         self.next()
@@ -0,0 +1,237 @@
+//! Watchdog keeping the scheduled queries of long-running modes from overloading the host.
+//!
+//! Each run of a scheduled query is measured: how much the resident size of siquery grew while
+//! it ran, its rows still being in memory, and the CPU time the run took. Memory already held
+//! before the run, which the allocator keeps once a previous query freed it, is not blamed on
+//! the query. CPU time is that of the whole process, as tables may be generated on worker
+//! threads, so other threads busy during the run, such as event watchers, count against it.
+//! A query going over a limit is skipped on its next turn, and deny-listed once it has done so
+//! `max_offences` times. Every offence is recorded as a warning of the query.
+
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::profile::record_warning;
+
+/// Limits of the watchdog. `None` leaves a resource unwatched.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WatchdogLimits {
+    /// Growth of the resident size of siquery during one run of a query, in bytes.
+    pub memory: Option<u64>,
+    /// CPU time one run of a query may take.
+    pub cpu_time: Option<Duration>,
+    /// Offences after which a query is not run anymore.
+    pub max_offences: u32,
+}
+
+impl Default for WatchdogLimits {
+    fn default() -> WatchdogLimits {
+        WatchdogLimits {
+            memory: None,
+            cpu_time: None,
+            max_offences: 3,
+        }
+    }
+}
+
+/// Resources used by siquery so far.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ResourceUsage {
+    /// Resident size in bytes.
+    pub resident_size: u64,
+    /// User and system CPU time.
+    pub cpu_time: Duration,
+}
+
+/// Usage of siquery itself, read from `/proc/self` the way the processes table reads it.
+#[cfg(all(target_os = "linux", feature = "processes"))]
+pub fn self_usage() -> Option<ResourceUsage> {
+    use crate::linux::processes::SimpleProcStat;
+
+    let (resident_size, ticks) = SimpleProcStat::get_proc_stat("self").usage();
+    let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    if resident_size == 0 || ticks_per_second <= 0 {
+        return None;
+    }
+    Some(ResourceUsage {
+        // The processes table reports VmRSS kilobytes with three zeros appended.
+        resident_size: resident_size / 1000 * 1024,
+        cpu_time: Duration::from_millis(ticks * 1000 / ticks_per_second as u64),
+    })
+}
+
+#[cfg(not(all(target_os = "linux", feature = "processes")))]
+pub fn self_usage() -> Option<ResourceUsage> {
+    None
+}
+
+/// Whether a scheduled query may run.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Verdict {
+    Run,
+    /// Backing off for this turn after an offence.
+    Skip,
+    Denied,
+}
+
+pub struct Watchdog {
+    limits: WatchdogLimits,
+    offences: HashMap<String, u32>,
+    backing_off: HashSet<String>,
+    usage: fn() -> Option<ResourceUsage>,
+}
+
+impl Watchdog {
+    pub fn new(limits: WatchdogLimits) -> Watchdog {
+        Watchdog {
+            limits,
+            offences: HashMap::new(),
+            backing_off: HashSet::new(),
+            usage: self_usage,
+        }
+    }
+
+    pub fn limits(&self) -> WatchdogLimits {
+        self.limits
+    }
+
+    fn enabled(&self) -> bool {
+        self.limits.memory.is_some() || self.limits.cpu_time.is_some()
+    }
+
+    pub fn offences(&self, name: &str) -> u32 {
+        self.offences.get(name).cloned().unwrap_or(0)
+    }
+
+    /// Deny-listed queries.
+    pub fn denied(&self) -> Vec<String> {
+        let mut denied: Vec<String> = self.offences.iter()
+            .filter(|(_, offences)| **offences >= self.limits.max_offences)
+            .map(|(name, _)| name.clone())
+            .collect();
+        denied.sort();
+        denied
+    }
+
+    /// Tells whether `name` may run this turn. A skipped turn ends the back-off.
+    pub fn check(&mut self, name: &str) -> Verdict {
+        if self.enabled() && self.offences(name) >= self.limits.max_offences {
+            Verdict::Denied
+        } else if self.backing_off.remove(name) {
+            Verdict::Skip
+        } else {
+            Verdict::Run
+        }
+    }
+
+    /// Runs `query` if the watchdog allows it, and records an offence if it goes over a limit.
+    pub fn run<T, F: FnOnce() -> T>(&mut self, name: &str, query: F) -> Option<T> {
+        if self.check(name) != Verdict::Run {
+            return None;
+        }
+        if !self.enabled() {
+            return Some(query());
+        }
+        let before = (self.usage)();
+        let result = query();
+        if let (Some(before), Some(after)) = (before, (self.usage)()) {
+            self.measure(name, before, after);
+        }
+        Some(result)
+    }
+
+    fn measure(&mut self, name: &str, before: ResourceUsage, after: ResourceUsage) {
+        let mut excess = Vec::new();
+        if let Some(memory) = self.limits.memory {
+            let grown = after.resident_size.saturating_sub(before.resident_size);
+            if grown > memory {
+                excess.push(format!("resident size grew by {} bytes, over the limit of {}", grown, memory));
+            }
+        }
+        if let Some(cpu_time) = self.limits.cpu_time {
+            let used = after.cpu_time.checked_sub(before.cpu_time).unwrap_or_default();
+            if used > cpu_time {
+                excess.push(format!("cpu time {:.3}s over the limit of {:.3}s",
+                                    used.as_secs_f64(), cpu_time.as_secs_f64()));
+            }
+        }
+        if excess.is_empty() {
+            return;
+        }
+        let offences = self.offences.entry(name.to_string()).or_insert(0);
+        *offences += 1;
+        let action = if *offences >= self.limits.max_offences {
+            format!("deny-listed after {} offences", offences)
+        } else {
+            self.backing_off.insert(name.to_string());
+            "skipping its next run".to_string()
+        };
+        record_warning(name, &format!("watchdog: {}, {}", excess.join(", "), action));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::profile::take_table_warnings;
+    use std::cell::Cell;
+
+    thread_local! {
+        static USAGE: Cell<ResourceUsage> = Cell::new(ResourceUsage::default());
+    }
+
+    fn fake_usage() -> Option<ResourceUsage> {
+        Some(USAGE.with(|usage| usage.get()))
+    }
+
+    fn set_usage(resident_size: u64, cpu_millis: u64) {
+        USAGE.with(|usage| usage.set(ResourceUsage { resident_size, cpu_time: Duration::from_millis(cpu_millis) }));
+    }
+
+    #[test]
+    fn test_watchdog() {
+        let mut watchdog = Watchdog::new(WatchdogLimits {
+            memory: Some(1000),
+            cpu_time: Some(Duration::from_millis(500)),
+            max_offences: 2,
+        });
+        watchdog.usage = fake_usage;
+
+        set_usage(100, 0);
+        assert_eq!(watchdog.run("light", || 1), Some(1));
+        assert_eq!(watchdog.run("heavy", || set_usage(2100, 0)), Some(()));
+        // Memory held from before is not blamed on the next query.
+        assert_eq!(watchdog.run("light", || set_usage(2500, 0)), Some(()));
+        assert_eq!(watchdog.offences("light"), 0);
+        assert_eq!(watchdog.offences("heavy"), 1);
+        assert_eq!(watchdog.check("heavy"), Verdict::Skip);
+        assert_eq!(watchdog.check("heavy"), Verdict::Run);
+
+        watchdog.run("heavy", || set_usage(2500, 800));
+        assert_eq!(watchdog.run("heavy", || 2), None);
+        assert_eq!(watchdog.check("heavy"), Verdict::Denied);
+        assert_eq!(watchdog.denied(), vec!["heavy".to_string()]);
+        assert_eq!(watchdog.offences("light"), 0);
+
+        let warnings = take_table_warnings("heavy");
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("grew by 2000 bytes") && warnings[0].contains("skipping"));
+        assert!(warnings[1].contains("cpu time 0.800s") && warnings[1].contains("deny-listed"));
+    }
+
+    #[test]
+    fn test_watchdog_disabled() {
+        let mut watchdog = Watchdog::new(WatchdogLimits::default());
+        watchdog.usage = fake_usage;
+        set_usage(u64::MAX, 0);
+        assert_eq!(watchdog.run("any", || 1), Some(1));
+        assert_eq!(watchdog.offences("any"), 0);
+    }
+
+    #[cfg(all(target_os = "linux", feature = "processes"))]
+    #[test]
+    fn test_self_usage() {
+        let usage = self_usage().unwrap();
+        assert!(usage.resident_size > 0);
+    }
+}
@@ -7,10 +7,10 @@ use crate::utils;
 pub struct Reader {}
 impl InterfaceAddressIface for Reader {
     fn get_wmi_nicconfig(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["nicconfig", "get",
                 "IPEnabled,InterfaceIndex,Description,DefaultIPGateway,IPAddress,IPSubnet,DHCPEnabled",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
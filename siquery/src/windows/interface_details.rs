@@ -8,8 +8,8 @@ use crate::utils;
 pub(crate) struct Reader {}
 impl InterfaceDetailsIface for Reader {
     fn get_wmi_nicconfig_details(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["nicconfig", "get", "IPEnabled,InterfaceIndex,MACAddress,MTU", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["nicconfig", "get", "IPEnabled,InterfaceIndex,MACAddress,MTU", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,13 +7,19 @@ use crate::utils;
 pub struct Reader {}
 impl LogicalDriveIface for Reader {
     fn get_wmi_drives_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["logicaldisk", "get",
                 "Description,DriveType,FileSystem,FreeSpace,MaximumComponentLength,Name\
                 ,Size,DriveType,SupportsFileBasedCompression,VolumeSerialNumber",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
+    // NA for windows
+    fn mounts(&self) -> Option<String> {None}
+    fn block_device(&self, _device: &str) -> Option<String> {None}
+    fn block_attribute(&self, _name: &str, _attribute: &str) -> Option<String> {None}
+    fn filesystem_uuids(&self) -> Vec<(String, String)> {Vec::new()}
+    fn filesystem_stats(&self, _mount_point: &str) -> Option<(i64, i64, i64)> {None}
 }
 
 impl LogicalDrive {
@@ -106,6 +112,12 @@ mod tests {
         fn get_wmi_drives_info(&self) -> Option<String> {
             Some(String::from(include_str!("../../test_data/wmi-driveinfo.txt")))
         }
+        // NA for windows
+        fn mounts(&self) -> Option<String> {None}
+        fn block_device(&self, _device: &str) -> Option<String> {None}
+        fn block_attribute(&self, _name: &str, _attribute: &str) -> Option<String> {None}
+        fn filesystem_uuids(&self) -> Vec<(String, String)> {Vec::new()}
+        fn filesystem_stats(&self, _mount_point: &str) -> Option<(i64, i64, i64)> {None}
     }
     #[test]
     fn test_logical_drives () {
@@ -7,13 +7,14 @@ use crate::utils;
 pub struct Reader {}
 impl OsVersionIface for Reader {
     fn get_os_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["os", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["os", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
     // NA for windows
-    fn os_release(&self) -> Option<String> {Some(String::new())}
+    fn release_file(&self, _path: &str) -> Option<String> {None}
     fn os_platform(&self) ->Option<String> {Some(String::new())}
+    fn kernel_release(&self) -> Option<String> {None}
 }
 
 impl OsVersion {
@@ -25,6 +26,12 @@ impl OsVersion {
             version: String::new(),
             major: 0,
             minor: 0,
+            patch: 0,
+            build: String::new(),
+            id: String::new(),
+            id_like: String::new(),
+            codename: String::new(),
+            kernel_release: String::new(),
         };
 
         if let Some(os_info) = reader.get_os_info() {
@@ -70,8 +77,9 @@ mod tests {
             Some(String::from(include_str!("../../test_data/wmi-osinfo.txt")))
         }
         // NA for windows
-        fn os_release(&self) -> Option<String> {Some(String::new())}
+        fn release_file(&self, _path: &str) -> Option<String> {None}
         fn os_platform(&self) ->Option<String> {Some(String::new())}
+        fn kernel_release(&self) -> Option<String> {None}
     }
     #[test]
     fn test_os_version () {
@@ -133,8 +133,8 @@ pub fn sid_to_string(sid: PSID) -> Result<String, DWORD> {
 pub struct Reader {}
 impl ProcessesIface for Reader {
     fn get_wmi_process_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["path", "Win32_Process", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["path", "Win32_Process", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,13 +7,13 @@ use crate::tables::{SystemInfoData,SystemInfoDataIface};
 pub struct Reader {}
 impl SystemInfoDataIface for Reader {
     fn get_wmi_cpu_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["cpu", "get", "Name,NumberOfLogicalProcessors", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["cpu", "get", "Name,NumberOfLogicalProcessors", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
     fn get_wmi_system_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["computersystem", "get", "Caption,TotalPhysicalMemory", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["computersystem", "get", "Caption,TotalPhysicalMemory", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
     // NA for windows
@@ -21,6 +21,7 @@ impl SystemInfoDataIface for Reader {
     fn meminfo(&self) -> Option<String> {Some(String::new())}
     fn cpuinfo(&self) -> Option<String> {Some(String::new())}
     fn cpu_count(&self) -> u32 {0}
+    fn dmi_attribute(&self, _name: &str) -> Option<String> {None}
 }
 
 
@@ -31,7 +32,23 @@ impl SystemInfoData {
             computer_name: String::new(),
             cpu_brand: String::new(),
             cpu_logical_cores: 0,
-            physical_memory: 0
+            physical_memory: 0,
+            uuid: String::new(),
+            cpu_physical_cores: 0,
+            cpu_sockets: 0,
+            cpu_microcode: String::new(),
+            cpu_flags: String::new(),
+            hardware_vendor: String::new(),
+            hardware_model: String::new(),
+            hardware_version: String::new(),
+            hardware_serial: String::new(),
+            board_vendor: String::new(),
+            board_model: String::new(),
+            board_version: String::new(),
+            board_serial: String::new(),
+            bios_vendor: String::new(),
+            bios_version: String::new(),
+            bios_date: String::new()
         }
     }
 
@@ -103,6 +120,7 @@ mod tests {
         fn meminfo(&self) -> Option<String> {Some(String::new())}
         fn cpuinfo(&self) -> Option<String> {Some(String::new())}
         fn cpu_count(&self) -> u32 {0}
+        fn dmi_attribute(&self, _name: &str) -> Option<String> {None}
     }
     #[test]
     fn test_system_info () {
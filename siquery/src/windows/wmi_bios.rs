@@ -7,8 +7,8 @@ use crate::utils;
 pub struct Reader {}
 impl WmiBiosIface for Reader {
     fn get_wmi_bios_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["bios", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["bios", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,8 +7,8 @@ use crate::tables::{WmiComputerInfo,WmiComputerInfoIface};
 pub struct Reader {}
 impl WmiComputerInfoIface for Reader {
     fn get_wmi_computer_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["computersystem", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["computersystem", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,11 +7,11 @@ use crate::utils;
 pub struct Reader {}
 impl WmiHotfixesIface for Reader {
     fn get_wmi_hotfixes_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["qfe",
                 "get",
                 "Caption,CSName,Description,HotFixID,InstalledBy,InstalledOn",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
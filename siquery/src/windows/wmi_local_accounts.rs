@@ -7,8 +7,8 @@ use crate::utils;
 pub struct Reader {}
 impl WmiLocalAccountsIface for Reader {
     fn get_wmi_local_accounts_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["useraccount", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["useraccount", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
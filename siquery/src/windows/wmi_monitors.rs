@@ -7,8 +7,8 @@ use crate::utils;
 pub struct Reader {}
 impl WmiMonitorsIface for Reader {
     fn get_wmi_monitor_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["desktopmonitor", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["desktopmonitor", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,8 +7,8 @@ use crate::utils;
 pub struct Reader {}
 impl WmiNetworkAdaptersIface for Reader {
     fn get_wmi_network_adapters_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["nicconfig", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["nicconfig", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,8 +7,8 @@ use crate::utils;
 pub struct Reader {}
 impl WmiMemoryIface for Reader {
     fn get_wmi_physical_memory(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["memorychip", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["memorychip", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
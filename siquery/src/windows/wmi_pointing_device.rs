@@ -7,8 +7,8 @@ use crate::utils;
 pub struct Reader {}
 impl WmiPointingDeviceIface for Reader {
     fn get_wmi_pointing_device(&self) -> Option<String> {
-        let output = Command::new("wmic")
-            .args(&["path", "Win32_PointingDevice", "get", "/format:list"]).output().ok()?;
+        let output = utils::command_output(Command::new("wmic")
+            .args(&["path", "Win32_PointingDevice", "get", "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
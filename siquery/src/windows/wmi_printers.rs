@@ -7,14 +7,14 @@ use crate::utils;
 pub struct Reader {}
 impl WmiPrintersIface for Reader {
     fn get_wmi_printers_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["printer",
                 "get",
                 "Attributes,Caption,CreationClassName,DeviceID,DoCompleteFirst,DriverName,\
                 ExtendedPrinterStatus,HorizontalResolution,Local,Name,PortName,PrinterStatus,\
                 PrintJobDataType,PrintProcessor,Priority,Status,SystemCreationClassName,\
                 SystemName,VerticalResolution",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
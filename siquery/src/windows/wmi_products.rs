@@ -7,11 +7,11 @@ use crate::utils;
 pub struct Reader {}
 impl WmiProductsIface for Reader {
     fn get_wmi_products_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["Product",
                 "get",
                 /*"InstallDate,Name,Vendor,Version",*/
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,13 +7,13 @@ use crate::utils;
 pub struct Reader {}
 impl WmiServicesIface for Reader {
     fn get_wmi_services_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["service",
                 "get",
                 "AcceptPause,AcceptStop,Caption,CreationClassName,Description,DesktopInteract,\
                 DisplayName,ErrorControl,ExitCode,Name,PathName,ServiceType,Started,StartMode,\
                 StartName,State,Status,SystemCreationClassName,SystemName",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,11 +7,11 @@ use crate::utils;
 pub struct Reader {}
 impl WmiSharesIface for Reader {
     fn get_wmi_shares_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["share",
                 "get",
                 "Caption,Description,Name,Path,Status,Type,AllowMaximum",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,11 +7,11 @@ use crate::utils;
 pub struct Reader {}
 impl WmiStartUpIface for Reader {
     fn get_wmi_start_up_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["STARTUP",
                 "get",
                 "Command,Location,Name,User",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -7,11 +7,11 @@ use crate::utils;
 pub struct Reader {}
 impl WmiTimeZoneIface for Reader {
     fn get_wmi_time_zone_info(&self) -> Option<String> {
-        let output = Command::new("wmic")
+        let output = utils::command_output(Command::new("wmic")
             .args(&["TIMEZONE",
                 "get",
                 "Description",
-                "/format:list"]).output().ok()?;
+                "/format:list"]))?;
         String::from_utf8(output.stdout).ok()
     }
 }
@@ -0,0 +1,34 @@
+use clap::ArgMatches;
+use rusqlite::Connection;
+use siquery::carve::{carve, carve_query, default_archive_path, CarveLimits};
+use std::path::PathBuf;
+use std::process;
+
+use crate::config::Config;
+
+/// Runs `siquery carve`: archives the given paths, or the paths a query returns.
+pub fn run_carve(matches: &ArgMatches, db: &Connection, config: &Config) {
+    let defaults = CarveLimits::default();
+    let limits = CarveLimits {
+        max_files: value_t!(matches, "max_files", usize).ok().or(config.carve.max_files).unwrap_or(defaults.max_files),
+        max_bytes: value_t!(matches, "max_size", u64).ok().or(config.carve.max_bytes).unwrap_or(defaults.max_bytes),
+    };
+    let archive = matches.value_of("out").map(PathBuf::from).unwrap_or_else(default_archive_path);
+    let result = match (matches.value_of("siquery"), matches.values_of("paths")) {
+        (Some(query), _) => carve_query(db, query, &archive, limits),
+        (None, Some(paths)) => carve(&paths.map(|path| path.to_string()).collect::<Vec<String>>(), &archive, limits, None),
+        (None, None) => {
+            println!("nothing to carve, give paths or --query");
+            process::exit(1);
+        }
+    };
+    let manifest = result.unwrap_or_else(|e| {
+        println!("{}: {}", archive.display(), e);
+        process::exit(1);
+    });
+    for file in manifest.skipped.iter() {
+        println!("skipped {}: {}", file.path, file.reason);
+    }
+    let size: u64 = manifest.files.iter().map(|file| file.size).sum();
+    println!("carved {} files, {} bytes, into {}", manifest.files.len(), size, archive.display());
+}
@@ -0,0 +1,342 @@
+use prettytable::{Table, row::Row, cell::Cell};
+use rusqlite::Connection;
+use serde_derive::Deserialize;
+use serde_json::{json, Map, Value};
+use siquery::query::execute_query_json;
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+
+/// A policy file: a list of checks run one after the other.
+#[derive(Deserialize, Debug)]
+pub struct Policy {
+    #[serde(default)]
+    pub name: Option<String>,
+    pub checks: Vec<Check>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Check {
+    pub id: String,
+    pub title: String,
+    #[serde(default = "default_severity")]
+    pub severity: String,
+    pub query: String,
+    pub expect: Expect,
+}
+
+fn default_severity() -> String {
+    "medium".to_string()
+}
+
+/// What a check expects from its query.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum Expect {
+    Rows(RowsExpect),
+    /// Every returned row must satisfy `column <op> value`.
+    Column { column: String, op: Op, value: Value },
+}
+
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RowsExpect {
+    Empty,
+    NonEmpty,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "snake_case")]
+pub enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    NotContains,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum Status {
+    Pass,
+    Fail,
+    Error(String),
+}
+
+pub struct CheckResult<'a> {
+    pub check: &'a Check,
+    pub status: Status,
+    /// Rows that made the check fail.
+    pub rows: Vec<Map<String, Value>>,
+    pub time: f64,
+}
+
+pub fn load_policy(path: &Path) -> Result<Policy, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    serde_yaml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => s.trim().parse().ok(),
+        _ => None,
+    }
+}
+
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+/// Compares a row value to the expected one, numerically when both sides are numbers.
+fn compare(actual: &Value, op: Op, expected: &Value) -> bool {
+    match op {
+        Op::Contains => return as_text(actual).contains(&as_text(expected)),
+        Op::NotContains => return !as_text(actual).contains(&as_text(expected)),
+        _ => {}
+    }
+    let ordering = match (as_number(actual), as_number(expected)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b),
+        _ => Some(as_text(actual).cmp(&as_text(expected))),
+    };
+    let ordering = match ordering {
+        Some(ordering) => ordering,
+        None => return false,
+    };
+    match op {
+        Op::Eq => ordering == std::cmp::Ordering::Equal,
+        Op::Ne => ordering != std::cmp::Ordering::Equal,
+        Op::Lt => ordering == std::cmp::Ordering::Less,
+        Op::Le => ordering != std::cmp::Ordering::Greater,
+        Op::Gt => ordering == std::cmp::Ordering::Greater,
+        Op::Ge => ordering != std::cmp::Ordering::Less,
+        Op::Contains | Op::NotContains => unreachable!(),
+    }
+}
+
+/// Applies an expectation to query results and returns the status with the offending rows.
+fn evaluate(expect: &Expect, rows: Vec<Map<String, Value>>) -> (Status, Vec<Map<String, Value>>) {
+    match expect {
+        Expect::Rows(RowsExpect::Empty) => {
+            if rows.is_empty() { (Status::Pass, rows) } else { (Status::Fail, rows) }
+        },
+        Expect::Rows(RowsExpect::NonEmpty) => {
+            if rows.is_empty() { (Status::Fail, rows) } else { (Status::Pass, Vec::new()) }
+        },
+        Expect::Column { column, op, value } => {
+            if let Some(row) = rows.first() {
+                if !row.contains_key(column) {
+                    return (Status::Error(format!("no such column: {}", column)), Vec::new());
+                }
+            }
+            let failed: Vec<Map<String, Value>> = rows.into_iter()
+                .filter(|row| !compare(row.get(column).unwrap_or(&Value::Null), *op, value))
+                .collect();
+            if failed.is_empty() { (Status::Pass, failed) } else { (Status::Fail, failed) }
+        },
+    }
+}
+
+pub fn run_checks<'a>(db: &Connection, policy: &'a Policy) -> Vec<CheckResult<'a>> {
+    policy.checks.iter().map(|check| {
+        let start = Instant::now();
+        let (status, rows) = match execute_query_json(db, &check.query, &[]) {
+            Ok(rows) => evaluate(&check.expect, rows),
+            Err(e) => (Status::Error(e.to_string()), Vec::new()),
+        };
+        let elapsed = start.elapsed();
+        CheckResult {
+            check,
+            status,
+            rows,
+            time: elapsed.as_secs() as f64 + elapsed.subsec_nanos() as f64 / 1_000_000_000.0,
+        }
+    }).collect()
+}
+
+fn status_name(status: &Status) -> &str {
+    match status {
+        Status::Pass => "pass",
+        Status::Fail => "fail",
+        Status::Error(_) => "error",
+    }
+}
+
+pub fn print_pretty(results: &[CheckResult]) {
+    let mut table = Table::new();
+    table.set_titles(Row::new(vec![
+        Cell::new("id"),
+        Cell::new("severity"),
+        Cell::new("status"),
+        Cell::new("title"),
+    ]));
+    for result in results {
+        table.add_row(Row::new(vec![
+            Cell::new(&result.check.id),
+            Cell::new(&result.check.severity),
+            Cell::new(status_name(&result.status)),
+            Cell::new(&result.check.title),
+        ]));
+    }
+    println!("{}", table);
+    for result in results.iter().filter(|result| result.status != Status::Pass) {
+        match result.status {
+            Status::Error(ref e) => println!("{}: {}", result.check.id, e),
+            _ => {
+                println!("{}: {} offending row(s)", result.check.id, result.rows.len());
+                for row in result.rows.iter() {
+                    println!("    {}", Value::Object(row.clone()));
+                }
+            }
+        }
+    }
+    let failed = results.iter().filter(|result| result.status != Status::Pass).count();
+    println!("{} checks, {} passed, {} failed", results.len(), results.len() - failed, failed);
+}
+
+pub fn print_json(results: &[CheckResult]) {
+    let checks: Vec<Value> = results.iter().map(|result| {
+        let mut check = json!({
+            "id": result.check.id,
+            "title": result.check.title,
+            "severity": result.check.severity,
+            "query": result.check.query,
+            "status": status_name(&result.status),
+            "rows": result.rows,
+        });
+        if let Status::Error(ref e) = result.status {
+            check["error"] = Value::String(e.clone());
+        }
+        check
+    }).collect();
+    println!("{}", serde_json::to_string_pretty(&checks).unwrap_or_default());
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn junit_report(name: &str, results: &[CheckResult]) -> String {
+    let failures = results.iter().filter(|result| result.status == Status::Fail).count();
+    let errors = results.iter().filter(|result| match result.status {
+        Status::Error(_) => true,
+        _ => false,
+    }).count();
+    let time: f64 = results.iter().map(|result| result.time).sum();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!("<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" errors=\"{}\" time=\"{:.3}\">\n",
+                          xml_escape(name), results.len(), failures, errors, time));
+    for result in results {
+        xml.push_str(&format!("  <testcase classname=\"{}\" name=\"{}: {}\" time=\"{:.3}\">",
+                              xml_escape(&result.check.severity), xml_escape(&result.check.id),
+                              xml_escape(&result.check.title), result.time));
+        match result.status {
+            Status::Pass => {},
+            Status::Fail => {
+                let rows: Vec<String> = result.rows.iter()
+                    .map(|row| Value::Object(row.clone()).to_string())
+                    .collect();
+                xml.push_str(&format!("\n    <failure message=\"{} offending row(s)\">{}</failure>\n  ",
+                                      result.rows.len(), xml_escape(&rows.join("\n"))));
+            },
+            Status::Error(ref e) => {
+                xml.push_str(&format!("\n    <error message=\"{}\"/>\n  ", xml_escape(e)));
+            },
+        }
+        xml.push_str("</testcase>\n");
+    }
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+name: baseline
+checks:
+  - id: uid0
+    title: No uid 0 users besides root
+    severity: high
+    query: select username from users where uid = 0 and username != 'root'
+    expect: empty
+  - id: mounts
+    title: No world-writable mounts
+    query: SELECT path, flags FROM mounts WHERE device_type = 'tmpfs'
+    expect:
+      column: flags
+      op: not_contains
+      value: mode=777
+"#;
+
+    fn rows(values: &[(&str, Value)]) -> Vec<Map<String, Value>> {
+        values.iter().map(|(k, v)| {
+            let mut row = Map::new();
+            row.insert(k.to_string(), v.clone());
+            row
+        }).collect()
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        let policy: Policy = serde_yaml::from_str(POLICY).unwrap();
+        assert_eq!(policy.checks.len(), 2);
+        assert_eq!(policy.checks[0].expect, Expect::Rows(RowsExpect::Empty));
+        assert_eq!(policy.checks[1].severity, "medium");
+        assert_eq!(policy.checks[1].expect, Expect::Column {
+            column: "flags".to_string(),
+            op: Op::NotContains,
+            value: Value::String("mode=777".to_string()),
+        });
+    }
+
+    #[test]
+    fn test_evaluate() {
+        let (status, failed) = evaluate(&Expect::Rows(RowsExpect::Empty), rows(&[("username", json!("toor"))]));
+        assert_eq!(status, Status::Fail);
+        assert_eq!(failed.len(), 1);
+        let (status, _) = evaluate(&Expect::Rows(RowsExpect::NonEmpty), Vec::new());
+        assert_eq!(status, Status::Fail);
+
+        let expect = Expect::Column { column: "uid".to_string(), op: Op::Ge, value: json!(1000) };
+        let (status, failed) = evaluate(&expect, rows(&[("uid", json!(0)), ("uid", json!("1000")), ("uid", json!(1001))]));
+        assert_eq!(status, Status::Fail);
+        assert_eq!(failed, rows(&[("uid", json!(0))]));
+        let expect = Expect::Column { column: "gid".to_string(), op: Op::Eq, value: json!(0) };
+        match evaluate(&expect, rows(&[("uid", json!(0))])).0 {
+            Status::Error(_) => {},
+            status => panic!("unexpected {:?}", status),
+        }
+    }
+
+    #[test]
+    fn test_junit_report() {
+        let policy: Policy = serde_yaml::from_str(POLICY).unwrap();
+        let results = vec![
+            CheckResult { check: &policy.checks[0], status: Status::Pass, rows: Vec::new(), time: 0.0 },
+            CheckResult { check: &policy.checks[1], status: Status::Fail,
+                rows: rows(&[("flags", json!("rw,mode=777,<nosuid>"))]), time: 0.0 },
+        ];
+        let xml = junit_report("baseline", &results);
+        assert!(xml.contains("tests=\"2\" failures=\"1\" errors=\"0\""));
+        assert!(xml.contains("<failure message=\"1 offending row(s)\">{&quot;flags&quot;:&quot;rw,mode=777,&lt;nosuid&gt;&quot;}</failure>"));
+    }
+}
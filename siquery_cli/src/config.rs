@@ -0,0 +1,475 @@
+use serde_derive::Deserialize;
+use siquery::options::{
+    csv_options, parse_delimiter, set_cache_ttl, set_carve_dir, set_carve_limits, set_csv_options, set_decorations, set_default_table_timeout,
+    set_root_prefix, set_table_policy, set_table_timeout, set_watchdog_limits, watchdog_limits, CsvQuote, Decorations,
+    DeniedTables, TablePolicy};
+use siquery::tables::get_table_list;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const CONFIG_FILE_NAME: &str = "siquery.toml";
+const KNOWN_KEYS: &[&str] = &[
+    "output", "csv_delimiter", "csv_header", "csv_line_ending", "csv_quote", "decorations",
+    "root_prefix", "osquery_compat", "table_timeout", "tables", "cache", "timeouts", "logger", "history", "remote", "carve",
+    "watchdog",
+];
+const KNOWN_TABLES_KEYS: &[&str] = &["enabled", "disabled", "denied"];
+const KNOWN_LOGGER_KEYS: &[&str] = &["path", "rotate_size", "rotate_count", "syslog"];
+const KNOWN_REMOTE_KEYS: &[&str] = &[
+    "server", "enroll_secret", "enroll_secret_path", "host_identifier", "node_key_path", "tls_server_certs",
+    "distributed_interval", "config_refresh",
+];
+const KNOWN_CARVE_KEYS: &[&str] = &["dir", "max_files", "max_bytes"];
+const KNOWN_WATCHDOG_KEYS: &[&str] = &["memory_limit", "cpu_limit", "max_offences"];
+const KNOWN_HISTORY_KEYS: &[&str] = &["path", "tables", "max_age_days", "max_snapshots"];
+const OUTPUT_MODES: &[&str] = &["pretty", "json", "csv", "html", "ndjson"];
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct Config {
+    /// Output mode used when no mode flag is given: pretty, json, csv, html or ndjson.
+    pub output: Option<String>,
+    pub csv_delimiter: Option<String>,
+    pub csv_header: Option<bool>,
+    /// `crlf` or `lf`.
+    pub csv_line_ending: Option<String>,
+    /// `minimal`, `all` or `non-numeric`.
+    pub csv_quote: Option<String>,
+    /// Comma separated ndjson decorations: name, hostIdentifier, unixTime.
+    pub decorations: Option<String>,
+    pub root_prefix: Option<String>,
+    /// Same as `--osquery-compat`.
+    pub osquery_compat: bool,
+    /// Seconds a table generator may run before the table returns no rows.
+    pub table_timeout: Option<u64>,
+    pub tables: TablesConfig,
+    /// Cache TTL in seconds, by table name.
+    pub cache: BTreeMap<String, u64>,
+    /// Generation timeout in seconds, by table name, overriding `table_timeout`.
+    pub timeouts: BTreeMap<String, u64>,
+    pub logger: LoggerConfig,
+    pub history: HistoryConfig,
+    pub remote: RemoteConfig,
+    pub carve: CarveConfig,
+    pub watchdog: WatchdogConfig,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct TablesConfig {
+    /// When set, only these tables are registered.
+    pub enabled: Option<Vec<String>>,
+    pub disabled: Vec<String>,
+    /// Whether tables left out by `enabled` and `disabled` are omitted or registered as failing stubs.
+    pub denied: Option<String>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct LoggerConfig {
+    pub path: Option<String>,
+    pub rotate_size: Option<u64>,
+    pub rotate_count: Option<u32>,
+    pub syslog: bool,
+}
+
+/// Archive location and limits of `siquery carve` and of the `carves` table.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct CarveConfig {
+    /// Directory of the archives and of the `carves` table.
+    pub dir: Option<String>,
+    pub max_files: Option<usize>,
+    /// Total size of the carved files, before compression.
+    pub max_bytes: Option<u64>,
+}
+
+/// Limits of the watchdog guarding scheduled queries.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct WatchdogConfig {
+    /// Growth of the resident size of siquery during one query, in megabytes.
+    pub memory_limit: Option<u64>,
+    /// Seconds of CPU time one run of a scheduled query may use.
+    pub cpu_limit: Option<u64>,
+    /// Offences after which a scheduled query is not run anymore.
+    pub max_offences: Option<u32>,
+}
+
+/// Server settings of `siquery remote`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct RemoteConfig {
+    /// Base URL of the osquery remote API server.
+    pub server: Option<String>,
+    pub enroll_secret: Option<String>,
+    /// File holding the enroll secret, preferred over `enroll_secret`.
+    pub enroll_secret_path: Option<String>,
+    pub host_identifier: Option<String>,
+    pub node_key_path: Option<String>,
+    /// PEM bundle of the certificates trusted for the server.
+    pub tls_server_certs: Option<String>,
+    /// Seconds between two checks for distributed queries.
+    pub distributed_interval: Option<u64>,
+    /// Seconds between two configuration fetches.
+    pub config_refresh: Option<u64>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(default)]
+pub struct HistoryConfig {
+    pub path: Option<String>,
+    /// Tables recorded by `siquery history record` when `--table` is not given.
+    pub tables: Vec<String>,
+    pub max_age_days: Option<u64>,
+    pub max_snapshots: Option<usize>,
+}
+
+/// Standard locations searched when `--config` is not given, in order.
+fn default_locations() -> Vec<PathBuf> {
+    let mut locations = Vec::new();
+    if let Ok(path) = env::var("SIQUERY_CONFIG") {
+        locations.push(PathBuf::from(path));
+    }
+    if cfg!(windows) {
+        if let Ok(dir) = env::var("PROGRAMDATA") {
+            locations.push(Path::new(&dir).join("siquery").join(CONFIG_FILE_NAME));
+        }
+    } else {
+        if let Ok(dir) = env::var("XDG_CONFIG_HOME") {
+            locations.push(Path::new(&dir).join("siquery").join(CONFIG_FILE_NAME));
+        } else if let Ok(dir) = env::var("HOME") {
+            locations.push(Path::new(&dir).join(".config").join("siquery").join(CONFIG_FILE_NAME));
+        }
+        locations.push(Path::new("/etc/siquery").join(CONFIG_FILE_NAME));
+    }
+    locations
+}
+
+pub fn find_config(path: Option<&str>) -> Option<PathBuf> {
+    match path {
+        Some(path) => Some(PathBuf::from(path)),
+        None => default_locations().into_iter().find(|path| path.is_file()),
+    }
+}
+
+pub fn load_config(path: &Path) -> Result<Config, String> {
+    let content = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    toml::from_str(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+fn unknown_keys(value: &toml::Value, known: &[&str], section: &str, problems: &mut Vec<String>) {
+    if let Some(table) = value.as_table() {
+        for key in table.keys().filter(|key| !known.contains(&key.as_str())) {
+            problems.push(format!("unknown key '{}{}'", section, key));
+        }
+    }
+}
+
+/// Validates a configuration file and returns every problem found.
+pub fn check_config(path: &Path) -> Vec<String> {
+    let mut problems = Vec::new();
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => return vec![format!("{}: {}", path.display(), e)],
+    };
+    let value: toml::Value = match toml::from_str(&content) {
+        Ok(value) => value,
+        Err(e) => return vec![format!("{}", e)],
+    };
+    unknown_keys(&value, KNOWN_KEYS, "", &mut problems);
+    if let Some(tables) = value.get("tables") {
+        unknown_keys(tables, KNOWN_TABLES_KEYS, "tables.", &mut problems);
+    }
+    if let Some(carve) = value.get("carve") {
+        unknown_keys(carve, KNOWN_CARVE_KEYS, "carve.", &mut problems);
+    }
+    if let Some(watchdog) = value.get("watchdog") {
+        unknown_keys(watchdog, KNOWN_WATCHDOG_KEYS, "watchdog.", &mut problems);
+    }
+    if let Some(remote) = value.get("remote") {
+        unknown_keys(remote, KNOWN_REMOTE_KEYS, "remote.", &mut problems);
+    }
+    if let Some(logger) = value.get("logger") {
+        unknown_keys(logger, KNOWN_LOGGER_KEYS, "logger.", &mut problems);
+    }
+    if let Some(history) = value.get("history") {
+        unknown_keys(history, KNOWN_HISTORY_KEYS, "history.", &mut problems);
+    }
+
+    let config: Config = match value.try_into() {
+        Ok(config) => config,
+        Err(e) => {
+            problems.push(format!("{}", e));
+            return problems;
+        }
+    };
+    if let Some(ref output) = config.output {
+        if !OUTPUT_MODES.contains(&output.as_str()) {
+            problems.push(format!("output '{}' must be one of {}", output, OUTPUT_MODES.join(", ")));
+        }
+    }
+    if let Some(ref delimiter) = config.csv_delimiter {
+        if let Err(e) = parse_delimiter(delimiter) {
+            problems.push(e);
+        }
+    }
+    if let Some(ref ending) = config.csv_line_ending {
+        if ending != "lf" && ending != "crlf" {
+            problems.push(format!("csv_line_ending '{}' must be lf or crlf", ending));
+        }
+    }
+    if let Some(ref quote) = config.csv_quote {
+        if let Err(e) = CsvQuote::parse(quote) {
+            problems.push(e);
+        }
+    }
+    if let Some(ref decorations) = config.decorations {
+        if let Err(e) = Decorations::parse(decorations) {
+            problems.push(format!("decorations: {}", e));
+        }
+    }
+    if let Some(ref prefix) = config.root_prefix {
+        if !Path::new(prefix).is_dir() {
+            problems.push(format!("root_prefix '{}' is not a directory", prefix));
+        }
+    }
+
+    if let Some(ref denied) = config.tables.denied {
+        if let Err(e) = DeniedTables::parse(denied) {
+            problems.push(format!("tables.denied: {}", e));
+        }
+    }
+
+    if config.watchdog.max_offences == Some(0) {
+        problems.push("watchdog.max_offences must be at least 1".to_string());
+    }
+
+    if let Some(ref certs) = config.remote.tls_server_certs {
+        if !Path::new(certs).is_file() {
+            problems.push(format!("remote.tls_server_certs '{}' is not a file", certs));
+        }
+    }
+
+    let available = get_table_list();
+    let mut referenced: Vec<&String> = config.tables.disabled.iter().collect();
+    if let Some(ref enabled) = config.tables.enabled {
+        referenced.extend(enabled.iter());
+    }
+    referenced.extend(config.cache.keys());
+    referenced.extend(config.timeouts.keys());
+    referenced.extend(config.history.tables.iter());
+    for table in referenced {
+        if !available.contains(table) {
+            problems.push(format!("table '{}' does not exist on this platform", table));
+        }
+    }
+    problems
+}
+
+impl Config {
+    pub fn output_mode(&self) -> Option<u8> {
+        match self.output.as_ref().map(|output| output.as_str()) {
+            Some("pretty") => Some(0),
+            Some("json") => Some(1),
+            Some("csv") => Some(2),
+            Some("html") => Some(3),
+            Some("ndjson") => Some(4),
+            _ => None,
+        }
+    }
+
+    /// Table policy built from the enabled and disabled lists.
+    pub fn table_policy(&self) -> TablePolicy {
+        TablePolicy {
+            allow: self.tables.enabled.as_ref().map(|enabled| enabled.iter().cloned().collect()),
+            deny: self.tables.disabled.iter().cloned().collect(),
+            denied: self.tables.denied.as_ref()
+                .and_then(|denied| DeniedTables::parse(denied).ok())
+                .unwrap_or(DeniedTables::Omit),
+        }
+    }
+
+    /// Pushes library-level settings to siquery.
+    pub fn apply(&self) {
+        if let Some(ref prefix) = self.root_prefix {
+            set_root_prefix(Some(PathBuf::from(prefix)));
+        }
+        let mut csv = csv_options();
+        if let Some(delimiter) = self.csv_delimiter.as_ref().and_then(|d| parse_delimiter(d).ok()) {
+            csv.delimiter = delimiter;
+        }
+        if let Some(header) = self.csv_header {
+            csv.header = header;
+        }
+        if let Some(ref ending) = self.csv_line_ending {
+            csv.crlf = ending != "lf";
+        }
+        if let Some(quote) = self.csv_quote.as_ref().and_then(|q| CsvQuote::parse(q).ok()) {
+            csv.quote = quote;
+        }
+        set_csv_options(csv);
+        if let Some(decorations) = self.decorations.as_ref().and_then(|d| Decorations::parse(d).ok()) {
+            set_decorations(decorations);
+        }
+        set_table_policy(self.table_policy());
+        if let Some(ref dir) = self.carve.dir {
+            set_carve_dir(Some(PathBuf::from(dir)));
+        }
+        set_carve_limits(self.carve.max_files, self.carve.max_bytes);
+        let mut watchdog = watchdog_limits();
+        if let Some(megabytes) = self.watchdog.memory_limit {
+            watchdog.memory = Some(megabytes * 1_048_576);
+        }
+        if let Some(seconds) = self.watchdog.cpu_limit {
+            watchdog.cpu_time = Some(Duration::from_secs(seconds));
+        }
+        if let Some(max_offences) = self.watchdog.max_offences {
+            watchdog.max_offences = max_offences;
+        }
+        set_watchdog_limits(watchdog);
+        for (table, ttl) in self.cache.iter() {
+            set_cache_ttl(table, Duration::from_secs(*ttl));
+        }
+        if let Some(timeout) = self.table_timeout {
+            set_default_table_timeout(Some(Duration::from_secs(timeout)));
+        }
+        for (table, timeout) in self.timeouts.iter() {
+            set_table_timeout(table, Duration::from_secs(*timeout));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::App;
+    use siquery::options::{set_csv_options, table_policy, CsvOptions};
+
+    const CONFIG: &str = r#"
+output = "csv"
+csv_delimiter = ";"
+csv_header = false
+table_timeout = 5
+
+[tables]
+disabled = ["users"]
+
+[cache]
+processes = 10
+
+[carve]
+max_files = 5
+"#;
+
+    fn write_config(name: &str, content: &str) -> PathBuf {
+        let path = env::temp_dir().join(format!("siquery_config_{}_{}.toml", std::process::id(), name));
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_load_config() {
+        let path = write_config("load", CONFIG);
+        let config = load_config(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(config.output_mode(), Some(2));
+        assert_eq!(config.csv_delimiter, Some(";".to_string()));
+        assert_eq!(config.csv_header, Some(false));
+        assert_eq!(config.table_timeout, Some(5));
+        assert_eq!(config.tables.disabled, vec!["users"]);
+        assert_eq!(config.cache.get("processes"), Some(&10));
+        assert_eq!(config.carve.max_files, Some(5));
+        assert_eq!(config.carve.max_bytes, None);
+        assert!(!config.table_policy().allows("users"));
+        assert!(load_config(&path).unwrap_err().starts_with(&path.display().to_string()));
+    }
+
+    #[test]
+    fn test_check_config() {
+        let foreign = if cfg!(windows) { "mounts" } else { "wmi_bios" };
+        let path = write_config("check", &format!(r#"
+colour = "blue"
+
+[tables]
+hidden = ["users"]
+
+[timeouts]
+{} = 1
+no_such_table = 1
+"#, foreign));
+        let problems = check_config(&path);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(problems, vec![
+            "unknown key 'colour'".to_string(),
+            "unknown key 'tables.hidden'".to_string(),
+            "table 'no_such_table' does not exist on this platform".to_string(),
+            format!("table '{}' does not exist on this platform", foreign),
+        ]);
+
+        let path = write_config("check_valid", CONFIG);
+        assert!(check_config(&path).is_empty());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_find_config() {
+        let dir = env::temp_dir().join(format!("siquery_config_{}_xdg", std::process::id()));
+        fs::create_dir_all(dir.join("siquery")).unwrap();
+        let xdg = dir.join("siquery").join(CONFIG_FILE_NAME);
+        fs::write(&xdg, CONFIG).unwrap();
+        let variable = write_config("variable", CONFIG);
+        let saved: Vec<(&str, Option<String>)> = ["SIQUERY_CONFIG", "XDG_CONFIG_HOME"].iter()
+            .map(|name| (*name, env::var(name).ok()))
+            .collect();
+        env::set_var("SIQUERY_CONFIG", &variable);
+        env::set_var("XDG_CONFIG_HOME", &dir);
+
+        // --config wins even when the file does not exist, then SIQUERY_CONFIG, then the user file.
+        assert_eq!(find_config(Some("explicit.toml")), Some(PathBuf::from("explicit.toml")));
+        assert_eq!(find_config(None), Some(variable.clone()));
+        fs::remove_file(&variable).unwrap();
+        if !cfg!(windows) {
+            assert_eq!(find_config(None), Some(xdg));
+        }
+
+        for (name, value) in saved {
+            match value {
+                Some(value) => env::set_var(name, value),
+                None => env::remove_var(name),
+            }
+        }
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_apply_overridden_by_flags() {
+        let path = write_config("apply", CONFIG);
+        let config = load_config(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let yaml = load_yaml!("cli.yml");
+        let matches = App::from_yaml(yaml).get_matches_from(vec![
+            "siquery", "--csv-delimiter", "tab", "--deny-tables", "groups", "--allow-tables", "users,groups,mounts",
+        ]);
+
+        config.apply();
+        assert_eq!(csv_options().delimiter, b';');
+        assert!(!csv_options().header);
+        crate::apply_csv_args(&matches);
+        crate::apply_policy_args(&matches);
+        // Flags replace the configured delimiter and keep the settings they do not name.
+        assert_eq!(csv_options().delimiter, b'\t');
+        assert!(!csv_options().header);
+        let policy = table_policy();
+        assert!(policy.allows("mounts"));
+        assert!(!policy.allows("users"));
+        assert!(!policy.allows("groups"));
+        assert!(!policy.allows("processes"));
+
+        set_csv_options(CsvOptions::default());
+        set_table_policy(TablePolicy::default());
+    }
+}
@@ -0,0 +1,106 @@
+use clap::ArgMatches;
+use serde_json::{json, Map, Value};
+use siquery::diff::{diff_results, TableDiff};
+use std::collections::HashMap;
+use std::fs;
+use std::process;
+
+fn load(path: &str) -> Map<String, Value> {
+    let content = fs::read_to_string(path).unwrap_or_else(|e| {
+        println!("{}: {}", path, e);
+        process::exit(2);
+    });
+    match serde_json::from_str(&content) {
+        Ok(Value::Object(tables)) => tables,
+        Ok(_) => {
+            println!("{}: expected an object of tables", path);
+            process::exit(2);
+        },
+        Err(e) => {
+            println!("{}: {}", path, e);
+            process::exit(2);
+        }
+    }
+}
+
+fn parse_keys(matches: &ArgMatches) -> HashMap<String, String> {
+    let mut keys = HashMap::new();
+    for key in matches.values_of("key").into_iter().flatten() {
+        let mut split = key.splitn(2, ':');
+        match (split.next(), split.next()) {
+            (Some(table), Some(column)) if !table.is_empty() && !column.is_empty() => {
+                keys.insert(table.to_string(), column.to_string());
+            },
+            _ => {
+                println!("invalid key '{}', expected table:column", key);
+                process::exit(2);
+            }
+        }
+    }
+    keys
+}
+
+/// Prints the rows added, removed and changed in one table, also used by `siquery history diff`.
+pub fn print_table_diff(diff: &TableDiff) {
+    let key = diff.key.as_ref().map(|key| format!(" (key {})", key)).unwrap_or_default();
+    println!("{}{}: {} added, {} removed, {} changed",
+             diff.table, key, diff.added.len(), diff.removed.len(), diff.changed.len());
+    for row in diff.added.iter() {
+        println!("+ {}", Value::Object(row.clone()));
+    }
+    for row in diff.removed.iter() {
+        println!("- {}", Value::Object(row.clone()));
+    }
+    for change in diff.changed.iter() {
+        let columns: Vec<String> = change.columns().into_iter()
+            .map(|(column, before, after)| format!("{} {} -> {}", column, before, after))
+            .collect();
+        println!("~ {}={}: {}", diff.key.as_ref().map(|k| k.as_str()).unwrap_or(""), change.key, columns.join(", "));
+    }
+}
+
+fn print_pretty(diffs: &[TableDiff]) {
+    for diff in diffs.iter().filter(|diff| !diff.is_empty()) {
+        print_table_diff(diff);
+    }
+    let changed = diffs.iter().filter(|diff| !diff.is_empty()).count();
+    println!("{} tables compared, {} with differences", diffs.len(), changed);
+}
+
+/// JSON report of one table, also used by `siquery history diff`.
+pub fn table_diff_json(diff: &TableDiff) -> Map<String, Value> {
+    let changed: Vec<Value> = diff.changed.iter().map(|change| {
+        let mut columns = Map::new();
+        for (column, before, after) in change.columns() {
+            columns.insert(column, json!({"before": before, "after": after}));
+        }
+        json!({"key": change.key, "columns": columns, "before": change.before, "after": change.after})
+    }).collect();
+    let mut report = Map::new();
+    report.insert("table".to_string(), json!(diff.table));
+    report.insert("key".to_string(), json!(diff.key));
+    report.insert("added".to_string(), json!(diff.added));
+    report.insert("removed".to_string(), json!(diff.removed));
+    report.insert("changed".to_string(), Value::Array(changed));
+    report
+}
+
+fn print_json(diffs: &[TableDiff]) {
+    let report: Vec<Value> = diffs.iter().map(|diff| Value::Object(table_diff_json(diff))).collect();
+    println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+}
+
+/// Runs `siquery diff before.json after.json`, exiting with 1 when the files differ like diff(1) does.
+pub fn run_diff(matches: &ArgMatches) {
+    let keys = parse_keys(matches);
+    let before = load(matches.value_of("before").unwrap_or(""));
+    let after = load(matches.value_of("after").unwrap_or(""));
+    let diffs = diff_results(&before, &after, &keys);
+    match matches.value_of("format") {
+        Some("json") => print_json(&diffs),
+        _ => print_pretty(&diffs),
+    }
+    if diffs.iter().any(|diff| !diff.is_empty()) {
+        process::exit(1);
+    }
+}
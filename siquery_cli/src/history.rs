@@ -0,0 +1,96 @@
+use clap::ArgMatches;
+use rusqlite::Connection;
+use serde_json::{json, Value};
+use siquery::events::unix_time;
+use siquery::history::{default_history_path, format_time, parse_time, HistoryStore, Retention};
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::diff::{print_table_diff, table_diff_json};
+
+/// Store given with `--history-db`, then in the configuration, then the default location.
+pub fn history_path(history_db: Option<&str>, config: &Config) -> PathBuf {
+    history_db
+        .map(PathBuf::from)
+        .or_else(|| config.history.path.as_ref().map(PathBuf::from))
+        .unwrap_or_else(default_history_path)
+}
+
+fn open_store(path: &PathBuf) -> HistoryStore {
+    HistoryStore::open(path).unwrap_or_else(|e| {
+        println!("{}: {}", path.display(), e);
+        process::exit(1);
+    })
+}
+
+fn record(matches: &ArgMatches, db: &Connection, config: &Config, path: &PathBuf) {
+    let tables: Vec<String> = match matches.value_of("table") {
+        Some(tables) => tables.split(',').map(|table| table.to_string()).collect(),
+        None => config.history.tables.clone(),
+    };
+    if tables.is_empty() {
+        println!("no tables to record, use --table or history.tables");
+        process::exit(1);
+    }
+    let mut store = open_store(path);
+    let max_age = value_t!(matches, "max_age", u64).ok().or(config.history.max_age_days);
+    store.set_retention(Retention {
+        max_age: max_age.map(|days| Duration::from_secs(days * 86400)),
+        max_snapshots: value_t!(matches, "max_snapshots", usize).ok().or(config.history.max_snapshots),
+    });
+    let interval = value_t!(matches, "interval", u64).unwrap_or(0);
+    loop {
+        let time = unix_time();
+        for table in tables.iter() {
+            match store.record(db, table, time) {
+                Ok(rows) => println!("{} {}: {} rows", format_time(time), table, rows),
+                Err(e) => println!("{}: {}", table, e),
+            }
+        }
+        if interval == 0 {
+            break;
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
+
+fn diff(matches: &ArgMatches, path: &PathBuf) {
+    let time = |name: &str| match matches.value_of(name) {
+        Some(time) => parse_time(time).unwrap_or_else(|e| {
+            println!("--{}: {}", name, e);
+            process::exit(1);
+        }),
+        None => unix_time(),
+    };
+    let (from, to) = (time("from"), time("to"));
+    let table = matches.value_of("table").unwrap_or("");
+    let diff = open_store(path).diff(table, from, to).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+    if matches.value_of("format") == Some("json") {
+        let mut report = table_diff_json(&diff.changes);
+        report.insert("from".to_string(), json!(diff.from));
+        report.insert("to".to_string(), json!(diff.to));
+        println!("{}", serde_json::to_string_pretty(&Value::Object(report)).unwrap_or_default());
+        return;
+    }
+    println!("{}: {} -> {}", diff.changes.table, format_time(diff.from), format_time(diff.to));
+    if diff.changes.is_empty() {
+        println!("no changes");
+    } else {
+        print_table_diff(&diff.changes);
+    }
+}
+
+/// Runs `siquery history record|diff`.
+pub fn run_history(matches: &ArgMatches, db: &Connection, config: &Config, path: &PathBuf) {
+    match matches.subcommand() {
+        ("record", Some(record_matches)) => record(record_matches, db, config, path),
+        ("diff", Some(diff_matches)) => diff(diff_matches, path),
+        _ => println!("{}", matches.usage()),
+    }
+}
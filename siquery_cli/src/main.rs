@@ -1,59 +1,539 @@
 #[macro_use]
 extern crate clap;
 
-use siquery::query::{init_db, execute_query};
+#[cfg(feature = "carve")]
+mod carve;
+mod check;
+mod config;
+mod diff;
+mod history;
+#[cfg(feature = "remote")]
+mod remote;
+
+use siquery::query::{
+    init_db, execute_query_with_params, execute_query_json, execute_statements, split_statements};
 use siquery::tables::get_table_list;
 use siquery::printer::{print_schema, print_table_by_name};
-use clap::App;
-use std::fs::File;
+use siquery::html::HtmlReport;
+use siquery::compat::{compat_schema, create_compat_views, COMPAT_TABLES};
+use siquery::history::attach_history;
+use siquery::merge::{load_host_results, merge_host};
+use siquery::options::{
+    csv_options, parse_delimiter, set_csv_options, set_decorations, set_default_table_timeout, set_flag,
+    set_table_policy, set_watchdog_limits, table_allowed, table_policy, watchdog_limits, CsvQuote, Decorations, DeniedTables};
+use siquery::watchdog::Watchdog;
+use siquery::logger::{Loggers, FileLogger};
+use siquery::profile::{print_profile, reset_table_stats, take_warnings};
+use clap::{App, ArgMatches};
+use rusqlite::Connection;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::process;
+use std::time::Duration;
+
+use crate::check::{load_policy, run_checks, Status};
+use crate::config::{Config, find_config, load_config, check_config};
+use crate::history::{history_path, run_history};
+
+#[cfg(feature = "columnar")]
+use siquery::columnar::{export_query, ColumnarFormat};
 
 #[cfg(target_os = "windows")]
 use siquery::inventory::execute_inventory_query;
 
-fn main() {
-    let yaml = load_yaml!("cli.yml");
-    let app = App::from_yaml(yaml);
-    let matches = app.version(crate_version!()).get_matches();
+#[cfg(target_os = "linux")]
+use siquery::sys::file_events::{self, FileEventsConfig};
+
+#[cfg(unix)]
+use siquery::logger::SyslogLogger;
+
+/// Output mode from the command line, falling back to the configuration file.
+fn output_mode(matches: &ArgMatches, config: &Config) -> u8 {
+    if let Some(format) = matches.value_of("format") {
+        match format {
+            "json" => 1,
+            "csv" => 2,
+            "html" => 3,
+            "ndjson" => 4,
+            "parquet" => 5,
+            "arrow" => 6,
+            _ => 0,
+        }
+    } else if matches.is_present("json_mode") {
+        1
+    } else if matches.is_present("csv_mode") || matches.is_present("tsv_mode") {
+        2
+    } else if matches.is_present("html_mode") {
+        3
+    } else if matches.is_present("ndjson_mode") {
+        4
+    } else if matches.is_present("pretty_mode") {
+        0
+    } else {
+        config.output_mode().unwrap_or(0)
+    }
+}
+
+/// Overrides the configured table policy with command line flags.
+fn apply_policy_args(matches: &ArgMatches) {
+    let mut policy = table_policy();
+    if let Some(tables) = matches.value_of("allow_tables") {
+        policy.allow = Some(tables.split(',').map(|name| name.trim().to_string()).collect());
+    }
+    if let Some(tables) = matches.value_of("deny_tables") {
+        policy.deny.extend(tables.split(',').map(|name| name.trim().to_string()));
+    }
+    if let Some(mode) = matches.value_of("denied_tables") {
+        match DeniedTables::parse(mode) {
+            Ok(denied) => policy.denied = denied,
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+    set_table_policy(policy);
+}
+
+/// Overrides the configured watchdog limits with command line flags.
+fn apply_watchdog_args(matches: &ArgMatches) {
+    let mut limits = watchdog_limits();
+    if matches.is_present("watchdog_memory_limit") {
+        let megabytes = value_t!(matches, "watchdog_memory_limit", u64).unwrap_or_else(|e| e.exit());
+        limits.memory = Some(megabytes * 1_048_576);
+    }
+    if matches.is_present("watchdog_cpu_limit") {
+        let seconds = value_t!(matches, "watchdog_cpu_limit", u64).unwrap_or_else(|e| e.exit());
+        limits.cpu_time = Some(Duration::from_secs(seconds));
+    }
+    set_watchdog_limits(limits);
+}
+
+/// Overrides the configured csv formatting with command line flags.
+fn apply_csv_args(matches: &ArgMatches) {
+    let mut csv = csv_options();
+    if matches.is_present("tsv_mode") {
+        csv.delimiter = b'\t';
+    }
+    if let Some(delimiter) = matches.value_of("csv_delimiter") {
+        csv.delimiter = parse_delimiter(delimiter).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        });
+    }
+    if matches.is_present("no_header") {
+        csv.header = false;
+    }
+    if let Some(ending) = matches.value_of("line_ending") {
+        csv.crlf = ending == "crlf";
+    }
+    if let Some(quote) = matches.value_of("quote").and_then(|q| CsvQuote::parse(q).ok()) {
+        csv.quote = quote;
+    }
+    set_csv_options(csv);
+}
+
+/// Parses `--param name=value` arguments.
+fn query_params(matches: &ArgMatches) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    if let Some(values) = matches.values_of("param") {
+        for value in values {
+            let mut split = value.splitn(2, '=');
+            match (split.next(), split.next()) {
+                (Some(name), Some(value)) if !name.is_empty() => {
+                    params.push((name.to_string(), value.to_string()));
+                },
+                _ => {
+                    println!("invalid parameter '{}', expected name=value", value);
+                    process::exit(1);
+                }
+            }
+        }
+    }
+    params
+}
+
+/// Reads the script given with `-f`, or with `-q -` from stdin.
+fn read_script(matches: &ArgMatches) -> Option<String> {
+    let path = match matches.value_of("file") {
+        Some(path) => path,
+        None if matches.value_of("siquery") == Some("-") => "-",
+        None => return None,
+    };
+    let script = if path == "-" {
+        let mut script = String::new();
+        io::stdin().read_to_string(&mut script).map(|_| script)
+    } else {
+        fs::read_to_string(path)
+    };
+    match script {
+        Ok(script) => Some(script),
+        Err(e) => {
+            println!("{}: {}", path, e);
+            process::exit(1);
+        }
+    }
+}
+
+/// Builds one HTML document from the selected tables or statements.
+fn html_report(matches: &ArgMatches, db: &Connection, script: &Option<String>, params: &[(String, String)]) {
+    let mut report = HtmlReport::new(db);
+    if let Some(table) = matches.value_of("table") {
+        let names: Vec<&str> = table.split(',').collect();
+        for name in get_table_list().iter().filter(|name| names.contains(&name.as_str())) {
+            report.add_table(name);
+        }
+    } else {
+        let statements = match script {
+            Some(script) => split_statements(script),
+            None => vec![matches.value_of("siquery").unwrap_or("").to_string()],
+        };
+        for (i, statement) in statements.iter().enumerate() {
+            report.add_query(&format!("query {}", i + 1), statement, params);
+        }
+    }
+    match matches.value_of("out") {
+        Some(path) => {
+            if let Err(e) = report.write_to(path) {
+                println!("{}: {}", path, e);
+                process::exit(1);
+            }
+        },
+        None => print!("{}", report.render()),
+    }
+}
+
+/// Writes a single table or statement to a Parquet (mode 5) or Arrow (mode 6) file.
+#[cfg(feature = "columnar")]
+fn columnar_export(matches: &ArgMatches, db: &Connection, mode: u8, script: &Option<String>,
+                   params: &[(String, String)]) {
+    let path = matches.value_of("out").unwrap_or_else(|| {
+        println!("--format parquet and --format arrow need --out <path>");
+        process::exit(1);
+    });
+    let statements = if let Some(table) = matches.value_of("table") {
+        table.split(',').map(|name| format!("select * from {}", name)).collect()
+    } else if let Some(script) = script {
+        split_statements(script)
+    } else {
+        matches.value_of("siquery").map(|query| vec![query.to_string()]).unwrap_or_default()
+    };
+    if statements.len() != 1 {
+        println!("columnar output needs exactly one table or statement");
+        process::exit(1);
+    }
+    let format = if mode == 5 { ColumnarFormat::Parquet } else { ColumnarFormat::Arrow };
+    if let Err(e) = export_query(db, &statements[0], params, format, Path::new(path)) {
+        println!("{}: {}", path, e);
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "carve")]
+fn run_carve(matches: &ArgMatches, db: &Connection, config: &Config) {
+    carve::run_carve(matches, db, config);
+}
+
+#[cfg(not(feature = "carve"))]
+fn run_carve(_matches: &ArgMatches, _db: &Connection, _config: &Config) {
+    println!("siquery was built without the carve feature");
+    process::exit(1);
+}
+
+#[cfg(feature = "remote")]
+fn run_remote(matches: &ArgMatches, db: &Connection, config: &Config) {
+    remote::run_remote(matches, db, config);
+}
+
+#[cfg(not(feature = "remote"))]
+fn run_remote(_matches: &ArgMatches, _db: &Connection, _config: &Config) {
+    println!("siquery was built without the remote feature");
+    process::exit(1);
+}
+
+#[cfg(not(feature = "columnar"))]
+fn columnar_export(_matches: &ArgMatches, _db: &Connection, _mode: u8, _script: &Option<String>,
+                   _params: &[(String, String)]) {
+    println!("siquery was built without the columnar feature");
+    process::exit(1);
+}
+
+fn run(matches: &ArgMatches, db: &Connection, mode: u8, script: &Option<String>, params: &[(String, String)],
+       osquery_compat: bool) {
     let table = matches.value_of("table").unwrap_or("").to_string();
     let siquery = matches.value_of("siquery").unwrap_or("").to_string();
     let schema = matches.value_of("schema").unwrap_or("").to_string();
-    let db = init_db();
 
     if matches.is_present("list_all") {
-        for table in get_table_list().iter() {
+        let mut tables: Vec<String> = get_table_list().into_iter().filter(|table| table_allowed(table)).collect();
+        if osquery_compat {
+            tables.extend(COMPAT_TABLES.iter()
+                .filter(|compat| tables.iter().any(|table| table == compat.source))
+                .map(|compat| compat.name.to_string())
+                .collect::<Vec<String>>());
+            tables.sort();
+            tables.dedup();
+        }
+        for table in tables.iter() {
             println!("{}", table);
         }
     } else if matches.is_present("rdm_inventory") {
         #[cfg(target_os = "windows")]
         execute_inventory_query();
     } else if matches.is_present("schema") {
-        print_schema(schema);
-    } else if matches.is_present("json_mode") {
-        if table.len() > 0 {
-            print_table_by_name(db, table, 1);
-        } else if siquery.len() > 0 {
-            execute_query(&db, &siquery, "".to_string(),1);
+        match compat_schema(&schema).filter(|_| osquery_compat) {
+            Some(compat) => println!("{}", compat),
+            None => print_schema(schema),
         }
-    } else if matches.is_present("csv_mode") {
-        if table.len() > 0 {
-            print_table_by_name(db, table, 2);
-        } else if siquery.len() > 0 {
-            execute_query(&db, &siquery, "".to_string(),2);
+    } else if mode == 5 || mode == 6 {
+        columnar_export(matches, db, mode, script, params);
+    } else if mode == 3 && (table.len() > 0 || script.is_some() || siquery.len() > 0) {
+        html_report(matches, db, script, params);
+    } else if table.len() > 0 {
+        print_table_by_name(db, table, mode);
+    } else if let Some(script) = script {
+        execute_statements(db, script, mode, params);
+    } else if siquery.len() > 0 {
+        execute_query_with_params(db, &siquery, "".to_string(), mode, params);
+    }
+}
+
+/// Runs `siquery check <policy>` and exits with 1 when a check does not pass.
+fn run_policy(matches: &ArgMatches, db: &Connection, mode: u8) {
+    let path = matches.value_of("policy").unwrap_or("");
+    let policy = load_policy(Path::new(path)).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+    let results = run_checks(db, &policy);
+    let format = matches.value_of("format").unwrap_or(if mode == 1 { "json" } else { "pretty" });
+    match format {
+        "json" => check::print_json(&results),
+        "junit" => print!("{}", check::junit_report(policy.name.as_ref().map(|n| n.as_str()).unwrap_or(path), &results)),
+        _ => check::print_pretty(&results),
+    }
+    if results.iter().any(|result| result.status != Status::Pass) {
+        process::exit(1);
+    }
+}
+
+/// Runs `siquery merge <inputs>... --out <file>`.
+fn merge(matches: &ArgMatches) {
+    let out = matches.value_of("out").unwrap_or("");
+    let db = Connection::open(out).unwrap_or_else(|e| {
+        println!("{}: {}", out, e);
+        process::exit(1);
+    });
+    let mut failed = false;
+    for input in matches.values_of("inputs").into_iter().flatten() {
+        let merged = load_host_results(Path::new(input)).and_then(|results| {
+            for warning in results.warnings.iter() {
+                eprintln!("warning: {}", warning);
+            }
+            merge_host(&db, &results).map(|rows| (results.host, rows))
+        });
+        match merged {
+            Ok((host, rows)) => println!("{}: {} rows from {}", host, rows, input),
+            Err(e) => {
+                println!("{}", e);
+                failed = true;
+            }
         }
-    } else if matches.is_present("html_mode") {
-        if table.len() > 0 {
-            let _file = File::create("inventory.html")
-                .map_err(|e| println!("html printer failed with: {:?}",e));
-            print_table_by_name(db, table, 3);
-        } else if siquery.len() > 0 {
-            execute_query(&db, &siquery, "".to_string(), 3);
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn create_loggers(matches: &ArgMatches, config: &Config) -> Loggers {
+    let mut loggers = Loggers::new();
+    if let Some(path) = matches.value_of("logger_path").or(config.logger.path.as_ref().map(|p| p.as_str())) {
+        let max_size = value_t!(matches, "logger_rotate_size", u64)
+            .unwrap_or(config.logger.rotate_size.unwrap_or(26_214_400));
+        let max_files = value_t!(matches, "logger_rotate_count", u32)
+            .unwrap_or(config.logger.rotate_count.unwrap_or(5));
+        loggers.add(Box::new(FileLogger::new(PathBuf::from(path), max_size, max_files)));
+    }
+    if matches.is_present("logger_syslog") || config.logger.syslog {
+        #[cfg(unix)]
+        loggers.add(Box::new(SyslogLogger::new()));
+        #[cfg(not(unix))]
+        println!("syslog logger is not supported on this platform");
+    }
+    loggers
+}
+
+fn log_results(matches: &ArgMatches, db: &Connection, loggers: &mut Loggers, watchdog: &mut Watchdog,
+               script: &Option<String>, params: &[(String, String)]) {
+    let mut queries: Vec<(String, String)> = Vec::new();
+    if let Some(script) = script {
+        for statement in split_statements(script) {
+            queries.push((statement.clone(), statement));
         }
-    } else {
-        if table.len() > 0 {
-            print_table_by_name(db,table, 0);
-        } else if siquery.len() > 0 {
-            execute_query(&db, &siquery, "".to_string(),0);
+    } else if let Some(siquery) = matches.value_of("siquery") {
+        queries.push((siquery.to_string(), siquery.to_string()));
+    } else if let Some(table) = matches.value_of("table") {
+        let tables = get_table_list();
+        for name in table.split(',').filter(|name| tables.iter().any(|t| t == name)) {
+            queries.push((name.to_string(), format!("select * from {}", name)));
+        }
+    }
+    for (name, query) in queries {
+        match watchdog.run(&name, || execute_query_json(db, &query, params)) {
+            Some(Ok(rows)) => {
+                for (logger, e) in loggers.log(&name, &rows) {
+                    eprintln!("warning: {} logger failed with: {}", logger, e);
+                }
+            },
+            Some(Err(e)) => println!("{}: {}", name, e),
+            None => {},
         }
     }
 }
 
+fn main() {
+    let yaml = load_yaml!("cli.yml");
+    let app = App::from_yaml(yaml);
+    let matches = app.version(crate_version!()).get_matches();
+    let config_path = find_config(matches.value_of("config_file"));
+
+    if let Some(config_matches) = matches.subcommand_matches("config") {
+        if config_matches.is_present("check") {
+            let path = match config_path {
+                Some(path) => path,
+                None => {
+                    println!("no configuration file found");
+                    process::exit(1);
+                }
+            };
+            let problems = check_config(&path);
+            for problem in problems.iter() {
+                println!("{}", problem);
+            }
+            if !problems.is_empty() {
+                process::exit(1);
+            }
+            println!("{}: ok", path.display());
+        }
+        return;
+    }
+
+    if let Some(diff_matches) = matches.subcommand_matches("diff") {
+        diff::run_diff(diff_matches);
+        return;
+    }
+    if let Some(merge_matches) = matches.subcommand_matches("merge") {
+        merge(merge_matches);
+        return;
+    }
+
+    let config = match config_path {
+        Some(ref path) => load_config(path).unwrap_or_else(|e| {
+            println!("{}", e);
+            process::exit(1);
+        }),
+        None => Config::default(),
+    };
+    config.apply();
+    apply_policy_args(&matches);
+    apply_csv_args(&matches);
+    if let Some(fields) = matches.value_of("decorations") {
+        match Decorations::parse(fields) {
+            Ok(decorations) => set_decorations(decorations),
+            Err(e) => {
+                println!("{}", e);
+                process::exit(1);
+            }
+        }
+    }
+
+    if matches.is_present("table_timeout") {
+        let timeout = value_t!(matches, "table_timeout", u64).unwrap_or_else(|e| e.exit());
+        set_default_table_timeout(Some(Duration::from_secs(timeout)));
+    }
+    apply_watchdog_args(&matches);
+    let interval = value_t!(matches, "interval", u64).unwrap_or(0);
+    let profile = matches.is_present("profile");
+    let mode = output_mode(&matches, &config);
+    let script = read_script(&matches);
+    let params = query_params(&matches);
+    let db = init_db();
+    let osquery_compat = matches.is_present("osquery_compat") || config.osquery_compat;
+    const MODES: &[&str] = &["pretty", "json", "csv", "html", "ndjson", "parquet", "arrow"];
+    set_flag("output", MODES.get(mode as usize).unwrap_or(&"pretty"), "Output mode");
+    set_flag("osquery_compat", &osquery_compat.to_string(), "Whether osquery compatible views are created");
+    set_flag("config", &config_path.as_ref().map(|path| path.display().to_string()).unwrap_or_default(),
+             "Configuration file");
+    set_flag("interval", &interval.to_string(), "Seconds between query runs, 0 to run once");
+    if osquery_compat {
+        if let Err(e) = create_compat_views(&db) {
+            println!("osquery compatibility views: {}", e);
+        }
+    }
+    let history_matches = matches.subcommand_matches("history");
+    let history_db = history_matches
+        .and_then(|m| m.subcommand().1)
+        .and_then(|m| m.value_of("history_db"))
+        .or(matches.value_of("history_db"));
+    let history_path = history_path(history_db, &config);
+    if let Some(history_matches) = history_matches {
+        run_history(history_matches, &db, &config, &history_path);
+        return;
+    }
+    if history_path.is_file() {
+        if let Err(e) = attach_history(&db, &history_path) {
+            println!("{}: {}", history_path.display(), e);
+        }
+    }
+    if let Some(check_matches) = matches.subcommand_matches("check") {
+        run_policy(check_matches, &db, mode);
+        return;
+    }
+    if let Some(carve_matches) = matches.subcommand_matches("carve") {
+        run_carve(carve_matches, &db, &config);
+        return;
+    }
+    if let Some(remote_matches) = matches.subcommand_matches("remote") {
+        run_remote(remote_matches, &db, &config);
+        return;
+    }
+    let mut loggers = create_loggers(&matches, &config);
+    let mut watchdog = Watchdog::new(watchdog_limits());
+
+    #[cfg(target_os = "linux")]
+    {
+        if let Some(paths) = matches.values_of("watch") {
+            let config = FileEventsConfig {
+                paths: paths.map(|path| path.to_string()).collect(),
+                drain: matches.is_present("drain_events"),
+                ..FileEventsConfig::default()
+            };
+            if let Err(e) = file_events::start(config) {
+                println!("file_events could not start: {}", e);
+            }
+        }
+    }
+
+    loop {
+        if profile {
+            reset_table_stats();
+        }
+        if loggers.is_empty() {
+            run(&matches, &db, mode, &script, &params, osquery_compat);
+        } else {
+            log_results(&matches, &db, &mut loggers, &mut watchdog, &script, &params);
+        }
+        for warning in take_warnings() {
+            eprintln!("warning: {}", warning);
+        }
+        if profile {
+            print_profile();
+        }
+        if interval == 0 {
+            break;
+        }
+        thread::sleep(Duration::from_secs(interval));
+    }
+}
@@ -0,0 +1,127 @@
+use clap::ArgMatches;
+use rusqlite::Connection;
+use siquery::query::execute_query_json;
+use siquery::options::watchdog_limits;
+use siquery::profile::take_warnings;
+use siquery::remote::{default_node_key_path, host_details, schedule, RemoteClient, RemoteConfig, ScheduledQuery};
+use siquery::watchdog::Watchdog;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::config::Config;
+
+/// Client settings from the command line, then the `[remote]` section of the configuration.
+fn remote_config(matches: &ArgMatches, config: &Config) -> RemoteConfig {
+    let remote = &config.remote;
+    let server = matches.value_of("server").map(|server| server.to_string()).or_else(|| remote.server.clone());
+    let server = server.unwrap_or_else(|| {
+        println!("no server, use --server or remote.server");
+        process::exit(1);
+    });
+    let secret_path = matches.value_of("enroll_secret_path").map(PathBuf::from)
+        .or_else(|| remote.enroll_secret_path.as_ref().map(PathBuf::from));
+    let enroll_secret = match secret_path {
+        Some(path) => fs::read_to_string(&path).map(|secret| secret.trim().to_string()).unwrap_or_else(|e| {
+            println!("{}: {}", path.display(), e);
+            process::exit(1);
+        }),
+        None => remote.enroll_secret.clone().unwrap_or_default(),
+    };
+    RemoteConfig {
+        server,
+        enroll_secret,
+        host_identifier: remote.host_identifier.clone().unwrap_or_default(),
+        node_key_path: matches.value_of("node_key_path").map(PathBuf::from)
+            .or_else(|| remote.node_key_path.as_ref().map(PathBuf::from))
+            .unwrap_or_else(default_node_key_path),
+        tls_server_certs: matches.value_of("tls_server_certs").map(PathBuf::from)
+            .or_else(|| remote.tls_server_certs.as_ref().map(PathBuf::from)),
+        ..RemoteConfig::default()
+    }
+}
+
+fn refresh_schedule(client: &mut RemoteClient) -> Option<Vec<ScheduledQuery>> {
+    match client.config() {
+        Ok(config) => Some(schedule(&config)),
+        Err(e) => {
+            println!("config: {}", e);
+            None
+        }
+    }
+}
+
+/// Runs `siquery remote`: enrolls, then runs the scheduled queries of the server's configuration
+/// and answers its distributed queries until interrupted, or once with `--once`. Scheduled queries
+/// run under the watchdog.
+pub fn run_remote(matches: &ArgMatches, db: &Connection, config: &Config) {
+    let mut client = RemoteClient::new(remote_config(matches, config)).unwrap_or_else(|e| {
+        println!("{}", e);
+        process::exit(1);
+    });
+    client.set_host_details(host_details(db));
+    match client.enroll() {
+        Ok(_) => println!("enrolled as {}", client.host_identifier()),
+        Err(e) => {
+            println!("enroll: {}", e);
+            process::exit(1);
+        }
+    }
+    let seconds = |name: &str, configured: Option<u64>, default: u64| {
+        Duration::from_secs(value_t!(matches, name, u64).ok().or(configured).unwrap_or(default).max(1))
+    };
+    let distributed_interval = seconds("distributed_interval", config.remote.distributed_interval, 60);
+    let config_refresh = seconds("config_refresh", config.remote.config_refresh, 300);
+    let once = matches.is_present("once");
+
+    let mut queries: Vec<ScheduledQuery> = Vec::new();
+    let mut last_runs: HashMap<String, Instant> = HashMap::new();
+    let mut watchdog = Watchdog::new(watchdog_limits());
+    let mut next_config = Instant::now();
+    let mut next_distributed = Instant::now();
+    loop {
+        let now = Instant::now();
+        if now >= next_config {
+            if let Some(schedule) = refresh_schedule(&mut client) {
+                queries = schedule;
+            }
+            next_config = now + config_refresh;
+        }
+        for query in queries.iter() {
+            let due = last_runs.get(&query.name)
+                .map(|last| now.duration_since(*last) >= Duration::from_secs(query.interval))
+                .unwrap_or(true);
+            if !due {
+                continue;
+            }
+            last_runs.insert(query.name.clone(), now);
+            match watchdog.run(&query.name, || execute_query_json(db, &query.query, &[])) {
+                Some(Ok(rows)) => {
+                    if let Err(e) = client.log_snapshot(&query.name, &rows) {
+                        println!("{}: {}", query.name, e);
+                    }
+                },
+                Some(Err(e)) => println!("{}: {}", query.name, e),
+                None => {},
+            }
+        }
+        for warning in take_warnings() {
+            eprintln!("warning: {}", warning);
+        }
+        if now >= next_distributed {
+            match client.run_distributed(db) {
+                Ok(0) => {},
+                Ok(count) => println!("answered {} distributed queries", count),
+                Err(e) => println!("distributed: {}", e),
+            }
+            next_distributed = now + distributed_interval;
+        }
+        if once {
+            break;
+        }
+        thread::sleep(Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,18 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Generates `siquery.h` in `OUT_DIR`. The copy in `include/` is committed, and the
+/// `test_header_up_to_date` test checks it matches.
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+    cbindgen::Builder::new()
+        .with_crate(&crate_dir)
+        .with_config(config)
+        .generate()
+        .expect("unable to generate siquery.h")
+        .write_to_file(out_dir.join("siquery.h"));
+}
@@ -0,0 +1,136 @@
+#ifndef SIQUERY_H
+#define SIQUERY_H
+
+/* Generated by cbindgen from siquery_ffi, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Type of a value, following SQLite's fundamental datatypes.
+ */
+typedef enum SiqueryType {
+  SIQUERY_TYPE_NULL = 0,
+  SIQUERY_TYPE_INTEGER = 1,
+  SIQUERY_TYPE_REAL = 2,
+  SIQUERY_TYPE_TEXT = 3,
+  SIQUERY_TYPE_BLOB = 4,
+} SiqueryType;
+
+typedef struct SiqueryConnection SiqueryConnection;
+
+typedef struct SiqueryResult SiqueryResult;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Opens a connection with every table available on this platform.
+ *
+ * Returns `NULL` on failure. Release it with `siquery_close`.
+ */
+struct SiqueryConnection *siquery_open(void);
+
+/**
+ * Opens a connection exposing only the `count` tables named in `tables`.
+ */
+struct SiqueryConnection *siquery_open_with_tables(const char *const *tables, size_t count);
+
+/**
+ * Closes a connection. Results obtained from it stay valid.
+ */
+void siquery_close(struct SiqueryConnection *connection);
+
+/**
+ * Runs `sql` and returns all of its rows, or `NULL` on error.
+ *
+ * Release the result with `siquery_result_free`.
+ */
+struct SiqueryResult *siquery_query(struct SiqueryConnection *connection, const char *sql);
+
+/**
+ * Number of tables of the connection.
+ */
+size_t siquery_table_count(const struct SiqueryConnection *connection);
+
+/**
+ * Name of table `index`, sorted by name, or `NULL` when out of range.
+ */
+const char *siquery_table_name(const struct SiqueryConnection *connection, size_t index);
+
+/**
+ * `CREATE TABLE` statement describing the columns of `table`, or `NULL` for an unknown table.
+ *
+ * Release the string with `siquery_string_free`.
+ */
+char *siquery_table_schema(const char *table);
+
+/**
+ * Releases a string returned by the API.
+ */
+void siquery_string_free(char *s);
+
+/**
+ * Message of the last error on this thread, or `NULL` when the last call succeeded.
+ *
+ * The string stays valid until the next call on the same thread.
+ */
+const char *siquery_last_error(void);
+
+void siquery_result_free(struct SiqueryResult *result);
+
+size_t siquery_result_row_count(const struct SiqueryResult *result);
+
+size_t siquery_result_column_count(const struct SiqueryResult *result);
+
+/**
+ * Name of column `column`, or `NULL` when out of range.
+ */
+const char *siquery_result_column_name(const struct SiqueryResult *result, size_t column);
+
+/**
+ * Moves to the next row. Returns 1 when there is one, 0 after the last row and -1 on error.
+ */
+int siquery_result_next(struct SiqueryResult *result);
+
+/**
+ * Type of column `column` in the current row.
+ */
+enum SiqueryType siquery_result_type(const struct SiqueryResult *result, size_t column);
+
+/**
+ * Column `column` of the current row as an integer, converted like `sqlite3_column_int64`.
+ */
+int64_t siquery_result_int64(const struct SiqueryResult *result, size_t column);
+
+/**
+ * Column `column` of the current row as a double, converted like `sqlite3_column_double`.
+ */
+double siquery_result_double(const struct SiqueryResult *result, size_t column);
+
+/**
+ * Column `column` of the current row as text, or `NULL` for a NULL value or on error.
+ *
+ * Numbers are formatted and blobs are cut at their first NUL byte. The string stays valid
+ * until the next call to `siquery_result_next`.
+ */
+const char *siquery_result_text(struct SiqueryResult *result, size_t column);
+
+/**
+ * Column `column` of the current row as bytes, storing their number in `length`.
+ *
+ * Returns `NULL` for a NULL value or an empty blob. The bytes stay valid until the result is freed.
+ */
+const uint8_t *siquery_result_blob(const struct SiqueryResult *result,
+                                   size_t column,
+                                   size_t *length);
+
+#ifdef __cplusplus
+} // extern "C"
+#endif // __cplusplus
+
+#endif /* SIQUERY_H */
@@ -0,0 +1,389 @@
+//! C API for embedding siquery.
+//!
+//! A `SiqueryConnection` wraps the in-memory SQLite connection returned by `init_db`, with every
+//! table registered through the siquery virtual table module. Queries return a `SiqueryResult`
+//! holding all rows, walked with `siquery_result_next` and read with the typed getters.
+//!
+//! Functions that fail return `NULL` or a negative value and leave a message for
+//! `siquery_last_error`. Strings returned by the API are owned by the object they come from,
+//! unless the function says they must be released with `siquery_string_free`.
+//!
+//! # Safety
+//!
+//! Pointers passed to the API must be `NULL` or come from the API and not have been released.
+//! Strings must be NUL terminated. A connection and its results must not be used from two
+//! threads at the same time.
+
+#![allow(clippy::missing_safety_doc)]
+
+use libc::{c_char, c_int, size_t};
+use rusqlite::{Connection, NO_PARAMS};
+use rusqlite::types::Value;
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+
+use siquery::query::{get_schema, init_db, init_db_with_tables};
+use siquery::tables::get_table_list;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn clear_last_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+/// Runs `f`, turning a panic into an error so it does not unwind into C.
+fn guard<T, F: FnOnce() -> Result<T, String>>(f: F) -> Option<T> {
+    clear_last_error();
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Some(value),
+        Ok(Err(message)) => {
+            set_last_error(message);
+            None
+        },
+        Err(cause) => {
+            let message = cause.downcast_ref::<&str>().map(|s| s.to_string())
+                .or_else(|| cause.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "unknown error".to_string());
+            set_last_error(format!("panic: {}", message));
+            None
+        }
+    }
+}
+
+unsafe fn to_str<'a>(s: *const c_char, name: &str) -> Result<&'a str, String> {
+    if s.is_null() {
+        return Err(format!("{} is NULL", name));
+    }
+    CStr::from_ptr(s).to_str().map_err(|e| format!("{}: {}", name, e))
+}
+
+/// Type of a value, following SQLite's fundamental datatypes.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SiqueryType {
+    Null = 0,
+    Integer = 1,
+    Real = 2,
+    Text = 3,
+    Blob = 4,
+}
+
+pub struct SiqueryConnection {
+    db: Connection,
+    tables: Vec<CString>,
+}
+
+pub struct SiqueryResult {
+    columns: Vec<CString>,
+    rows: Vec<Vec<Value>>,
+    /// Index of the current row plus one, 0 before the first call to `siquery_result_next`.
+    position: usize,
+    /// Text of the current row's columns, converted when first asked for.
+    text: Vec<Option<CString>>,
+}
+
+impl SiqueryConnection {
+    fn new(db: Connection) -> Result<SiqueryConnection, String> {
+        let tables = {
+            let mut statement = db.prepare("SELECT name FROM sqlite_master WHERE type = 'table' ORDER BY name")
+                .map_err(|e| e.to_string())?;
+            let names = statement.query_map(NO_PARAMS, |row| row.get::<usize, String>(0))
+                .map_err(|e| e.to_string())?;
+            let names: Result<Vec<String>, _> = names.collect();
+            names.map_err(|e| e.to_string())?
+                .into_iter()
+                .filter_map(|name| CString::new(name).ok())
+                .collect()
+        };
+        Ok(SiqueryConnection { db, tables })
+    }
+
+    fn query(&self, sql: &str) -> Result<SiqueryResult, rusqlite::Error> {
+        let mut statement = self.db.prepare(sql)?;
+        let columns: Vec<CString> = statement.column_names().iter()
+            .map(|name| CString::new(name.replace('\0', " ")).unwrap_or_default())
+            .collect();
+        let mut response = statement.query(NO_PARAMS)?;
+        let mut rows = Vec::new();
+        while let Some(res) = response.next()? {
+            let mut values = Vec::with_capacity(columns.len());
+            for i in 0..columns.len() {
+                values.push(res.get::<usize, Value>(i)?);
+            }
+            rows.push(values);
+        }
+        Ok(SiqueryResult { columns, rows, position: 0, text: Vec::new() })
+    }
+}
+
+impl SiqueryResult {
+    fn value(&self, column: usize) -> Result<&Value, String> {
+        if self.position == 0 || self.position > self.rows.len() {
+            return Err("no current row, call siquery_result_next first".to_string());
+        }
+        self.rows[self.position - 1].get(column)
+            .ok_or_else(|| format!("column index {} out of range", column))
+    }
+}
+
+/// Opens a connection with every table available on this platform.
+///
+/// Returns `NULL` on failure. Release it with `siquery_close`.
+#[no_mangle]
+pub extern "C" fn siquery_open() -> *mut SiqueryConnection {
+    guard(|| SiqueryConnection::new(init_db()))
+        .map(|connection| Box::into_raw(Box::new(connection)))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Opens a connection exposing only the `count` tables named in `tables`.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_open_with_tables(tables: *const *const c_char, count: size_t)
+                                                  -> *mut SiqueryConnection {
+    guard(|| {
+        if tables.is_null() && count > 0 {
+            return Err("tables is NULL".to_string());
+        }
+        let mut names = Vec::with_capacity(count);
+        for i in 0..count {
+            names.push(to_str(*tables.add(i), "table name")?.to_string());
+        }
+        SiqueryConnection::new(init_db_with_tables(names))
+    })
+        .map(|connection| Box::into_raw(Box::new(connection)))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Closes a connection. Results obtained from it stay valid.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_close(connection: *mut SiqueryConnection) {
+    if !connection.is_null() {
+        drop(Box::from_raw(connection));
+    }
+}
+
+/// Runs `sql` and returns all of its rows, or `NULL` on error.
+///
+/// Release the result with `siquery_result_free`.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_query(connection: *mut SiqueryConnection, sql: *const c_char)
+                                       -> *mut SiqueryResult {
+    guard(|| {
+        let connection = connection.as_ref().ok_or_else(|| "connection is NULL".to_string())?;
+        let sql = to_str(sql, "sql")?;
+        connection.query(sql).map_err(|e| e.to_string())
+    })
+        .map(|result| Box::into_raw(Box::new(result)))
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Number of tables of the connection.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_table_count(connection: *const SiqueryConnection) -> size_t {
+    connection.as_ref().map(|connection| connection.tables.len()).unwrap_or(0)
+}
+
+/// Name of table `index`, sorted by name, or `NULL` when out of range.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_table_name(connection: *const SiqueryConnection, index: size_t)
+                                            -> *const c_char {
+    connection.as_ref()
+        .and_then(|connection| connection.tables.get(index))
+        .map(|name| name.as_ptr())
+        .unwrap_or(ptr::null())
+}
+
+/// `CREATE TABLE` statement describing the columns of `table`, or `NULL` for an unknown table.
+///
+/// Release the string with `siquery_string_free`.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_table_schema(table: *const c_char) -> *mut c_char {
+    guard(|| {
+        let table = to_str(table, "table")?;
+        // get_schema answers with a placeholder schema for names it does not know.
+        if !get_table_list().iter().any(|name| name == table) {
+            return Err(format!("no such table: {}", table));
+        }
+        let schema = get_schema(table).ok_or_else(|| format!("no such table: {}", table))?;
+        CString::new(schema).map_err(|e| e.to_string())
+    })
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+/// Releases a string returned by the API.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_string_free(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Message of the last error on this thread, or `NULL` when the last call succeeded.
+///
+/// The string stays valid until the next call on the same thread.
+#[no_mangle]
+pub extern "C" fn siquery_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map(|message| message.as_ptr()).unwrap_or(ptr::null()))
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_free(result: *mut SiqueryResult) {
+    if !result.is_null() {
+        drop(Box::from_raw(result));
+    }
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_row_count(result: *const SiqueryResult) -> size_t {
+    result.as_ref().map(|result| result.rows.len()).unwrap_or(0)
+}
+
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_column_count(result: *const SiqueryResult) -> size_t {
+    result.as_ref().map(|result| result.columns.len()).unwrap_or(0)
+}
+
+/// Name of column `column`, or `NULL` when out of range.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_column_name(result: *const SiqueryResult, column: size_t)
+                                                    -> *const c_char {
+    result.as_ref()
+        .and_then(|result| result.columns.get(column))
+        .map(|name| name.as_ptr())
+        .unwrap_or(ptr::null())
+}
+
+/// Moves to the next row. Returns 1 when there is one, 0 after the last row and -1 on error.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_next(result: *mut SiqueryResult) -> c_int {
+    let result = match result.as_mut() {
+        Some(result) => result,
+        None => {
+            set_last_error("result is NULL".to_string());
+            return -1;
+        }
+    };
+    clear_last_error();
+    if result.position < result.rows.len() {
+        result.position += 1;
+        result.text = vec![None; result.columns.len()];
+        1
+    } else {
+        result.position = result.rows.len() + 1;
+        0
+    }
+}
+
+/// Type of column `column` in the current row.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_type(result: *const SiqueryResult, column: size_t) -> SiqueryType {
+    let value = result.as_ref().ok_or_else(|| "result is NULL".to_string()).and_then(|result| result.value(column));
+    match value {
+        Ok(Value::Null) => SiqueryType::Null,
+        Ok(Value::Integer(_)) => SiqueryType::Integer,
+        Ok(Value::Real(_)) => SiqueryType::Real,
+        Ok(Value::Text(_)) => SiqueryType::Text,
+        Ok(Value::Blob(_)) => SiqueryType::Blob,
+        Err(e) => {
+            set_last_error(e);
+            SiqueryType::Null
+        }
+    }
+}
+
+/// Column `column` of the current row as an integer, converted like `sqlite3_column_int64`.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_int64(result: *const SiqueryResult, column: size_t) -> i64 {
+    let value = result.as_ref().ok_or_else(|| "result is NULL".to_string()).and_then(|result| result.value(column));
+    match value {
+        Ok(Value::Integer(i)) => *i,
+        Ok(Value::Real(f)) => *f as i64,
+        Ok(Value::Text(s)) => s.trim().parse::<i64>().ok()
+            .or_else(|| s.trim().parse::<f64>().ok().map(|f| f as i64))
+            .unwrap_or(0),
+        Ok(_) => 0,
+        Err(e) => {
+            set_last_error(e);
+            0
+        }
+    }
+}
+
+/// Column `column` of the current row as a double, converted like `sqlite3_column_double`.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_double(result: *const SiqueryResult, column: size_t) -> f64 {
+    let value = result.as_ref().ok_or_else(|| "result is NULL".to_string()).and_then(|result| result.value(column));
+    match value {
+        Ok(Value::Integer(i)) => *i as f64,
+        Ok(Value::Real(f)) => *f,
+        Ok(Value::Text(s)) => s.trim().parse::<f64>().unwrap_or(0.0),
+        Ok(_) => 0.0,
+        Err(e) => {
+            set_last_error(e);
+            0.0
+        }
+    }
+}
+
+/// Column `column` of the current row as text, or `NULL` for a NULL value or on error.
+///
+/// Numbers are formatted and blobs are cut at their first NUL byte. The string stays valid
+/// until the next call to `siquery_result_next`.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_text(result: *mut SiqueryResult, column: size_t) -> *const c_char {
+    let result = match result.as_mut() {
+        Some(result) => result,
+        None => {
+            set_last_error("result is NULL".to_string());
+            return ptr::null();
+        }
+    };
+    let text = match result.value(column) {
+        Ok(Value::Null) => return ptr::null(),
+        Ok(Value::Integer(i)) => i.to_string().into_bytes(),
+        Ok(Value::Real(f)) => f.to_string().into_bytes(),
+        Ok(Value::Text(s)) => s.clone().into_bytes(),
+        Ok(Value::Blob(b)) => b.clone(),
+        Err(e) => {
+            set_last_error(e);
+            return ptr::null();
+        }
+    };
+    let text = result.text[column].get_or_insert_with(|| {
+        let end = text.iter().position(|b| *b == 0).unwrap_or(text.len());
+        CString::new(&text[..end]).unwrap_or_default()
+    });
+    text.as_ptr()
+}
+
+/// Column `column` of the current row as bytes, storing their number in `length`.
+///
+/// Returns `NULL` for a NULL value or an empty blob. The bytes stay valid until the result is freed.
+#[no_mangle]
+pub unsafe extern "C" fn siquery_result_blob(result: *const SiqueryResult, column: size_t, length: *mut size_t)
+                                             -> *const u8 {
+    let value = result.as_ref().ok_or_else(|| "result is NULL".to_string()).and_then(|result| result.value(column));
+    let bytes: &[u8] = match value {
+        Ok(Value::Text(s)) => s.as_bytes(),
+        Ok(Value::Blob(b)) => b,
+        Ok(_) => &[],
+        Err(e) => {
+            set_last_error(e);
+            &[]
+        }
+    };
+    if !length.is_null() {
+        *length = bytes.len();
+    }
+    if bytes.is_empty() { ptr::null() } else { bytes.as_ptr() }
+}
@@ -0,0 +1,47 @@
+//! Builds `harness.c` against the generated header and the cdylib, then runs it.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// Directory holding the cdylib built for this test, `target/<profile>/deps` next to the test binary.
+fn library_dir() -> PathBuf {
+    env::current_exe().unwrap().parent().unwrap().to_path_buf()
+}
+
+/// The committed header is the one cbindgen generates from the current sources.
+#[test]
+fn test_header_up_to_date() {
+    let generated = PathBuf::from(env!("OUT_DIR")).join("siquery.h");
+    let committed = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("include").join("siquery.h");
+    let read = |path: &PathBuf| std::fs::read_to_string(path).unwrap().replace("\r\n", "\n");
+    assert!(read(&generated) == read(&committed), "{} is out of date, copy {} over it",
+            committed.display(), generated.display());
+}
+
+#[test]
+#[cfg(unix)]
+fn test_c_harness() {
+    let crate_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let target = library_dir();
+    let harness = target.join("siquery_ffi_harness");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .arg(crate_dir.join("tests").join("harness.c"))
+        .arg("-std=c99")
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I").arg(crate_dir.join("include"))
+        .arg("-L").arg(&target)
+        .arg(format!("-Wl,-rpath,{}", target.display()))
+        .arg("-lsiquery_ffi")
+        .arg("-o").arg(&harness)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(status.success(), "failed to build harness.c");
+
+    let output = Command::new(&harness).output().unwrap();
+    assert!(output.status.success(), "harness failed:\n{}{}",
+            String::from_utf8_lossy(&output.stdout), String::from_utf8_lossy(&output.stderr));
+    assert!(String::from_utf8_lossy(&output.stdout).ends_with("ok\n"));
+}
@@ -0,0 +1,106 @@
+/* Exercises the C API the way an embedding agent would. Exits non-zero on the first failure. */
+#include <stdio.h>
+#include <string.h>
+#include "siquery.h"
+
+#define CHECK(cond) do { \
+    if (!(cond)) { \
+        const char *error = siquery_last_error(); \
+        fprintf(stderr, "%s:%d: check failed: %s (%s)\n", __FILE__, __LINE__, #cond, error ? error : "no error"); \
+        return 1; \
+    } \
+} while (0)
+
+static int test_values(SiqueryConnection *connection) {
+    SiqueryResult *result = siquery_query(connection, "SELECT 42 AS i, 2.5 AS r, 'text' AS t, NULL AS n, x'00ff' AS b");
+    CHECK(result != NULL);
+    CHECK(siquery_result_row_count(result) == 1);
+    CHECK(siquery_result_column_count(result) == 5);
+    CHECK(strcmp(siquery_result_column_name(result, 2), "t") == 0);
+    CHECK(siquery_result_column_name(result, 5) == NULL);
+
+    CHECK(siquery_result_next(result) == 1);
+    CHECK(siquery_result_type(result, 0) == SIQUERY_TYPE_INTEGER);
+    CHECK(siquery_result_int64(result, 0) == 42);
+    CHECK(strcmp(siquery_result_text(result, 0), "42") == 0);
+    CHECK(siquery_result_type(result, 1) == SIQUERY_TYPE_REAL);
+    CHECK(siquery_result_double(result, 1) == 2.5);
+    CHECK(siquery_result_type(result, 2) == SIQUERY_TYPE_TEXT);
+    CHECK(strcmp(siquery_result_text(result, 2), "text") == 0);
+    CHECK(siquery_result_type(result, 3) == SIQUERY_TYPE_NULL);
+    CHECK(siquery_result_text(result, 3) == NULL);
+    CHECK(siquery_result_type(result, 4) == SIQUERY_TYPE_BLOB);
+    size_t length = 0;
+    const uint8_t *blob = siquery_result_blob(result, 4, &length);
+    CHECK(length == 2 && blob[0] == 0x00 && blob[1] == 0xff);
+
+    siquery_result_int64(result, 9);
+    CHECK(siquery_last_error() != NULL);
+    CHECK(siquery_result_next(result) == 0);
+    CHECK(siquery_result_next(result) == 0);
+    siquery_result_free(result);
+    return 0;
+}
+
+static int test_tables(SiqueryConnection *connection) {
+    size_t count = siquery_table_count(connection);
+    int found = 0;
+    CHECK(count > 0);
+    for (size_t i = 0; i < count; i++) {
+        const char *name = siquery_table_name(connection, i);
+        CHECK(name != NULL);
+        found |= strcmp(name, "os_version") == 0;
+    }
+    CHECK(found);
+    CHECK(siquery_table_name(connection, count) == NULL);
+
+    char *schema = siquery_table_schema("os_version");
+    CHECK(schema != NULL);
+    CHECK(strstr(schema, "CREATE TABLE") != NULL);
+    siquery_string_free(schema);
+    CHECK(siquery_table_schema("no_such_table") == NULL);
+    CHECK(strstr(siquery_last_error(), "no_such_table") != NULL);
+
+    SiqueryResult *result = siquery_query(connection, "SELECT name FROM os_version");
+    CHECK(result != NULL);
+    CHECK(siquery_result_next(result) == 1);
+    CHECK(siquery_result_type(result, 0) == SIQUERY_TYPE_TEXT);
+    siquery_result_free(result);
+    return 0;
+}
+
+static int test_errors(SiqueryConnection *connection) {
+    CHECK(siquery_query(connection, "SELECT * FROM no_such_table") == NULL);
+    CHECK(strstr(siquery_last_error(), "no_such_table") != NULL);
+    CHECK(siquery_query(NULL, "SELECT 1") == NULL);
+    CHECK(siquery_query(connection, NULL) == NULL);
+
+    SiqueryResult *result = siquery_query(connection, "SELECT 1");
+    CHECK(siquery_last_error() == NULL);
+    CHECK(siquery_result_text(result, 0) == NULL);
+    CHECK(siquery_last_error() != NULL);
+    siquery_result_free(result);
+    return 0;
+}
+
+static int test_subset(void) {
+    const char *tables[] = { "os_version", "uptime" };
+    SiqueryConnection *connection = siquery_open_with_tables(tables, 2);
+    CHECK(connection != NULL);
+    CHECK(siquery_table_count(connection) == 2);
+    CHECK(strcmp(siquery_table_name(connection, 1), "uptime") == 0);
+    CHECK(siquery_query(connection, "SELECT * FROM processes") == NULL);
+    siquery_close(connection);
+    return 0;
+}
+
+int main(void) {
+    SiqueryConnection *connection = siquery_open();
+    CHECK(connection != NULL);
+    if (test_values(connection) || test_tables(connection) || test_errors(connection) || test_subset()) {
+        return 1;
+    }
+    siquery_close(connection);
+    printf("ok\n");
+    return 0;
+}